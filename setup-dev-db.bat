@@ -27,6 +27,74 @@ sqlite3 "%DB_FILE%" < src-tauri/migrations/003_add_youth_member_age_limit.sql
 REM Apply fourth migration
 sqlite3 "%DB_FILE%" < src-tauri/migrations/004_update_member_type_constraints.sql
 
+REM Apply fifth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/005_add_fee_rules.sql
+
+REM Apply sixth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/006_add_member_types.sql
+
+REM Apply seventh migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/007_add_fee_items.sql
+
+REM Apply eighth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/008_add_invoice_message.sql
+
+REM Apply ninth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/009_add_invoice_editing.sql
+
+REM Apply tenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/010_add_billing_periods.sql
+
+REM Apply eleventh migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/011_add_invoice_batches.sql
+
+REM Apply twelfth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/012_add_billing_mode.sql
+
+REM Apply thirteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/013_add_member_guardians.sql
+
+REM Apply fourteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/014_add_customers.sql
+
+REM Apply fifteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/015_add_corporate_members.sql
+
+REM Apply sixteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/016_add_member_type_rules.sql
+
+REM Apply seventeenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/017_add_account_mappings.sql
+
+REM Apply eighteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/018_add_tilitin_exports.sql
+
+REM Apply nineteenth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/019_add_write_offs.sql
+
+REM Apply twentieth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/020_add_household_credits.sql
+
+REM Apply twenty-first migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/021_add_member_resignation.sql
+
+REM Apply twenty-second migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/022_add_report_definitions.sql
+
+REM Apply twenty-third migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/023_add_tilitin_export_database.sql
+
+REM Apply twenty-fourth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/024_add_invoice_payer.sql
+
+REM Apply twenty-fifth migration
+sqlite3 "%DB_FILE%" < src-tauri/migrations/025_add_unique_invoice_number.sql
+
+REM Record the last applied migration so the app does not run them again
+for %%f in (src-tauri\migrations\*.sql) do set LAST_MIGRATION=%%~nf
+set /a MIGRATION_VERSION=1%LAST_MIGRATION:~0,3% - 1000
+sqlite3 "%DB_FILE%" "PRAGMA user_version = %MIGRATION_VERSION%;"
+
 echo Development database created successfully!
 echo Location: %CD%\%DB_FILE%
 
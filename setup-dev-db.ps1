@@ -36,6 +36,73 @@ try {
     # Apply fourth migration
     Get-Content "src-tauri/migrations/004_update_member_type_constraints.sql" | sqlite3 $DB_FILE
     
+    # Apply fifth migration
+    Get-Content "src-tauri/migrations/005_add_fee_rules.sql" | sqlite3 $DB_FILE
+    
+    # Apply sixth migration
+    Get-Content "src-tauri/migrations/006_add_member_types.sql" | sqlite3 $DB_FILE
+    
+    # Apply seventh migration
+    Get-Content "src-tauri/migrations/007_add_fee_items.sql" | sqlite3 $DB_FILE
+    
+    # Apply eighth migration
+    Get-Content "src-tauri/migrations/008_add_invoice_message.sql" | sqlite3 $DB_FILE
+    
+    # Apply ninth migration
+    Get-Content "src-tauri/migrations/009_add_invoice_editing.sql" | sqlite3 $DB_FILE
+    
+    # Apply tenth migration
+    Get-Content "src-tauri/migrations/010_add_billing_periods.sql" | sqlite3 $DB_FILE
+    
+    # Apply eleventh migration
+    Get-Content "src-tauri/migrations/011_add_invoice_batches.sql" | sqlite3 $DB_FILE
+    
+    # Apply twelfth migration
+    Get-Content "src-tauri/migrations/012_add_billing_mode.sql" | sqlite3 $DB_FILE
+    
+    # Apply thirteenth migration
+    Get-Content "src-tauri/migrations/013_add_member_guardians.sql" | sqlite3 $DB_FILE
+    
+    # Apply fourteenth migration
+    Get-Content "src-tauri/migrations/014_add_customers.sql" | sqlite3 $DB_FILE
+    
+    # Apply fifteenth migration
+    Get-Content "src-tauri/migrations/015_add_corporate_members.sql" | sqlite3 $DB_FILE
+    
+    # Apply sixteenth migration
+    Get-Content "src-tauri/migrations/016_add_member_type_rules.sql" | sqlite3 $DB_FILE
+    
+    # Apply seventeenth migration
+    Get-Content "src-tauri/migrations/017_add_account_mappings.sql" | sqlite3 $DB_FILE
+    
+    # Apply eighteenth migration
+    Get-Content "src-tauri/migrations/018_add_tilitin_exports.sql" | sqlite3 $DB_FILE
+    
+    # Apply nineteenth migration
+    Get-Content "src-tauri/migrations/019_add_write_offs.sql" | sqlite3 $DB_FILE
+    
+    # Apply twentieth migration
+    Get-Content "src-tauri/migrations/020_add_household_credits.sql" | sqlite3 $DB_FILE
+    
+    # Apply twenty-first migration
+    Get-Content "src-tauri/migrations/021_add_member_resignation.sql" | sqlite3 $DB_FILE
+    
+    # Apply twenty-second migration
+    Get-Content "src-tauri/migrations/022_add_report_definitions.sql" | sqlite3 $DB_FILE
+    
+    # Apply twenty-third migration
+    Get-Content "src-tauri/migrations/023_add_tilitin_export_database.sql" | sqlite3 $DB_FILE
+    
+    # Apply twenty-fourth migration
+    Get-Content "src-tauri/migrations/024_add_invoice_payer.sql" | sqlite3 $DB_FILE
+    
+    # Apply twenty-fifth migration
+    Get-Content "src-tauri/migrations/025_add_unique_invoice_number.sql" | sqlite3 $DB_FILE
+    
+    # Record the last applied migration so the app does not run them again
+    $lastMigration = Get-ChildItem "src-tauri/migrations/*.sql" | Sort-Object Name | Select-Object -Last 1
+    sqlite3 $DB_FILE "PRAGMA user_version = $([int]$lastMigration.Name.Substring(0, 3));"
+    
     Write-Host "Development database created successfully!" -ForegroundColor Green
     Write-Host "Location: $(Get-Location)\$DB_FILE" -ForegroundColor Cyan
     
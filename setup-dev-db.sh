@@ -28,6 +28,73 @@ sqlite3 "$DB_FILE" < src-tauri/migrations/003_add_youth_member_age_limit.sql
 # Apply fourth migration
 sqlite3 "$DB_FILE" < src-tauri/migrations/004_update_member_type_constraints.sql
 
+# Apply fifth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/005_add_fee_rules.sql
+
+# Apply sixth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/006_add_member_types.sql
+
+# Apply seventh migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/007_add_fee_items.sql
+
+# Apply eighth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/008_add_invoice_message.sql
+
+# Apply ninth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/009_add_invoice_editing.sql
+
+# Apply tenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/010_add_billing_periods.sql
+
+# Apply eleventh migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/011_add_invoice_batches.sql
+
+# Apply twelfth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/012_add_billing_mode.sql
+
+# Apply thirteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/013_add_member_guardians.sql
+
+# Apply fourteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/014_add_customers.sql
+
+# Apply fifteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/015_add_corporate_members.sql
+
+# Apply sixteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/016_add_member_type_rules.sql
+
+# Apply seventeenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/017_add_account_mappings.sql
+
+# Apply eighteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/018_add_tilitin_exports.sql
+
+# Apply nineteenth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/019_add_write_offs.sql
+
+# Apply twentieth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/020_add_household_credits.sql
+
+# Apply twenty-first migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/021_add_member_resignation.sql
+
+# Apply twenty-second migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/022_add_report_definitions.sql
+
+# Apply twenty-third migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/023_add_tilitin_export_database.sql
+
+# Apply twenty-fourth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/024_add_invoice_payer.sql
+
+# Apply twenty-fifth migration
+sqlite3 "$DB_FILE" < src-tauri/migrations/025_add_unique_invoice_number.sql
+
+# Record the last applied migration so the app does not run them again
+LAST_MIGRATION=$(ls src-tauri/migrations/*.sql | tail -n 1 | xargs basename | cut -c1-3)
+sqlite3 "$DB_FILE" "PRAGMA user_version = $((10#$LAST_MIGRATION));"
+
 echo "Development database created successfully!"
 echo "Location: $(pwd)/$DB_FILE"
 
@@ -4,6 +4,6 @@ ALTER TABLE households ADD COLUMN vastaanottaja TEXT;
 -- Lisää laskunumero laskulle (erillinen kenttä viitenumerosta)
 ALTER TABLE invoices ADD COLUMN laskunumero TEXT;
 
--- Päivitä esimerkkidata
-UPDATE households SET vastaanottaja = 'Matti Korhonen' WHERE id = 1;
-UPDATE households SET vastaanottaja = 'Testi Henkilö' WHERE id = 2;
\ No newline at end of file
+-- Päivitä esimerkkidata; jo asetettua vastaanottajaa ei korvata
+UPDATE households SET vastaanottaja = 'Matti Korhonen' WHERE id = 1 AND vastaanottaja IS NULL;
+UPDATE households SET vastaanottaja = 'Testi Henkilö' WHERE id = 2 AND vastaanottaja IS NULL;
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Maksusäännöt (fee_rules): talouskohtaiset perhealennukset
+-- katto       = enintään N täyttä jäsenmaksua taloutta kohden, muut maksutta
+-- nnes_jasen  = N:nnestä jäsenestä alkaen prosenttialennus
+-- perhemaksu  = kiinteä maksu taloudelle, jossa vähintään N laskutettavaa jäsentä
+CREATE TABLE IF NOT EXISTS fee_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL,
+    saantotyyppi TEXT NOT NULL CHECK (saantotyyppi IN ('katto', 'nnes_jasen', 'perhemaksu')),
+    vuosi INTEGER,
+    taydet_maksut INTEGER,
+    jasen_nro INTEGER,
+    alennus_prosentti DECIMAL(5,2),
+    summa DECIMAL(10,2),
+    vahimmaisjasenet INTEGER,
+    aktiivinen BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_fee_rules_vuosi ON fee_rules(vuosi);
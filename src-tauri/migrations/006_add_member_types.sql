@@ -0,0 +1,87 @@
+-- Jäsentyypit tauluun kiinteän CHECK-rajoitteen sijaan
+
+-- SQLite ei salli sarakkeen rajoitteen poistamista, joten members ja
+-- membership_fees rakennetaan uudelleen. Migraatioajo kytkee vierasavaimet pois
+-- päältä, ettei members-taulun pudotus kaadu invoice_lines-viittauksiin.
+
+-- Aiemman 004-ajon mahdollisesti jättämä välitaulu
+DROP TABLE IF EXISTS members_new;
+DROP TABLE IF EXISTS membership_fees_new;
+
+-- Jäsentyypit (member_types)
+CREATE TABLE IF NOT EXISTS member_types (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL UNIQUE,
+    kuvaus TEXT,
+    ika_min INTEGER,
+    ika_max INTEGER,
+    laskutettava BOOLEAN NOT NULL DEFAULT 1,
+    seuraava_tyyppi TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Nykyiset jäsentyypit; nuorisojäsenen yläikäraja yhdistyksen asetuksista
+INSERT OR IGNORE INTO member_types (nimi, kuvaus, ika_min, ika_max, laskutettava, seuraava_tyyppi) VALUES
+('varsinainen', 'Varsinainen jäsen', NULL, NULL, 1, NULL),
+('nuorisojasen', 'Nuorisojäsen', NULL,
+    (SELECT COALESCE(MAX(nuorisojasen_ikaraja), 18) - 1 FROM organization), 1, 'varsinainen'),
+('kannatus', 'Kannatusjäsen', NULL, NULL, 1, NULL),
+('kunnia', 'Kunniajäsen', NULL, NULL, 0, NULL);
+
+-- Muut tietokannassa jo käytetyt tyypit, ettei vierasavain katkea
+INSERT OR IGNORE INTO member_types (nimi) SELECT DISTINCT jasentyyppi FROM members;
+INSERT OR IGNORE INTO member_types (nimi) SELECT DISTINCT jasentyyppi FROM membership_fees;
+
+-- Jäsenet (members) ilman CHECK-rajoitetta
+CREATE TABLE members_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    etunimi TEXT NOT NULL,
+    sukunimi TEXT NOT NULL,
+    henkilotunnus TEXT,
+    syntymaaika DATE,
+    puhelinnumero TEXT,
+    sahkoposti TEXT,
+    osoite_id INTEGER NOT NULL,
+    liittymispaiva DATE NOT NULL,
+    jasentyyppi TEXT NOT NULL,
+    aktiivinen BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (osoite_id) REFERENCES addresses(id),
+    FOREIGN KEY (jasentyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE
+);
+
+INSERT INTO members_new (id, etunimi, sukunimi, henkilotunnus, syntymaaika, puhelinnumero,
+    sahkoposti, osoite_id, liittymispaiva, jasentyyppi, aktiivinen, created_at, updated_at)
+SELECT id, etunimi, sukunimi, henkilotunnus, syntymaaika, puhelinnumero,
+    sahkoposti, osoite_id, liittymispaiva, jasentyyppi, aktiivinen, created_at, updated_at
+FROM members;
+
+DROP TABLE members;
+ALTER TABLE members_new RENAME TO members;
+
+CREATE INDEX IF NOT EXISTS idx_members_osoite ON members(osoite_id);
+CREATE INDEX IF NOT EXISTS idx_members_jasentyyppi ON members(jasentyyppi);
+CREATE INDEX IF NOT EXISTS idx_members_aktiivinen ON members(aktiivinen);
+
+-- Jäsenmaksut (membership_fees) ilman CHECK-rajoitetta
+CREATE TABLE membership_fees_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    vuosi INTEGER NOT NULL,
+    jasentyyppi TEXT NOT NULL,
+    summa DECIMAL(10,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(vuosi, jasentyyppi),
+    FOREIGN KEY (jasentyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE
+);
+
+INSERT INTO membership_fees_new (id, vuosi, jasentyyppi, summa, created_at, updated_at)
+SELECT id, vuosi, jasentyyppi, summa, created_at, updated_at
+FROM membership_fees;
+
+DROP TABLE membership_fees;
+ALTER TABLE membership_fees_new RENAME TO membership_fees;
+
+CREATE INDEX IF NOT EXISTS idx_membership_fees_vuosi_tyyppi ON membership_fees(vuosi, jasentyyppi);
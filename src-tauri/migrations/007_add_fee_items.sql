@@ -0,0 +1,59 @@
+-- Lisämaksut (fee_items): jäsenlehti, avainpantti, liittymismaksu ym.
+-- peruste: jasen       = jokaiselta laskutettavalta jäseneltä
+--          talous      = kerran taloutta kohden
+--          uusi_jasen  = laskutusvuonna liittyneeltä jäseneltä kerran
+--          valinnainen = vain jäseniltä, jotka ovat tilanneet maksun (member_fee_items)
+CREATE TABLE IF NOT EXISTS fee_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL,
+    summa DECIMAL(10,2) NOT NULL,
+    peruste TEXT NOT NULL CHECK (peruste IN ('jasen', 'talous', 'uusi_jasen', 'valinnainen')),
+    jasentyyppi TEXT,
+    vuosi INTEGER,
+    aktiivinen BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (jasentyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE
+);
+
+-- Jäsenten tilaamat valinnaiset maksut
+CREATE TABLE IF NOT EXISTS member_fee_items (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    jasen_id INTEGER NOT NULL,
+    maksu_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(jasen_id, maksu_id),
+    FOREIGN KEY (jasen_id) REFERENCES members(id) ON DELETE CASCADE,
+    FOREIGN KEY (maksu_id) REFERENCES fee_items(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_fee_items_jasen ON member_fee_items(jasen_id);
+
+-- Laskurivit (invoice_lines): talouskohtaisilla riveillä ei ole jäsentä, joten
+-- jasen_id muuttuu valinnaiseksi ja rivi voi viitata lisämaksuun
+
+DROP TABLE IF EXISTS invoice_lines_new;
+
+CREATE TABLE invoice_lines_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    lasku_id INTEGER NOT NULL,
+    jasen_id INTEGER,
+    maksu_id INTEGER,
+    kuvaus TEXT NOT NULL,
+    summa DECIMAL(10,2) NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (lasku_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    FOREIGN KEY (jasen_id) REFERENCES members(id),
+    FOREIGN KEY (maksu_id) REFERENCES fee_items(id) ON DELETE SET NULL
+);
+
+INSERT INTO invoice_lines_new (id, lasku_id, jasen_id, kuvaus, summa, created_at)
+SELECT id, lasku_id, jasen_id, kuvaus, summa, created_at
+FROM invoice_lines;
+
+DROP TABLE invoice_lines;
+ALTER TABLE invoice_lines_new RENAME TO invoice_lines;
+
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_lasku ON invoice_lines(lasku_id);
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_jasen ON invoice_lines(jasen_id);
+CREATE INDEX IF NOT EXISTS idx_invoice_lines_maksu ON invoice_lines(maksu_id);
@@ -0,0 +1,2 @@
+-- Vapaamuotoinen viesti laskulle (käsin luodut ja muokatut laskut)
+ALTER TABLE invoices ADD COLUMN viesti TEXT;
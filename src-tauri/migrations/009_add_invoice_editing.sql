@@ -0,0 +1,17 @@
+-- Laskun lähetyspäivä: lähetettyä laskua ei voi enää muokata
+ALTER TABLE invoices ADD COLUMN lahetetty DATE;
+
+-- Laskujen muutosloki. Ei viiteavainta laskuun, jotta loki säilyy
+-- myös laskun poiston jälkeen.
+-- muutos: rivi_lisatty, rivi_muutettu, rivi_poistettu, erapaiva, viesti, lahetetty
+CREATE TABLE IF NOT EXISTS invoice_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    lasku_id INTEGER NOT NULL,
+    rivi_id INTEGER,
+    muutos TEXT NOT NULL,
+    vanha_arvo TEXT,
+    uusi_arvo TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_invoice_changes_lasku ON invoice_changes(lasku_id);
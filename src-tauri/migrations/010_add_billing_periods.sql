@@ -0,0 +1,52 @@
+-- Laskutuskaudet: koko vuosi tai lukukausi. Jäsenmaksut haetaan kauden
+-- maksuvuodelta riippumatta siitä, milloin laskut luodaan.
+CREATE TABLE IF NOT EXISTS billing_periods (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL UNIQUE,
+    tyyppi TEXT NOT NULL DEFAULT 'vuosi' CHECK (tyyppi IN ('vuosi', 'lukukausi')),
+    maksuvuosi INTEGER NOT NULL,
+    lukukausi INTEGER CHECK (lukukausi IN (1, 2)),
+    oletuserapaiva DATE,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Vuosilaskutuksen laskut viittaavat kauteen; käsin luodulla laskulla ei ole kautta.
+-- Lisälasku on jo laskutetun talouden myöhemmin liittyneille jäsenille.
+ALTER TABLE invoices ADD COLUMN kausi_id INTEGER REFERENCES billing_periods(id);
+ALTER TABLE invoices ADD COLUMN lisalasku BOOLEAN NOT NULL DEFAULT 0;
+
+-- Olemassa olevat laskut kohdistetaan jäsenmaksurivin vuoden ("Jäsenmaksu 2026 - ...")
+-- vuosikauteen. Luontipäivää ei käytetä, koska joulukuussa luotu lasku voi olla
+-- seuraavan vuoden maksu. Laskut, joilla ei ole jäsenmaksuriviä, jäävät ilman kautta.
+CREATE TEMPORARY TABLE invoice_fee_years AS
+SELECT lasku_id, MIN(CAST(substr(kuvaus, 12, 4) AS INTEGER)) AS maksuvuosi
+FROM invoice_lines
+WHERE kuvaus GLOB 'Jäsenmaksu [0-9][0-9][0-9][0-9] *'
+GROUP BY lasku_id;
+
+INSERT OR IGNORE INTO billing_periods (nimi, tyyppi, maksuvuosi)
+SELECT DISTINCT CAST(maksuvuosi AS TEXT), 'vuosi', maksuvuosi
+FROM invoice_fee_years;
+
+UPDATE invoices
+SET kausi_id = (
+    SELECT bp.id FROM billing_periods bp
+    JOIN invoice_fee_years fy ON fy.maksuvuosi = bp.maksuvuosi
+    WHERE bp.tyyppi = 'vuosi'
+    AND fy.lasku_id = invoices.id
+)
+WHERE kausi_id IS NULL;
+
+DROP TABLE invoice_fee_years;
+
+-- Talouden saman kauden myöhemmät laskut ovat lisälaskuja, joten yksilöivä
+-- indeksi ei estä migraatiota, vaikka taloutta olisi laskutettu kahdesti
+UPDATE invoices
+SET lisalasku = 1
+WHERE kausi_id IS NOT NULL
+AND id NOT IN (SELECT MIN(id) FROM invoices WHERE kausi_id IS NOT NULL GROUP BY talous_id, kausi_id);
+
+-- Sama talous voi saada kaudelta vain yhden varsinaisen laskun
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invoices_talous_kausi
+ON invoices(talous_id, kausi_id) WHERE kausi_id IS NOT NULL AND lisalasku = 0;
@@ -0,0 +1,31 @@
+-- Laskutusajot: yhden laskutuskerran laskut voi perua yhtenä kokonaisuutena
+-- ennen lähettämistä. Perutun ajon rivi säilyy, peruttu-aika kertoo perumisen.
+CREATE TABLE IF NOT EXISTS invoice_batches (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kausi_id INTEGER,
+    laskuja INTEGER NOT NULL DEFAULT 0,
+    summa DECIMAL(10,2) NOT NULL DEFAULT 0,
+    peruttu DATETIME,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (kausi_id) REFERENCES billing_periods(id)
+);
+
+ALTER TABLE invoices ADD COLUMN ajo_id INTEGER REFERENCES invoice_batches(id);
+
+CREATE INDEX IF NOT EXISTS idx_invoices_ajo ON invoices(ajo_id);
+
+-- Ikään perustuvat jäsentyyppimuutokset. Laskutusajon muutokset palautetaan,
+-- jos ajo perutaan.
+CREATE TABLE IF NOT EXISTS member_type_changes (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    jasen_id INTEGER NOT NULL,
+    vanha_tyyppi TEXT NOT NULL,
+    uusi_tyyppi TEXT NOT NULL,
+    ika INTEGER,
+    ajo_id INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (jasen_id) REFERENCES members(id) ON DELETE CASCADE,
+    FOREIGN KEY (ajo_id) REFERENCES invoice_batches(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_type_changes_jasen ON member_type_changes(jasen_id);
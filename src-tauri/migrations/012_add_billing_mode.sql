@@ -0,0 +1,16 @@
+-- Laskutustapa: talouskohtainen lasku, jäsenkohtainen lasku tai talouskohtainen
+-- lasku, josta yksittäiset jäsenet voi erottaa omalle laskulleen.
+ALTER TABLE organization ADD COLUMN laskutustapa TEXT NOT NULL DEFAULT 'talous'
+CHECK (laskutustapa IN ('talous', 'jasen', 'talous_erikseen'));
+
+-- Jäsen laskutetaan omalla laskullaan talouden laskun sijaan
+ALTER TABLE members ADD COLUMN oma_lasku BOOLEAN NOT NULL DEFAULT 0;
+
+-- Jäsenkohtaisen laskun saaja; talouden laskulla tyhjä
+ALTER TABLE invoices ADD COLUMN jasen_id INTEGER REFERENCES members(id);
+
+-- Kaudella yksi varsinainen lasku taloutta tai talouden jäsentä kohden
+DROP INDEX IF EXISTS idx_invoices_talous_kausi;
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invoices_talous_kausi
+ON invoices(talous_id, kausi_id, IFNULL(jasen_id, 0))
+    WHERE kausi_id IS NOT NULL AND lisalasku = 0;
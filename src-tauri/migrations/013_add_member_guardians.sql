@@ -0,0 +1,23 @@
+-- Jäsenen huoltaja/maksaja, esim. nuorisojäsenen vanhempi. Huoltaja on joko
+-- toinen jäsen (huoltaja_jasen_id) tai jäsenrekisterin ulkopuolinen henkilö,
+-- jonka yhteystiedot tallennetaan tähän tauluun. Jäsenellä on enintään yksi maksaja.
+CREATE TABLE IF NOT EXISTS member_guardians (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    jasen_id INTEGER NOT NULL UNIQUE,
+    huoltaja_jasen_id INTEGER,
+    nimi TEXT,
+    katuosoite TEXT,
+    postinumero TEXT,
+    postitoimipaikka TEXT,
+    sahkoposti TEXT,
+    puhelinnumero TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (jasen_id) REFERENCES members(id) ON DELETE CASCADE,
+    FOREIGN KEY (huoltaja_jasen_id) REFERENCES members(id) ON DELETE CASCADE,
+    CHECK (huoltaja_jasen_id IS NOT NULL OR nimi IS NOT NULL),
+    CHECK (huoltaja_jasen_id IS NULL OR huoltaja_jasen_id != jasen_id)
+);
+
+-- Huoltajalle ohjatun laskun maksaja
+ALTER TABLE invoices ADD COLUMN huoltaja_id INTEGER REFERENCES member_guardians(id) ON DELETE SET NULL;
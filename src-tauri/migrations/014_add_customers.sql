@@ -0,0 +1,73 @@
+-- Asiakasrekisteri: laskun saajat, jotka eivät ole talouksia, esim. työnantajat,
+-- kunnat, kattojärjestöt ja tilavuokraajat. Asiakas on henkilö tai yhteisö.
+CREATE TABLE IF NOT EXISTS customers (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    tyyppi TEXT NOT NULL DEFAULT 'yhteiso' CHECK (tyyppi IN ('henkilo', 'yhteiso')),
+    nimi TEXT NOT NULL,
+    y_tunnus TEXT,
+    verkkolaskuosoite TEXT,
+    valittajatunnus TEXT,
+    yhteyshenkilo TEXT,
+    sahkoposti TEXT,
+    puhelinnumero TEXT,
+    katuosoite TEXT,
+    postinumero TEXT,
+    postitoimipaikka TEXT,
+    aktiivinen BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Laskut (invoices): asiakkaalle tehdyllä laskulla ei välttämättä ole taloutta,
+-- joten talous_id muuttuu valinnaiseksi ja laskulle tulee asiakas. Jäseniin
+-- liittyvät rivit viittaavat edelleen jäseniin.
+
+DROP TABLE IF EXISTS invoices_new;
+
+CREATE TABLE invoices_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    talous_id INTEGER,
+    asiakas_id INTEGER,
+    luontipaiva DATE NOT NULL DEFAULT (date('now')),
+    erapaiva DATE NOT NULL,
+    summa DECIMAL(10,2) NOT NULL,
+    viitenumero TEXT NOT NULL UNIQUE,
+    laskunumero TEXT,
+    maksettu BOOLEAN NOT NULL DEFAULT 0,
+    maksupaiva DATE,
+    viesti TEXT,
+    lahetetty DATE,
+    kausi_id INTEGER,
+    lisalasku BOOLEAN NOT NULL DEFAULT 0,
+    ajo_id INTEGER,
+    jasen_id INTEGER,
+    huoltaja_id INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (talous_id) REFERENCES households(id),
+    FOREIGN KEY (asiakas_id) REFERENCES customers(id),
+    FOREIGN KEY (kausi_id) REFERENCES billing_periods(id),
+    FOREIGN KEY (ajo_id) REFERENCES invoice_batches(id),
+    FOREIGN KEY (jasen_id) REFERENCES members(id),
+    FOREIGN KEY (huoltaja_id) REFERENCES member_guardians(id) ON DELETE SET NULL,
+    CHECK (talous_id IS NOT NULL OR asiakas_id IS NOT NULL)
+);
+
+INSERT INTO invoices_new (id, talous_id, luontipaiva, erapaiva, summa, viitenumero,
+    laskunumero, maksettu, maksupaiva, viesti, lahetetty, kausi_id, lisalasku, ajo_id,
+    jasen_id, huoltaja_id, created_at, updated_at)
+SELECT id, talous_id, luontipaiva, erapaiva, summa, viitenumero,
+    laskunumero, maksettu, maksupaiva, viesti, lahetetty, kausi_id, lisalasku, ajo_id,
+    jasen_id, huoltaja_id, created_at, updated_at
+FROM invoices;
+
+DROP TABLE invoices;
+ALTER TABLE invoices_new RENAME TO invoices;
+
+CREATE INDEX IF NOT EXISTS idx_invoices_talous ON invoices(talous_id);
+CREATE INDEX IF NOT EXISTS idx_invoices_maksettu ON invoices(maksettu);
+CREATE INDEX IF NOT EXISTS idx_invoices_ajo ON invoices(ajo_id);
+CREATE INDEX IF NOT EXISTS idx_invoices_asiakas ON invoices(asiakas_id);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invoices_talous_kausi
+ON invoices(talous_id, kausi_id, IFNULL(jasen_id, 0))
+WHERE kausi_id IS NOT NULL AND lisalasku = 0;
@@ -0,0 +1,16 @@
+-- Jäsenlaji: henkilöjäsen tai yhteisöjäsen. Yhteisöjäsenen nimi on sukunimi-
+-- kentässä ja etunimi on tyhjä; henkilötunnusta ja syntymäaikaa ei ole.
+ALTER TABLE members ADD COLUMN jasenlaji TEXT NOT NULL DEFAULT 'henkilo'
+CHECK (jasenlaji IN ('henkilo', 'yhteiso'));
+
+-- Yhteisöjäsenen tunniste-, yhteys- ja laskutustiedot
+ALTER TABLE members ADD COLUMN y_tunnus TEXT;
+ALTER TABLE members ADD COLUMN yhteyshenkilo TEXT;
+ALTER TABLE members ADD COLUMN laskuviite TEXT;
+ALTER TABLE members ADD COLUMN verkkolaskuosoite TEXT;
+ALTER TABLE members ADD COLUMN valittajatunnus TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_members_y_tunnus
+ON members(y_tunnus) WHERE y_tunnus IS NOT NULL AND y_tunnus != '';
+
+CREATE INDEX IF NOT EXISTS idx_members_jasenlaji ON members(jasenlaji);
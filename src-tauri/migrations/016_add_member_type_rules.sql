@@ -0,0 +1,54 @@
+-- Ikään perustuvat jäsentyypin siirtymäsäännöt. Korvaa member_types-taulun
+-- seuraava_tyyppi-sarakkeen: sääntö voi siirtää jäsenen mihin tahansa tyyppiin
+-- (myös nuoremmaksi tai seniorityyppiin), ja ikä lasketaan joko vuositasolla
+-- (maksuvuosi - syntymävuosi) tai tarkasti syntymäpäivästä leikkuripäivään.
+CREATE TABLE IF NOT EXISTS member_type_rules (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL,
+    lahtotyyppi TEXT NOT NULL,
+    kohdetyyppi TEXT NOT NULL,
+    ika_min INTEGER,
+    ika_max INTEGER,
+    laskentatapa TEXT NOT NULL DEFAULT 'vuosi' CHECK (laskentatapa IN ('vuosi', 'paiva')),
+    -- Päivämääräkohtaisen laskennan leikkuripäivä muodossa KK-PP, oletus 31.12.
+    leikkuripaiva TEXT,
+    jarjestys INTEGER NOT NULL DEFAULT 0,
+    aktiivinen BOOLEAN NOT NULL DEFAULT 1,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK (lahtotyyppi != kohdetyyppi),
+    CHECK (ika_min IS NOT NULL OR ika_max IS NOT NULL),
+    FOREIGN KEY (lahtotyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE,
+    FOREIGN KEY (kohdetyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_member_type_rules_lahtotyyppi ON member_type_rules(lahtotyyppi);
+
+-- Nykyiset siirtymät säännöiksi: ikärajan ylittänyt siirtyy seuraavaan tyyppiin
+INSERT INTO member_type_rules (nimi, lahtotyyppi, kohdetyyppi, ika_min, laskentatapa)
+SELECT nimi || ' -> ' || seuraava_tyyppi, nimi, seuraava_tyyppi, ika_max + 1, 'vuosi'
+FROM member_types
+WHERE ika_max IS NOT NULL AND seuraava_tyyppi IS NOT NULL AND seuraava_tyyppi != nimi
+  AND seuraava_tyyppi IN (SELECT nimi FROM member_types);
+
+-- Uusi nuori jäsen varsinaisesta nuorisojäseneksi nuorisojäsenen ikärajan mukaan
+INSERT INTO member_type_rules (nimi, lahtotyyppi, kohdetyyppi, ika_max, laskentatapa)
+SELECT 'varsinainen -> nuorisojasen', 'varsinainen', 'nuorisojasen', ika_max, 'vuosi'
+FROM member_types
+WHERE nimi = 'nuorisojasen' AND ika_max IS NOT NULL
+  AND EXISTS (SELECT 1 FROM member_types WHERE nimi = 'varsinainen');
+
+ALTER TABLE member_types DROP COLUMN seuraava_tyyppi;
+
+-- Tyyppimuutokset ovat ehdotuksia, jotka hyväksytään tai hylätään yksitellen.
+-- Aiemmin laskutusajon yhteydessä tehdyt muutokset on jo hyväksytty.
+ALTER TABLE member_type_changes ADD COLUMN tila TEXT NOT NULL DEFAULT 'hyvaksytty'
+CHECK (tila IN ('ehdotettu', 'hyvaksytty', 'hylatty'));
+ALTER TABLE member_type_changes ADD COLUMN saanto_id INTEGER
+REFERENCES member_type_rules(id) ON DELETE SET NULL;
+ALTER TABLE member_type_changes ADD COLUMN vuosi INTEGER;
+ALTER TABLE member_type_changes ADD COLUMN kasitelty DATETIME;
+
+-- Jäsenellä on kerrallaan enintään yksi käsittelemätön ehdotus
+CREATE UNIQUE INDEX IF NOT EXISTS idx_member_type_changes_ehdotettu
+ON member_type_changes(jasen_id) WHERE tila = 'ehdotettu';
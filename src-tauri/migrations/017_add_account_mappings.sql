@@ -0,0 +1,20 @@
+-- Kirjanpidon tilit kirjanpitoaineiston muodostamiseen: myyntisaamiset, pankki
+-- ja oletustulotili, sekä tulotilit jäsentyypeittäin ja lisämaksuittain.
+-- Jäsentyypin tai lisämaksun tilin puuttuessa käytetään oletustulotiliä.
+CREATE TABLE IF NOT EXISTS account_mappings (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kohde TEXT NOT NULL CHECK (kohde IN ('saatavat', 'pankki', 'tulot', 'jasentyyppi', 'lisamaksu')),
+    jasentyyppi TEXT,
+    maksu_id INTEGER,
+    tili TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((kohde = 'jasentyyppi') = (jasentyyppi IS NOT NULL)),
+    CHECK ((kohde = 'lisamaksu') = (maksu_id IS NOT NULL)),
+    FOREIGN KEY (jasentyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE ON DELETE CASCADE,
+    FOREIGN KEY (maksu_id) REFERENCES fee_items(id) ON DELETE CASCADE
+);
+
+-- Kullekin kohteelle yksi tili
+CREATE UNIQUE INDEX IF NOT EXISTS idx_account_mappings_kohde
+ON account_mappings(kohde, IFNULL(jasentyyppi, ''), IFNULL(maksu_id, 0));
@@ -0,0 +1,14 @@
+-- Tilitin-kirjanpitoon viedyt tositteet, jotta samaa tositetta ei viedä kahdesti.
+-- tyyppi on kirjanpitotositteen tyyppi (lasku, hyvitys, maksu).
+CREATE TABLE IF NOT EXISTS tilitin_exports (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    lasku_id INTEGER NOT NULL,
+    tyyppi TEXT NOT NULL,
+    tietokanta TEXT NOT NULL,
+    tositenumero INTEGER NOT NULL,
+    tilitin_tosite_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (lasku_id) REFERENCES invoices(id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tilitin_exports_tosite ON tilitin_exports(lasku_id, tyyppi);
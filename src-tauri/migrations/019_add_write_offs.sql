@@ -0,0 +1,33 @@
+-- Luottotappio: maksamattoman laskun saatava kirjataan pois, mutta lasku
+-- säilyy historiassa. Luottotappioksi kirjattu lasku ei ole avoin saatava.
+ALTER TABLE invoices ADD COLUMN luottotappio_pvm DATE;
+ALTER TABLE invoices ADD COLUMN luottotappio_syy TEXT;
+
+-- Jäsen esitetty erotettavaksi maksamattomien maksujen vuoksi (esityspäivä)
+ALTER TABLE members ADD COLUMN erottaminen_esitetty DATE;
+
+CREATE INDEX IF NOT EXISTS idx_members_erottaminen_esitetty ON members(erottaminen_esitetty);
+
+-- Luottotappiotili kirjanpitoaineistoon: CHECK-rajoitteen vuoksi taulu rakennetaan uudelleen
+CREATE TABLE account_mappings_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    kohde TEXT NOT NULL CHECK (kohde IN ('saatavat', 'pankki', 'tulot', 'luottotappiot', 'jasentyyppi', 'lisamaksu')),
+    jasentyyppi TEXT,
+    maksu_id INTEGER,
+    tili TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((kohde = 'jasentyyppi') = (jasentyyppi IS NOT NULL)),
+    CHECK ((kohde = 'lisamaksu') = (maksu_id IS NOT NULL)),
+    FOREIGN KEY (jasentyyppi) REFERENCES member_types(nimi) ON UPDATE CASCADE ON DELETE CASCADE,
+    FOREIGN KEY (maksu_id) REFERENCES fee_items(id) ON DELETE CASCADE
+);
+
+INSERT INTO account_mappings_new (id, kohde, jasentyyppi, maksu_id, tili, created_at, updated_at)
+SELECT id, kohde, jasentyyppi, maksu_id, tili, created_at, updated_at FROM account_mappings;
+
+DROP TABLE account_mappings;
+ALTER TABLE account_mappings_new RENAME TO account_mappings;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_account_mappings_kohde
+ON account_mappings(kohde, IFNULL(jasentyyppi, ''), IFNULL(maksu_id, 0));
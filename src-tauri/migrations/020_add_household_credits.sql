@@ -0,0 +1,46 @@
+-- Talouden ennakkomaksut ja hyvitykset, esim. liikasuoritus. Positiivinen summa
+-- kasvattaa talouden hyvityssaldoa, negatiivinen on laskulla käytetty hyvitys.
+-- Käytetty hyvitys viittaa laskuun ja laskun hyvitysriviin, joten laskun tai
+-- rivin poisto palauttaa hyvityksen saldoon.
+CREATE TABLE IF NOT EXISTS household_credits (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    talous_id INTEGER NOT NULL,
+    paivamaara DATE NOT NULL,
+    summa DECIMAL(10,2) NOT NULL CHECK (summa <> 0),
+    selite TEXT NOT NULL,
+    lasku_id INTEGER,
+    rivi_id INTEGER,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((summa > 0) = (lasku_id IS NULL)),
+    FOREIGN KEY (talous_id) REFERENCES households(id) ON DELETE CASCADE,
+    FOREIGN KEY (lasku_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    FOREIGN KEY (rivi_id) REFERENCES invoice_lines(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_household_credits_talous ON household_credits(talous_id);
+CREATE INDEX IF NOT EXISTS idx_household_credits_lasku ON household_credits(lasku_id);
+
+-- Tilitiniin viety tosite voi koskea myös ennakkomaksua: lasku_id ei ole enää
+-- pakollinen, joten taulu rakennetaan uudelleen
+CREATE TABLE tilitin_exports_new (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    lasku_id INTEGER,
+    ennakko_id INTEGER,
+    tyyppi TEXT NOT NULL,
+    tietokanta TEXT NOT NULL,
+    tositenumero INTEGER NOT NULL,
+    tilitin_tosite_id INTEGER NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    CHECK ((lasku_id IS NULL) <> (ennakko_id IS NULL)),
+    FOREIGN KEY (lasku_id) REFERENCES invoices(id) ON DELETE CASCADE,
+    FOREIGN KEY (ennakko_id) REFERENCES household_credits(id) ON DELETE CASCADE
+);
+
+INSERT INTO tilitin_exports_new (id, lasku_id, tyyppi, tietokanta, tositenumero, tilitin_tosite_id, created_at)
+SELECT id, lasku_id, tyyppi, tietokanta, tositenumero, tilitin_tosite_id, created_at FROM tilitin_exports;
+
+DROP TABLE tilitin_exports;
+ALTER TABLE tilitin_exports_new RENAME TO tilitin_exports;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tilitin_exports_tosite ON tilitin_exports(lasku_id, tyyppi);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tilitin_exports_ennakko ON tilitin_exports(ennakko_id) WHERE ennakko_id IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Jäsenen eroamispäivä vuosiraportin jäsenmääriä varten. Päivä kirjataan, kun
+-- jäsen merkitään ei-aktiiviseksi, ja poistetaan, jos jäsen aktivoidaan uudelleen.
+ALTER TABLE members ADD COLUMN eroamispaiva DATE;
+
+-- Jo ei-aktiivisten jäsenten eroamispäivä ei ole tiedossa, joten se jää tyhjäksi.
+-- Vuosiraportti näyttää heidät erikseen eronneina, joiden eroamispäivä on tuntematon.
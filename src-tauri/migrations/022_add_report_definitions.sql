@@ -0,0 +1,14 @@
+-- Tallennetut raportit: kohde (jasenet, taloudet tai laskut) sekä sarakkeet,
+-- suodattimet ja järjestys JSON-muodossa. Kentät viittaavat sovelluksen
+-- sallimiin kenttiin, joten raportti ei sisällä SQL:ää.
+CREATE TABLE IF NOT EXISTS report_definitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    nimi TEXT NOT NULL UNIQUE,
+    kuvaus TEXT,
+    kohde TEXT NOT NULL,
+    sarakkeet TEXT NOT NULL,
+    suodattimet TEXT NOT NULL DEFAULT '[]',
+    jarjestys TEXT NOT NULL DEFAULT '[]',
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    updated_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,7 @@
+-- Viety tosite tunnistetaan myös Tilitin-tiedostosta, jotta samat tositteet
+-- voi viedä toiseen tiedostoon (esim. tilikausikohtaiset tiedostot)
+DROP INDEX IF EXISTS idx_tilitin_exports_tosite;
+DROP INDEX IF EXISTS idx_tilitin_exports_ennakko;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tilitin_exports_tosite ON tilitin_exports(lasku_id, tyyppi, tietokanta);
+CREATE UNIQUE INDEX IF NOT EXISTS idx_tilitin_exports_ennakko ON tilitin_exports(ennakko_id, tietokanta) WHERE ennakko_id IS NOT NULL;
@@ -0,0 +1,22 @@
+-- Huoltajalle ohjatun laskun maksajan nimi ja osoite tallennetaan laskulle,
+-- jotta ne säilyvät, vaikka huoltaja vaihdetaan tai poistetaan
+ALTER TABLE invoices ADD COLUMN maksaja_nimi TEXT;
+ALTER TABLE invoices ADD COLUMN maksaja_katuosoite TEXT;
+ALTER TABLE invoices ADD COLUMN maksaja_postinumero TEXT;
+ALTER TABLE invoices ADD COLUMN maksaja_postitoimipaikka TEXT;
+ALTER TABLE invoices ADD COLUMN maksaja_sahkoposti TEXT;
+
+-- Olemassa oleville laskuille huoltajan nykyiset tiedot
+UPDATE invoices
+SET (maksaja_nimi, maksaja_katuosoite, maksaja_postinumero, maksaja_postitoimipaikka, maksaja_sahkoposti) = (
+    SELECT COALESCE(TRIM(gm.etunimi || ' ' || gm.sukunimi), g.nimi),
+           COALESCE(a.katuosoite, g.katuosoite),
+           COALESCE(a.postinumero, g.postinumero),
+           COALESCE(a.postitoimipaikka, g.postitoimipaikka),
+           COALESCE(gm.sahkoposti, g.sahkoposti)
+    FROM member_guardians g
+    LEFT JOIN members gm ON g.huoltaja_jasen_id = gm.id
+    LEFT JOIN addresses a ON gm.osoite_id = a.id
+    WHERE g.id = invoices.huoltaja_id
+)
+WHERE huoltaja_id IS NOT NULL AND maksaja_nimi IS NULL;
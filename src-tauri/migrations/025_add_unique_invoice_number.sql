@@ -0,0 +1,12 @@
+-- Laskunumero on yksilöllinen. Rinnakkaisissa laskutusajoissa syntyneet samat
+-- numerot erotetaan lisäämällä myöhempien laskujen numeroon laskun tunniste.
+UPDATE invoices
+SET laskunumero = laskunumero || '-L' || id
+WHERE laskunumero IS NOT NULL
+AND id NOT IN (
+    SELECT MIN(id) FROM invoices
+    WHERE laskunumero IS NOT NULL
+    GROUP BY laskunumero
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_invoices_laskunumero ON invoices(laskunumero) WHERE laskunumero IS NOT NULL;
@@ -1,5 +1,9 @@
 use crate::database::Database;
+use crate::export;
+use crate::fee_rules::full_name;
 use crate::models::*;
+use crate::report_builder;
+use std::collections::HashMap;
 use std::sync::Arc;
 use tauri::State;
 use tokio::sync::Mutex;
@@ -72,7 +76,7 @@ pub async fn create_member_with_address(
             let household_name = if osoitetyyppi == "uusi" {
                 memberData["talouden_nimi"].as_str().map(|s| s.to_string())
             } else {
-                Some(format!("{} {}", 
+                Some(full_name(
                     memberData["etunimi"].as_str().unwrap_or(""),
                     memberData["sukunimi"].as_str().unwrap_or("")))
             };
@@ -102,10 +106,8 @@ pub async fn create_member_with_address(
         _ => return Err("Invalid osoitetyyppi".to_string()),
     };
     
-    // Parse member type
-    let member_type_str = memberData["jasentyyppi"].as_str().unwrap_or("Varsinainen");
-    let member_type: MemberType = member_type_str.parse()
-        .map_err(|e: String| format!("Invalid member type: {}", e))?;
+    // Member type is validated against the member_types table in the database layer
+    let member_type = memberData["jasentyyppi"].as_str().unwrap_or("varsinainen").to_string();
     
     // Parse dates
     let syntymaaika = memberData["syntymaaika"].as_str()
@@ -126,6 +128,15 @@ pub async fn create_member_with_address(
         liittymispaiva,
         jasentyyppi: member_type,
         aktiivinen: memberData["aktiivinen"].as_bool().unwrap_or(true),
+        oma_lasku: memberData["oma_lasku"].as_bool().unwrap_or(false),
+        jasenlaji: memberData["jasenlaji"].as_str()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        y_tunnus: memberData["y_tunnus"].as_str().map(|s| s.to_string()),
+        yhteyshenkilo: memberData["yhteyshenkilo"].as_str().map(|s| s.to_string()),
+        laskuviite: memberData["laskuviite"].as_str().map(|s| s.to_string()),
+        verkkolaskuosoite: memberData["verkkolaskuosoite"].as_str().map(|s| s.to_string()),
+        valittajatunnus: memberData["valittajatunnus"].as_str().map(|s| s.to_string()),
     };
     
     db.create_member(&member).await.map_err(|e| e.to_string())
@@ -172,7 +183,7 @@ pub async fn update_member_with_address(
             let household_name = if osoitetyyppi == "uusi" {
                 memberData["talouden_nimi"].as_str().map(|s| s.to_string())
             } else {
-                Some(format!("{} {}", 
+                Some(full_name(
                     memberData["etunimi"].as_str().unwrap_or(""),
                     memberData["sukunimi"].as_str().unwrap_or("")))
             };
@@ -214,10 +225,8 @@ pub async fn update_member_with_address(
         }
     };
     
-    // Parse member type
-    let member_type_str = memberData["jasentyyppi"].as_str().unwrap_or("Varsinainen");
-    let member_type: MemberType = member_type_str.parse()
-        .map_err(|e: String| format!("Invalid member type: {}", e))?;
+    // Member type is validated against the member_types table in the database layer
+    let member_type = memberData["jasentyyppi"].as_str().unwrap_or("varsinainen").to_string();
     
     // Parse dates
     let syntymaaika = memberData["syntymaaika"].as_str()
@@ -238,6 +247,15 @@ pub async fn update_member_with_address(
         liittymispaiva,
         jasentyyppi: member_type,
         aktiivinen: memberData["aktiivinen"].as_bool().unwrap_or(true),
+        oma_lasku: memberData["oma_lasku"].as_bool().unwrap_or(false),
+        jasenlaji: memberData["jasenlaji"].as_str()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or_default(),
+        y_tunnus: memberData["y_tunnus"].as_str().map(|s| s.to_string()),
+        yhteyshenkilo: memberData["yhteyshenkilo"].as_str().map(|s| s.to_string()),
+        laskuviite: memberData["laskuviite"].as_str().map(|s| s.to_string()),
+        verkkolaskuosoite: memberData["verkkolaskuosoite"].as_str().map(|s| s.to_string()),
+        valittajatunnus: memberData["valittajatunnus"].as_str().map(|s| s.to_string()),
     };
     
     let updated_member = db.update_member(id, &member).await.map_err(|e| e.to_string())?;
@@ -371,6 +389,47 @@ pub async fn delete_household(
     db.delete_household(id).await.map_err(|e| e.to_string())
 }
 
+#[tauri::command]
+pub async fn get_household_statement(
+    db: State<'_, DbState>,
+    talous_id: i64,
+) -> Result<HouseholdStatement, String> {
+    let db = db.lock().await;
+    db.get_household_statement(talous_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_household_credits(
+    db: State<'_, DbState>,
+    talous_id: i64,
+) -> Result<Vec<HouseholdCredit>, String> {
+    let db = db.lock().await;
+    db.get_household_credits(talous_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_household_credit(
+    db: State<'_, DbState>,
+    credit: CreateHouseholdCredit,
+) -> Result<HouseholdCredit, String> {
+    let db = db.lock().await;
+    db.add_household_credit(&credit)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_household_credit(db: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_household_credit(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn save_pdf_file(
     file_path: String,
@@ -390,17 +449,161 @@ pub async fn show_save_dialog(
     default_filename: String,
 ) -> Result<Option<String>, String> {
     use tauri_plugin_dialog::DialogExt;
-    
+
+    // Suodatin oletustiedostonimen päätteen mukaan (pdf, csv, json, xlsx)
+    let extension = std::path::Path::new(&default_filename)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("pdf")
+        .to_lowercase();
+    let filter_name = format!("{} Files", extension.to_uppercase());
+
     let file_path = app_handle
         .dialog()
         .file()
         .set_file_name(&default_filename)
-        .add_filter("PDF Files", &["pdf"])
+        .add_filter(&filter_name, &[extension.as_str()])
         .blocking_save_file();
     
     Ok(file_path.map(|p| p.to_string()))
 }
 
+#[tauri::command]
+pub async fn get_member_types(db: State<'_, DbState>) -> Result<Vec<MemberType>, String> {
+    let db = db.lock().await;
+    db.get_member_types().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_member_type(
+    db: State<'_, DbState>,
+    member_type: CreateMemberType,
+) -> Result<MemberType, String> {
+    let db = db.lock().await;
+    db.create_member_type(&member_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_member_type(
+    db: State<'_, DbState>,
+    id: i64,
+    member_type: CreateMemberType,
+) -> Result<MemberType, String> {
+    let db = db.lock().await;
+    db.update_member_type(id, &member_type)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_member_type(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_member_type(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_member_type_rules(
+    db: State<'_, DbState>,
+) -> Result<Vec<MemberTypeRule>, String> {
+    let db = db.lock().await;
+    db.get_member_type_rules().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_member_type_rule(
+    db: State<'_, DbState>,
+    rule: CreateMemberTypeRule,
+) -> Result<MemberTypeRule, String> {
+    let db = db.lock().await;
+    db.create_member_type_rule(&rule)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_member_type_rule(
+    db: State<'_, DbState>,
+    id: i64,
+    rule: CreateMemberTypeRule,
+) -> Result<MemberTypeRule, String> {
+    let db = db.lock().await;
+    db.update_member_type_rule(id, &rule)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_member_type_rule(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_member_type_rule(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_member_type_changes(
+    db: State<'_, DbState>,
+    year: i32,
+) -> Result<Vec<MemberTypeChange>, String> {
+    let db = db.lock().await;
+    db.get_pending_member_type_changes(year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn propose_member_type_changes(
+    db: State<'_, DbState>,
+    year: i32,
+) -> Result<Vec<MemberTypeChangeLog>, String> {
+    let db = db.lock().await;
+    db.propose_member_type_changes(year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_member_type_change_log(
+    db: State<'_, DbState>,
+    tila: Option<String>,
+) -> Result<Vec<MemberTypeChangeLog>, String> {
+    let db = db.lock().await;
+    let tila = tila
+        .map(|t| t.parse::<MemberTypeChangeStatus>())
+        .transpose()?;
+    db.get_member_type_change_log(tila.as_ref())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn approve_member_type_change(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<MemberTypeChangeLog, String> {
+    let db = db.lock().await;
+    db.approve_member_type_change(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn reject_member_type_change(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<MemberTypeChangeLog, String> {
+    let db = db.lock().await;
+    db.reject_member_type_change(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn get_membership_fees(db: State<'_, DbState>) -> Result<Vec<MembershipFee>, String> {
     let db = db.lock().await;
@@ -431,80 +634,858 @@ pub async fn update_membership_fee(
 }
 
 #[tauri::command]
-pub async fn get_invoices(db: State<'_, DbState>) -> Result<Vec<InvoiceWithDetails>, String> {
+pub async fn get_fee_rules(db: State<'_, DbState>) -> Result<Vec<FeeRule>, String> {
     let db = db.lock().await;
-    db.get_invoices().await.map_err(|e| e.to_string())
+    db.get_fee_rules().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn validate_invoice_creation(
+pub async fn create_fee_rule(
     db: State<'_, DbState>,
-    year: i32,
-) -> Result<String, String> {
+    rule: CreateFeeRule,
+) -> Result<FeeRule, String> {
     let db = db.lock().await;
-    db.validate_invoice_creation(year)
+    db.create_fee_rule(&rule).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_fee_rule(
+    db: State<'_, DbState>,
+    id: i64,
+    rule: CreateFeeRule,
+) -> Result<FeeRule, String> {
+    let db = db.lock().await;
+    db.update_fee_rule(id, &rule)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn create_invoice_for_year(
+pub async fn delete_fee_rule(
     db: State<'_, DbState>,
-    year: i32,
-    due_date: Option<String>,
-) -> Result<Vec<Invoice>, String> {
-    let due_date = match due_date {
-        Some(s) if !s.is_empty() => Some(
-            chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
-                .map_err(|e| format!("Virheellinen eräpäivä: {}", e))?,
-        ),
-        _ => None,
-    };
+    id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_fee_rule(id).await.map_err(|e| e.to_string())
+}
 
+#[tauri::command]
+pub async fn get_fee_items(db: State<'_, DbState>) -> Result<Vec<FeeItem>, String> {
     let db = db.lock().await;
-    db.create_invoice_for_year(year, due_date)
+    db.get_fee_items().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_fee_item(
+    db: State<'_, DbState>,
+    item: CreateFeeItem,
+) -> Result<FeeItem, String> {
+    let db = db.lock().await;
+    db.create_fee_item(&item).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_fee_item(
+    db: State<'_, DbState>,
+    id: i64,
+    item: CreateFeeItem,
+) -> Result<FeeItem, String> {
+    let db = db.lock().await;
+    db.update_fee_item(id, &item)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn mark_invoice_paid(
+pub async fn delete_fee_item(
     db: State<'_, DbState>,
     id: i64,
-    payment_date: String,
-) -> Result<Invoice, String> {
-    let payment_date = chrono::NaiveDate::parse_from_str(&payment_date, "%Y-%m-%d")
-        .map_err(|e| format!("Invalid date format: {}", e))?;
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_fee_item(id).await.map_err(|e| e.to_string())
+}
 
+#[tauri::command]
+pub async fn get_member_guardian(
+    db: State<'_, DbState>,
+    member_id: i64,
+) -> Result<Option<MemberGuardian>, String> {
     let db = db.lock().await;
-    db.mark_invoice_paid(id, payment_date)
+    db.get_member_guardian(member_id)
         .await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_invoice(
+pub async fn set_member_guardian(
+    db: State<'_, DbState>,
+    member_id: i64,
+    guardian: CreateMemberGuardian,
+) -> Result<MemberGuardian, String> {
+    let db = db.lock().await;
+    db.set_member_guardian(member_id, &guardian)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_member_guardian(
+    db: State<'_, DbState>,
+    member_id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.remove_member_guardian(member_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_member_fee_items(
+    db: State<'_, DbState>,
+    member_id: i64,
+) -> Result<Vec<FeeItem>, String> {
+    let db = db.lock().await;
+    db.get_member_fee_items(member_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn subscribe_member_fee_item(
+    db: State<'_, DbState>,
+    member_id: i64,
+    fee_item_id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.subscribe_member_fee_item(member_id, fee_item_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn unsubscribe_member_fee_item(
+    db: State<'_, DbState>,
+    member_id: i64,
+    fee_item_id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.unsubscribe_member_fee_item(member_id, fee_item_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_billing_periods(db: State<'_, DbState>) -> Result<Vec<BillingPeriod>, String> {
+    let db = db.lock().await;
+    db.get_billing_periods().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_billing_period(
+    db: State<'_, DbState>,
+    period: CreateBillingPeriod,
+) -> Result<BillingPeriod, String> {
+    let db = db.lock().await;
+    db.create_billing_period(&period)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_billing_period(
+    db: State<'_, DbState>,
+    id: i64,
+    period: CreateBillingPeriod,
+) -> Result<BillingPeriod, String> {
+    let db = db.lock().await;
+    db.update_billing_period(id, &period)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_billing_period(
     db: State<'_, DbState>,
     id: i64,
 ) -> Result<(), String> {
     let db = db.lock().await;
-    db.delete_invoice(id).await.map_err(|e| e.to_string())
+    db.delete_billing_period(id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn delete_membership_fee(
+pub async fn get_customers(db: State<'_, DbState>) -> Result<Vec<Customer>, String> {
+    let db = db.lock().await;
+    db.get_customers().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_customer(
+    db: State<'_, DbState>,
+    customer: CreateCustomer,
+) -> Result<Customer, String> {
+    let db = db.lock().await;
+    db.create_customer(&customer)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_customer(
+    db: State<'_, DbState>,
+    id: i64,
+    customer: CreateCustomer,
+) -> Result<Customer, String> {
+    let db = db.lock().await;
+    db.update_customer(id, &customer)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_customer(
     db: State<'_, DbState>,
     id: i64,
 ) -> Result<(), String> {
     let db = db.lock().await;
-    db.delete_membership_fee(id).await.map_err(|e| e.to_string())
+    db.delete_customer(id).await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_dashboard_stats(db: State<'_, DbState>) -> Result<DashboardStats, String> {
+pub async fn get_invoices(db: State<'_, DbState>) -> Result<Vec<InvoiceWithDetails>, String> {
+    let db = db.lock().await;
+    db.get_invoices().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn validate_invoice_creation(
+    db: State<'_, DbState>,
+    year: i32,
+) -> Result<String, String> {
+    let db = db.lock().await;
+    db.validate_invoice_creation(year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_invoice_for_year(
+    db: State<'_, DbState>,
+    year: i32,
+    due_date: Option<String>,
+) -> Result<InvoiceBatchResult, String> {
+    let due_date = match due_date {
+        Some(s) if !s.is_empty() => Some(
+            chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map_err(|e| format!("Virheellinen eräpäivä: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    let db = db.lock().await;
+    db.create_invoice_for_year(year, due_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn validate_period_invoicing(
+    db: State<'_, DbState>,
+    period_id: i64,
+) -> Result<String, String> {
+    let db = db.lock().await;
+    db.validate_period_invoicing(period_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_invoices_for_period(
+    db: State<'_, DbState>,
+    period_id: i64,
+    due_date: Option<String>,
+) -> Result<InvoiceBatchResult, String> {
+    let due_date = match due_date {
+        Some(s) if !s.is_empty() => Some(
+            chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map_err(|e| format!("Virheellinen eräpäivä: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    let db = db.lock().await;
+    db.create_invoices_for_period(period_id, due_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_invoices_for_selection(
+    db: State<'_, DbState>,
+    period_id: i64,
+    selection: InvoiceSelection,
+    due_date: Option<String>,
+) -> Result<InvoiceBatchResult, String> {
+    let due_date = match due_date {
+        Some(s) if !s.is_empty() => Some(
+            chrono::NaiveDate::parse_from_str(&s, "%Y-%m-%d")
+                .map_err(|e| format!("Virheellinen eräpäivä: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    let db = db.lock().await;
+    db.create_invoices_for_selection(period_id, &selection, due_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_invoice_creation(
+    db: State<'_, DbState>,
+    year: i32,
+) -> Result<InvoicePreview, String> {
+    let db = db.lock().await;
+    db.preview_invoice_creation(year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_period_invoicing(
+    db: State<'_, DbState>,
+    period_id: i64,
+) -> Result<InvoicePreview, String> {
+    let db = db.lock().await;
+    db.preview_period_invoicing(period_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn preview_invoices_for_selection(
+    db: State<'_, DbState>,
+    period_id: i64,
+    selection: InvoiceSelection,
+) -> Result<InvoicePreview, String> {
+    let db = db.lock().await;
+    db.preview_invoices_for_selection(period_id, &selection)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Tallentaa esikatselun tiedostoon: .json-päätteellä JSON, muuten CSV
+#[tauri::command]
+pub async fn export_invoice_preview(
+    db: State<'_, DbState>,
+    period_id: i64,
+    file_path: String,
+) -> Result<(), String> {
+    let preview = {
+        let db = db.lock().await;
+        db.preview_period_invoicing(period_id)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let content = if file_path.to_lowercase().ends_with(".json") {
+        serde_json::to_string_pretty(&preview).map_err(|e| e.to_string())?
+    } else {
+        export::invoice_preview_csv(&preview)
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_invoice_batches(db: State<'_, DbState>) -> Result<Vec<InvoiceBatch>, String> {
+    let db = db.lock().await;
+    db.get_invoice_batches().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rollback_invoice_batch(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<InvoiceBatch, String> {
+    let db = db.lock().await;
+    db.rollback_invoice_batch(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_manual_invoice(
+    db: State<'_, DbState>,
+    invoice: CreateManualInvoice,
+) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.create_manual_invoice(&invoice)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_invoice(
+    db: State<'_, DbState>,
+    id: i64,
+    invoice: UpdateInvoice,
+) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.update_invoice(id, &invoice)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_invoice_line(
+    db: State<'_, DbState>,
+    invoice_id: i64,
+    line: CreateManualInvoiceLine,
+) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.add_invoice_line(invoice_id, &line)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_invoice_line(
+    db: State<'_, DbState>,
+    id: i64,
+    line: CreateManualInvoiceLine,
+) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.update_invoice_line(id, &line)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_invoice_line(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.delete_invoice_line(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn mark_invoice_sent(
+    db: State<'_, DbState>,
+    id: i64,
+    sent_date: String,
+) -> Result<Invoice, String> {
+    let sent_date = chrono::NaiveDate::parse_from_str(&sent_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+
+    let db = db.lock().await;
+    db.mark_invoice_sent(id, sent_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_invoice_changes(
+    db: State<'_, DbState>,
+    invoice_id: i64,
+) -> Result<Vec<InvoiceChange>, String> {
+    let db = db.lock().await;
+    db.get_invoice_changes(invoice_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn mark_invoice_paid(
+    db: State<'_, DbState>,
+    id: i64,
+    payment_date: String,
+) -> Result<Invoice, String> {
+    let payment_date = chrono::NaiveDate::parse_from_str(&payment_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+
+    let db = db.lock().await;
+    db.mark_invoice_paid(id, payment_date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_write_off_candidates(
+    db: State<'_, DbState>,
+    due_before: String,
+) -> Result<Vec<Invoice>, String> {
+    let due_before = chrono::NaiveDate::parse_from_str(&due_before, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+
+    let db = db.lock().await;
+    db.get_write_off_candidates(due_before)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn write_off_invoices(
+    db: State<'_, DbState>,
+    write_off: CreateWriteOff,
+) -> Result<Vec<Invoice>, String> {
+    let db = db.lock().await;
+    db.write_off_invoices(&write_off).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn cancel_invoice_write_off(db: State<'_, DbState>, id: i64) -> Result<Invoice, String> {
+    let db = db.lock().await;
+    db.cancel_invoice_write_off(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn clear_member_expulsion_review(
+    db: State<'_, DbState>,
+    member_id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.clear_member_expulsion_review(member_id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_invoice(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_invoice(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_membership_fee(
+    db: State<'_, DbState>,
+    id: i64,
+) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_membership_fee(id).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_account_mappings(db: State<'_, DbState>) -> Result<Vec<AccountMapping>, String> {
+    let db = db.lock().await;
+    db.get_account_mappings().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_account_mapping(
+    db: State<'_, DbState>,
+    mapping: CreateAccountMapping,
+) -> Result<AccountMapping, String> {
+    let db = db.lock().await;
+    db.set_account_mapping(&mapping).await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_account_mapping(db: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_account_mapping(id).await.map_err(|e| e.to_string())
+}
+
+fn parse_date_range(
+    start_date: &str,
+    end_date: &str,
+) -> Result<(chrono::NaiveDate, chrono::NaiveDate), String> {
+    let start = chrono::NaiveDate::parse_from_str(start_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+    let end = chrono::NaiveDate::parse_from_str(end_date, "%Y-%m-%d")
+        .map_err(|e| format!("Invalid date format: {}", e))?;
+    Ok((start, end))
+}
+
+#[tauri::command]
+pub async fn get_journal(
+    db: State<'_, DbState>,
+    start_date: String,
+    end_date: String,
+) -> Result<Journal, String> {
+    let (start, end) = parse_date_range(&start_date, &end_date)?;
+
+    let db = db.lock().await;
+    db.get_journal(start, end).await.map_err(|e| e.to_string())
+}
+
+// Tallentaa tositteet tiedostoon: .json-päätteellä JSON, muuten CSV
+#[tauri::command]
+pub async fn export_journal(
+    db: State<'_, DbState>,
+    start_date: String,
+    end_date: String,
+    file_path: String,
+) -> Result<(), String> {
+    let (start, end) = parse_date_range(&start_date, &end_date)?;
+    let journal = {
+        let db = db.lock().await;
+        db.get_journal(start, end).await.map_err(|e| e.to_string())?
+    };
+
+    let content = if file_path.to_lowercase().ends_with(".json") {
+        serde_json::to_string_pretty(&journal).map_err(|e| e.to_string())?
+    } else {
+        export::journal_csv(&journal)
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn export_to_tilitin(
+    db: State<'_, DbState>,
+    start_date: String,
+    end_date: String,
+    tilitin_path: String,
+) -> Result<TilitinExport, String> {
+    let (start, end) = parse_date_range(&start_date, &end_date)?;
+
+    let db = db.lock().await;
+    db.export_to_tilitin(start, end, &tilitin_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Raportin päivämäärä: annettu tai tämä päivä
+fn parse_report_date(date: Option<String>) -> Result<chrono::NaiveDate, String> {
+    match date {
+        Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|e| format!("Invalid date format: {}", e)),
+        None => Ok(chrono::Utc::now().date_naive()),
+    }
+}
+
+#[tauri::command]
+pub async fn get_receivables_aging(
+    db: State<'_, DbState>,
+    date: Option<String>,
+) -> Result<AgingReport, String> {
+    let date = parse_report_date(date)?;
+    let db = db.lock().await;
+    db.get_receivables_aging(date)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_receivables_aging(
+    db: State<'_, DbState>,
+    date: Option<String>,
+    file_path: String,
+) -> Result<(), String> {
+    let date = parse_report_date(date)?;
+    let report = {
+        let db = db.lock().await;
+        db.get_receivables_aging(date)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let content = if file_path.to_lowercase().ends_with(".json") {
+        serde_json::to_string_pretty(&report).map_err(|e| e.to_string())?
+    } else {
+        export::aging_report_csv(&report)
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_membership_report(
+    db: State<'_, DbState>,
+    year: i32,
+) -> Result<MembershipReport, String> {
+    let db = db.lock().await;
+    db.get_membership_report(year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_member_register(
+    db: State<'_, DbState>,
+    options: MemberRegisterOptions,
+) -> Result<MemberRegister, String> {
+    let db = db.lock().await;
+    db.get_member_register(&options)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_member_register(
+    db: State<'_, DbState>,
+    options: MemberRegisterOptions,
+    file_path: String,
+) -> Result<(), String> {
+    let register = {
+        let db = db.lock().await;
+        db.get_member_register(&options)
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let content = if file_path.to_lowercase().ends_with(".json") {
+        serde_json::to_string_pretty(&register).map_err(|e| e.to_string())?
+    } else {
+        export::member_register_csv(&register)
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_voting_list(
+    db: State<'_, DbState>,
+    year: i32,
+    include_exempt: Option<bool>,
+) -> Result<VotingList, String> {
+    let db = db.lock().await;
+    db.get_voting_list(year, include_exempt.unwrap_or(false))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_voting_list(
+    db: State<'_, DbState>,
+    year: i32,
+    include_exempt: Option<bool>,
+    file_path: String,
+) -> Result<(), String> {
+    let list = {
+        let db = db.lock().await;
+        db.get_voting_list(year, include_exempt.unwrap_or(false))
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let content = if file_path.to_lowercase().ends_with(".json") {
+        serde_json::to_string_pretty(&list).map_err(|e| e.to_string())?
+    } else {
+        export::voting_list_csv(&list)
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_statistics(
+    db: State<'_, DbState>,
+    start_year: i32,
+    end_year: i32,
+) -> Result<Statistics, String> {
+    let db = db.lock().await;
+    db.get_statistics(start_year, end_year)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_report_fields(source: ReportSource) -> Result<Vec<ReportFieldInfo>, String> {
+    Ok(report_builder::field_infos(source))
+}
+
+#[tauri::command]
+pub async fn get_report_definitions(db: State<'_, DbState>) -> Result<Vec<ReportDefinition>, String> {
+    let db = db.lock().await;
+    db.get_report_definitions().await.map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_report_definition(
+    db: State<'_, DbState>,
+    definition: CreateReportDefinition,
+) -> Result<ReportDefinition, String> {
+    let db = db.lock().await;
+    db.create_report_definition(&definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_report_definition(
+    db: State<'_, DbState>,
+    id: i64,
+    definition: CreateReportDefinition,
+) -> Result<ReportDefinition, String> {
+    let db = db.lock().await;
+    db.update_report_definition(id, &definition)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_report_definition(db: State<'_, DbState>, id: i64) -> Result<(), String> {
+    let db = db.lock().await;
+    db.delete_report_definition(id)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn run_report(
+    db: State<'_, DbState>,
+    id: i64,
+    params: Option<HashMap<String, String>>,
+) -> Result<ReportResult, String> {
+    let db = db.lock().await;
+    db.run_saved_report(id, &params.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+// Tallentamattoman raportin esikatselu editorissa
+#[tauri::command]
+pub async fn preview_report(
+    db: State<'_, DbState>,
+    definition: CreateReportDefinition,
+    params: Option<HashMap<String, String>>,
+) -> Result<ReportResult, String> {
+    let db = db.lock().await;
+    db.run_report(&definition.nimi, &definition, &params.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn export_report(
+    db: State<'_, DbState>,
+    id: i64,
+    params: Option<HashMap<String, String>>,
+    file_path: String,
+) -> Result<(), String> {
+    let result = {
+        let db = db.lock().await;
+        db.run_saved_report(id, &params.unwrap_or_default())
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    let lower = file_path.to_lowercase();
+    let content = if lower.ends_with(".xlsx") {
+        export::report_xlsx(&result)
+    } else if lower.ends_with(".json") {
+        serde_json::to_vec_pretty(&result).map_err(|e| e.to_string())?
+    } else {
+        export::report_csv(&result).into_bytes()
+    };
+
+    std::fs::write(&file_path, content).map_err(|e| format!("Failed to write file: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_dashboard_stats(
+    db: State<'_, DbState>,
+    year: Option<i32>,
+) -> Result<DashboardStats, String> {
     let db = db.lock().await;
     
-    let current_year = chrono::Utc::now().year();
+    let current_year = year.unwrap_or_else(|| chrono::Utc::now().year());
     
     let total_members = db.get_total_members().await.map_err(|e| e.to_string())?;
     let open_invoices = db.get_open_invoices_count().await.map_err(|e| e.to_string())?;
@@ -1,6 +1,12 @@
+use crate::fee_rules::{self, HouseholdMember, InvoiceDraft, LineDraft};
+use crate::journal::{self, JournalCredit, JournalInvoice, JournalLine};
+use crate::report_builder::{self, BindValue};
+use crate::reports::{self, AgingInvoice, ReportMember, VotingMember};
+use crate::tilitin;
 use crate::models::*;
 use anyhow::Result;
-use sqlx::{migrate::MigrateDatabase, Pool, Row, Sqlite};
+use chrono::Datelike;
+use sqlx::{migrate::MigrateDatabase, Connection, Pool, Row, Sqlite, SqliteConnection};
 use std::path::PathBuf;
 
 // Suomalaisen viitenumeron tarkistussumman laskenta
@@ -19,6 +25,63 @@ fn calculate_finnish_check_digit(reference: &str) -> u8 {
     check_digit as u8
 }
 
+// Y-tunnuksen muoto 1234567-8 ja tarkistusnumero (painot 7, 9, 10, 5, 8, 4, 2)
+fn is_valid_business_id(business_id: &str) -> bool {
+    let Some((number, check)) = business_id.split_once('-') else {
+        return false;
+    };
+    if number.len() != 7 || check.len() != 1 {
+        return false;
+    }
+    let digits: Vec<u32> = number.chars().filter_map(|c| c.to_digit(10)).collect();
+    let Some(check) = check.chars().next().and_then(|c| c.to_digit(10)) else {
+        return false;
+    };
+    if digits.len() != 7 {
+        return false;
+    }
+
+    let weights = [7, 9, 10, 5, 8, 4, 2];
+    let sum: u32 = digits.iter().zip(weights).map(|(d, w)| d * w).sum();
+    match sum % 11 {
+        0 => check == 0,
+        1 => false,
+        remainder => check == 11 - remainder,
+    }
+}
+
+// Täysinä vuosina annettuna päivänä
+fn age_on(birth_date: chrono::NaiveDate, date: chrono::NaiveDate) -> i32 {
+    let mut age = date.year() - birth_date.year();
+    if (date.month(), date.day()) < (birth_date.month(), birth_date.day()) {
+        age -= 1;
+    }
+    age
+}
+
+// Siirtymäsäännön leikkuripäivä (KK-PP) annettuna vuonna
+fn cutoff_date(year: i32, leikkuripaiva: &str) -> Option<chrono::NaiveDate> {
+    chrono::NaiveDate::parse_from_str(&format!("{}-{}", year, leikkuripaiva.trim()), "%Y-%m-%d")
+        .ok()
+}
+
+// Jäsenen ikä siirtymäsäännön laskentatavalla: vuositasolla maksuvuosi miinus
+// syntymävuosi, tai tarkka ikä maksuvuoden leikkuripäivänä (oletus 31.12.)
+fn transition_age(rule: &MemberTypeRule, birth_date: chrono::NaiveDate, year: i32) -> i32 {
+    match rule.laskentatapa.parse().unwrap_or_default() {
+        AgeBasis::Vuosi => year - birth_date.year(),
+        AgeBasis::Paiva => {
+            let cutoff = rule
+                .leikkuripaiva
+                .as_deref()
+                .and_then(|d| cutoff_date(year, d))
+                .or_else(|| chrono::NaiveDate::from_ymd_opt(year, 12, 31))
+                .unwrap_or(birth_date);
+            age_on(birth_date, cutoff)
+        }
+    }
+}
+
 pub struct Database {
     pool: Pool<Sqlite>,
 }
@@ -50,52 +113,171 @@ impl Database {
             .connect(&db_url)
             .await?;
 
-        // Run migrations manually - log errors but continue if table/column already exists
-        if let Err(e) = sqlx::query(include_str!("../migrations/001_initial.sql"))
-            .execute(&pool)
-            .await
-        {
-            // Only log if it's not a "table already exists" error
-            let err_str = e.to_string();
-            if !err_str.contains("already exists") {
-                eprintln!("Migration 001 warning: {}", err_str);
+        Self::run_migrations(&pool).await?;
+
+        Ok(Database { pool })
+    }
+
+    // Ajaa migraatiot, joita tietokantaan ei ole vielä ajettu. Viimeisin ajettu
+    // migraatio tallennetaan PRAGMA user_version -arvoon, jotta taulut uudelleen
+    // rakentavat migraatiot (004, 006) eivät aja jokaisella käynnistyskerralla.
+    // Vanhoissa tietokannoissa user_version on 0, jolloin 001-004 ajetaan kerran
+    // uudelleen, joten migraatioiden on oltava toistettavia.
+    //
+    // Jokainen migraatio ajetaan omassa transaktiossaan yhdessä user_version-
+    // päivityksen kanssa. Mikä tahansa virhe peruu koko migraation ja keskeyttää
+    // käynnistyksen, joten migraatiota ei merkitä ajetuksi puolinaisena.
+    // Vierasavaimet ovat pois päältä migraatioiden ajan, jotta taulujen
+    // uudelleenrakennus ei poista viittaavia rivejä; PRAGMA foreign_keys ei
+    // toimi transaktion sisällä.
+    async fn run_migrations(pool: &Pool<Sqlite>) -> Result<()> {
+        let migrations: &[(i32, &str)] = &[
+            (1, include_str!("../migrations/001_initial.sql")),
+            (2, include_str!("../migrations/002_add_fields.sql")),
+            (3, include_str!("../migrations/003_add_youth_member_age_limit.sql")),
+            (4, include_str!("../migrations/004_update_member_type_constraints.sql")),
+            (5, include_str!("../migrations/005_add_fee_rules.sql")),
+            (6, include_str!("../migrations/006_add_member_types.sql")),
+            (7, include_str!("../migrations/007_add_fee_items.sql")),
+            (8, include_str!("../migrations/008_add_invoice_message.sql")),
+            (9, include_str!("../migrations/009_add_invoice_editing.sql")),
+            (10, include_str!("../migrations/010_add_billing_periods.sql")),
+            (11, include_str!("../migrations/011_add_invoice_batches.sql")),
+            (12, include_str!("../migrations/012_add_billing_mode.sql")),
+            (13, include_str!("../migrations/013_add_member_guardians.sql")),
+            (14, include_str!("../migrations/014_add_customers.sql")),
+            (15, include_str!("../migrations/015_add_corporate_members.sql")),
+            (16, include_str!("../migrations/016_add_member_type_rules.sql")),
+            (17, include_str!("../migrations/017_add_account_mappings.sql")),
+            (18, include_str!("../migrations/018_add_tilitin_exports.sql")),
+            (19, include_str!("../migrations/019_add_write_offs.sql")),
+            (20, include_str!("../migrations/020_add_household_credits.sql")),
+            (21, include_str!("../migrations/021_add_member_resignation.sql")),
+            (22, include_str!("../migrations/022_add_report_definitions.sql")),
+            (23, include_str!("../migrations/023_add_tilitin_export_database.sql")),
+            (24, include_str!("../migrations/024_add_invoice_payer.sql")),
+            (25, include_str!("../migrations/025_add_unique_invoice_number.sql")),
+        ];
+
+        // Sama yhteys koko ajon ajan, koska PRAGMA foreign_keys on yhteyskohtainen
+        let mut conn = pool.acquire().await?;
+
+        let current_version: i32 = sqlx::query("PRAGMA user_version")
+            .fetch_one(&mut *conn)
+            .await?
+            .get(0);
+
+        sqlx::query("PRAGMA foreign_keys = OFF")
+            .execute(&mut *conn)
+            .await?;
+
+        let result = Self::apply_migrations(&mut conn, migrations, current_version).await;
+
+        sqlx::query("PRAGMA foreign_keys = ON")
+            .execute(&mut *conn)
+            .await?;
+
+        result
+    }
+
+    async fn apply_migrations(
+        conn: &mut SqliteConnection,
+        migrations: &[(i32, &str)],
+        current_version: i32,
+    ) -> Result<()> {
+        for &(version, sql) in migrations {
+            if version <= current_version {
+                continue;
             }
-        }
 
-        // Run second migration
-        if let Err(e) = sqlx::query(include_str!("../migrations/002_add_fields.sql"))
-            .execute(&pool)
-            .await
-        {
-            let err_str = e.to_string();
-            if !err_str.contains("duplicate column") && !err_str.contains("already exists") {
-                eprintln!("Migration 002 warning: {}", err_str);
+            // Keskeytetty transaktio perutaan, kun se pudotetaan
+            let mut transaction = conn.begin().await?;
+            for statement in Self::split_statements(sql) {
+                if let Some((table, column)) = Self::added_column(statement) {
+                    // SQLitessä ei ole ADD COLUMN IF NOT EXISTS -muotoa
+                    let exists: i64 = sqlx::query(
+                        "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+                    )
+                    .bind(table)
+                    .bind(column)
+                    .fetch_one(&mut *transaction)
+                    .await?
+                    .get(0);
+                    if exists > 0 {
+                        continue;
+                    }
+                }
+
+                sqlx::query(statement)
+                    .execute(&mut *transaction)
+                    .await
+                    .map_err(|e| anyhow::anyhow!("Migraatio {:03} epäonnistui: {}", version, e))?;
             }
+
+            sqlx::query(&format!("PRAGMA user_version = {}", version))
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await?;
         }
 
-        // Run third migration
-        if let Err(e) = sqlx::query(include_str!("../migrations/003_add_youth_member_age_limit.sql"))
-            .execute(&pool)
-            .await
-        {
-            let err_str = e.to_string();
-            if !err_str.contains("duplicate column") && !err_str.contains("already exists") {
-                eprintln!("Migration 003 warning: {}", err_str);
+        Ok(())
+    }
+
+    // Jakaa migraation lauseisiin puolipisteistä. Merkkijonojen sisällä olevat
+    // puolipisteet ja kommentit ohitetaan; migraatioissa ei ole triggereitä.
+    fn split_statements(sql: &str) -> Vec<&str> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        let mut in_string = false;
+        let mut in_comment = false;
+        let mut chars = sql.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\n' if in_comment => in_comment = false,
+                _ if in_comment => {}
+                '\'' => in_string = !in_string,
+                '-' if !in_string && chars.peek().map(|&(_, n)| n) == Some('-') => {
+                    in_comment = true
+                }
+                ';' if !in_string => {
+                    statements.push(&sql[start..i]);
+                    start = i + 1;
+                }
+                _ => {}
             }
         }
+        statements.push(&sql[start..]);
+
+        statements
+            .into_iter()
+            .map(str::trim)
+            .filter(|statement| {
+                statement
+                    .lines()
+                    .any(|line| !line.trim().is_empty() && !line.trim().starts_with("--"))
+            })
+            .collect()
+    }
 
-        // Run fourth migration
-        if let Err(e) = sqlx::query(include_str!("../migrations/004_update_member_type_constraints.sql"))
-            .execute(&pool)
-            .await
-        {
-            let err_str = e.to_string();
-            if !err_str.contains("already exists") {
-                eprintln!("Migration 004 warning: {}", err_str);
+    // Taulu ja sarake, jos lause on "ALTER TABLE taulu ADD COLUMN sarake ..."
+    fn added_column(statement: &str) -> Option<(&str, &str)> {
+        let code = statement
+            .lines()
+            .filter(|line| !line.trim().starts_with("--"))
+            .flat_map(str::split_whitespace);
+        let words: Vec<&str> = code.take(6).collect();
+        match words.as_slice() {
+            [alter, table_kw, table, add, column_kw, column]
+                if alter.eq_ignore_ascii_case("ALTER")
+                    && table_kw.eq_ignore_ascii_case("TABLE")
+                    && add.eq_ignore_ascii_case("ADD")
+                    && column_kw.eq_ignore_ascii_case("COLUMN") =>
+            {
+                Some((*table, *column))
             }
+            _ => None,
         }
-
-        Ok(Database { pool })
     }
 
     pub async fn close(&self) {
@@ -132,6 +314,7 @@ impl Database {
                 pankkitili: row.get("pankkitili"),
                 bic: row.get("bic"),
                 nuorisojasen_ikaraja: row.get("nuorisojasen_ikaraja"),
+                laskutustapa: row.get("laskutustapa"),
                 created_at: row.get("created_at"),
                 updated_at: row.get("updated_at"),
             }))
@@ -141,20 +324,35 @@ impl Database {
     }
 
     pub async fn create_member(&self, member: &CreateMember) -> Result<Member> {
+        let member = &Self::normalize_member(member)?;
+        let member_type = self.resolve_member_type(&member.jasentyyppi).await?;
+        Self::check_member_type_age(&member_type, member.syntymaaika)?;
+
         let id = sqlx::query(
             "INSERT INTO members (etunimi, sukunimi, henkilotunnus, syntymaaika, 
-             puhelinnumero, sahkoposti, osoite_id, liittymispaiva, jasentyyppi, aktiivinen)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+             puhelinnumero, sahkoposti, osoite_id, liittymispaiva, jasentyyppi, aktiivinen, oma_lasku,
+             jasenlaji, y_tunnus, yhteyshenkilo, laskuviite, verkkolaskuosoite, valittajatunnus,
+             eroamispaiva)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?,
+                     CASE WHEN ? THEN NULL ELSE date('now') END)",
         )
         .bind(&member.etunimi)
         .bind(&member.sukunimi)
         .bind(&member.henkilotunnus)
-        .bind(&member.syntymaaika)
+        .bind(member.syntymaaika)
         .bind(&member.puhelinnumero)
         .bind(&member.sahkoposti)
         .bind(member.osoite_id)
-        .bind(&member.liittymispaiva)
-        .bind(member.jasentyyppi.to_string())
+        .bind(member.liittymispaiva)
+        .bind(&member_type.nimi)
+        .bind(member.aktiivinen)
+        .bind(member.oma_lasku)
+        .bind(member.jasenlaji.to_string())
+        .bind(&member.y_tunnus)
+        .bind(&member.yhteyshenkilo)
+        .bind(&member.laskuviite)
+        .bind(&member.verkkolaskuosoite)
+        .bind(&member.valittajatunnus)
         .bind(member.aktiivinen)
         .execute(&self.pool)
         .await?
@@ -178,6 +376,15 @@ impl Database {
             liittymispaiva: row.get("liittymispaiva"),
             jasentyyppi: row.get("jasentyyppi"),
             aktiivinen: row.get("aktiivinen"),
+            oma_lasku: row.get("oma_lasku"),
+            jasenlaji: row.get("jasenlaji"),
+            y_tunnus: row.get("y_tunnus"),
+            yhteyshenkilo: row.get("yhteyshenkilo"),
+            laskuviite: row.get("laskuviite"),
+            verkkolaskuosoite: row.get("verkkolaskuosoite"),
+            valittajatunnus: row.get("valittajatunnus"),
+            erottaminen_esitetty: row.try_get("erottaminen_esitetty").ok().flatten(),
+            eroamispaiva: row.try_get("eroamispaiva").ok().flatten(),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -212,7 +419,9 @@ impl Database {
             "SELECT 
                 m.id, m.etunimi, m.sukunimi, m.henkilotunnus, m.syntymaaika,
                 m.puhelinnumero, m.sahkoposti, m.osoite_id, m.liittymispaiva,
-                m.jasentyyppi, m.aktiivinen, m.created_at, m.updated_at,
+                m.jasentyyppi, m.aktiivinen, m.oma_lasku, m.jasenlaji, m.y_tunnus, m.yhteyshenkilo,
+                m.laskuviite, m.verkkolaskuosoite, m.valittajatunnus, m.erottaminen_esitetty, m.eroamispaiva,
+                m.created_at, m.updated_at,
                 a.katuosoite, a.postinumero, a.postitoimipaikka, a.talous_id,
                 a.created_at as address_created_at, a.updated_at as address_updated_at,
                 h.talouden_nimi, h.vastaanottaja, h.laskutusosoite_sama, h.laskutusosoite_id,
@@ -239,6 +448,15 @@ impl Database {
                 liittymispaiva: row.get("liittymispaiva"),
                 jasentyyppi: row.get("jasentyyppi"),
                 aktiivinen: row.get("aktiivinen"),
+                oma_lasku: row.get("oma_lasku"),
+                jasenlaji: row.get("jasenlaji"),
+                y_tunnus: row.get("y_tunnus"),
+                yhteyshenkilo: row.get("yhteyshenkilo"),
+                laskuviite: row.get("laskuviite"),
+                verkkolaskuosoite: row.get("verkkolaskuosoite"),
+                valittajatunnus: row.get("valittajatunnus"),
+                erottaminen_esitetty: row.try_get("erottaminen_esitetty").ok().flatten(),
+                eroamispaiva: row.try_get("eroamispaiva").ok().flatten(),
                 created_at: row.get("created_at"),
                 updated_at: row.get("updated_at"),
             };
@@ -285,7 +503,7 @@ impl Database {
                 "UPDATE organization SET
                  nimi = ?, katuosoite = ?, postinumero = ?, postitoimipaikka = ?,
                  puhelinnumero = ?, sahkoposti = ?, y_tunnus = ?, pankkitili = ?, bic = ?,
-                 nuorisojasen_ikaraja = ?, updated_at = CURRENT_TIMESTAMP
+                 nuorisojasen_ikaraja = ?, laskutustapa = ?, updated_at = CURRENT_TIMESTAMP
                  WHERE id = (SELECT id FROM organization LIMIT 1)",
             )
             .bind(&org.nimi)
@@ -298,13 +516,14 @@ impl Database {
             .bind(&org.pankkitili)
             .bind(&org.bic)
             .bind(org.nuorisojasen_ikaraja)
+            .bind(org.laskutustapa.to_string())
             .execute(&self.pool)
             .await?;
         } else {
             // Insert new organization
             sqlx::query(
-                "INSERT INTO organization (nimi, katuosoite, postinumero, postitoimipaikka, puhelinnumero, sahkoposti, y_tunnus, pankkitili, bic, nuorisojasen_ikaraja)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+                "INSERT INTO organization (nimi, katuosoite, postinumero, postitoimipaikka, puhelinnumero, sahkoposti, y_tunnus, pankkitili, bic, nuorisojasen_ikaraja, laskutustapa)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             )
             .bind(&org.nimi)
             .bind(&org.katuosoite)
@@ -316,32 +535,69 @@ impl Database {
             .bind(&org.pankkitili)
             .bind(&org.bic)
             .bind(org.nuorisojasen_ikaraja)
+            .bind(org.laskutustapa.to_string())
             .execute(&self.pool)
             .await?;
         }
 
+        // Nuorisojäsenen ikäraja on jäsentyyppitaulussa yläikärajana (ikäraja - 1)
+        sqlx::query(
+            "UPDATE member_types SET ika_max = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE nimi = 'nuorisojasen'",
+        )
+        .bind(org.nuorisojasen_ikaraja - 1)
+        .execute(&self.pool)
+        .await?;
+
         // Return updated organization
         let org = self.get_organization().await?;
         Ok(org.ok_or_else(|| sqlx::Error::RowNotFound)?)
     }
 
     pub async fn update_member(&self, id: i64, member: &CreateMember) -> Result<Member> {
+        let member = &Self::normalize_member(member)?;
+        let member_type = self.resolve_member_type(&member.jasentyyppi).await?;
+
+        // Ikäraja tarkistetaan vain tyypin vaihtuessa, jotta esim. ikärajan jo
+        // ylittäneen nuorisojäsenen muut tiedot voi päivittää ennen tyyppimuutosta
+        let current_type: Option<String> =
+            sqlx::query("SELECT jasentyyppi FROM members WHERE id = ?")
+                .bind(id)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|row| row.get("jasentyyppi"));
+        if current_type.as_deref() != Some(member_type.nimi.as_str()) {
+            Self::check_member_type_age(&member_type, member.syntymaaika)?;
+        }
+
         sqlx::query(
             "UPDATE members SET 
              etunimi = ?, sukunimi = ?, henkilotunnus = ?, syntymaaika = ?,
              puhelinnumero = ?, sahkoposti = ?, osoite_id = ?, liittymispaiva = ?,
-             jasentyyppi = ?, aktiivinen = ?, updated_at = CURRENT_TIMESTAMP
+             jasentyyppi = ?, aktiivinen = ?, oma_lasku = ?, jasenlaji = ?, y_tunnus = ?,
+             yhteyshenkilo = ?, laskuviite = ?, verkkolaskuosoite = ?, valittajatunnus = ?,
+             eroamispaiva = CASE WHEN ? THEN NULL ELSE COALESCE(eroamispaiva, date('now')) END,
+             updated_at = CURRENT_TIMESTAMP
              WHERE id = ?",
         )
         .bind(&member.etunimi)
         .bind(&member.sukunimi)
         .bind(&member.henkilotunnus)
-        .bind(&member.syntymaaika)
+        .bind(member.syntymaaika)
         .bind(&member.puhelinnumero)
         .bind(&member.sahkoposti)
         .bind(member.osoite_id)
-        .bind(&member.liittymispaiva)
-        .bind(member.jasentyyppi.to_string())
+        .bind(member.liittymispaiva)
+        .bind(&member_type.nimi)
+        .bind(member.aktiivinen)
+        .bind(member.oma_lasku)
+        .bind(member.jasenlaji.to_string())
+        .bind(&member.y_tunnus)
+        .bind(&member.yhteyshenkilo)
+        .bind(&member.laskuviite)
+        .bind(&member.verkkolaskuosoite)
+        .bind(&member.valittajatunnus)
+        // Ei-aktiiviseksi merkitylle kirjataan eroamispäivä, uudelleen aktivoidulta se poistetaan
         .bind(member.aktiivinen)
         .bind(id)
         .execute(&self.pool)
@@ -365,6 +621,15 @@ impl Database {
             liittymispaiva: row.get("liittymispaiva"),
             jasentyyppi: row.get("jasentyyppi"),
             aktiivinen: row.get("aktiivinen"),
+            oma_lasku: row.get("oma_lasku"),
+            jasenlaji: row.get("jasenlaji"),
+            y_tunnus: row.get("y_tunnus"),
+            yhteyshenkilo: row.get("yhteyshenkilo"),
+            laskuviite: row.get("laskuviite"),
+            verkkolaskuosoite: row.get("verkkolaskuosoite"),
+            valittajatunnus: row.get("valittajatunnus"),
+            erottaminen_esitetty: row.try_get("erottaminen_esitetty").ok().flatten(),
+            eroamispaiva: row.try_get("eroamispaiva").ok().flatten(),
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
@@ -543,6 +808,422 @@ impl Database {
         })
     }
 
+    fn member_type_from_row(row: &sqlx::sqlite::SqliteRow) -> MemberType {
+        MemberType {
+            id: row.get("id"),
+            nimi: row.get("nimi"),
+            kuvaus: row.get("kuvaus"),
+            ika_min: row.get("ika_min"),
+            ika_max: row.get("ika_max"),
+            laskutettava: row.get("laskutettava"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_member_types(&self) -> Result<Vec<MemberType>> {
+        let rows = sqlx::query("SELECT * FROM member_types ORDER BY id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::member_type_from_row).collect())
+    }
+
+    // Hakee jäsentyypin nimellä kirjainkoosta välittämättä ("Varsinainen" -> "varsinainen").
+    // Vertailu tehdään Rustissa, koska SQLiten lower() ei muunna ääkkösiä.
+    pub async fn resolve_member_type(&self, name: &str) -> Result<MemberType> {
+        let wanted = name.trim().to_lowercase();
+
+        self.get_member_types()
+            .await?
+            .into_iter()
+            .find(|member_type| member_type.nimi.to_lowercase() == wanted)
+            .ok_or_else(|| anyhow::anyhow!("Tuntematon jäsentyyppi: {}", name))
+    }
+
+    /// Tarkistaa jäsenlajin mukaiset tiedot ja siistii tyhjät kentät pois.
+    /// Henkilöjäsenellä on etu- ja sukunimi eikä yhteisön tietoja. Yhteisöjäsenen
+    /// nimi on sukunimi-kentässä, eikä sillä ole henkilötunnusta tai syntymäaikaa.
+    fn normalize_member(member: &CreateMember) -> Result<CreateMember> {
+        let trimmed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+
+        let mut normalized = CreateMember {
+            etunimi: member.etunimi.trim().to_string(),
+            sukunimi: member.sukunimi.trim().to_string(),
+            henkilotunnus: trimmed(&member.henkilotunnus),
+            puhelinnumero: trimmed(&member.puhelinnumero),
+            sahkoposti: trimmed(&member.sahkoposti),
+            y_tunnus: trimmed(&member.y_tunnus),
+            yhteyshenkilo: trimmed(&member.yhteyshenkilo),
+            laskuviite: trimmed(&member.laskuviite),
+            verkkolaskuosoite: trimmed(&member.verkkolaskuosoite),
+            valittajatunnus: trimmed(&member.valittajatunnus),
+            ..member.clone()
+        };
+
+        match normalized.jasenlaji {
+            MemberKind::Henkilo => {
+                if normalized.etunimi.is_empty() || normalized.sukunimi.is_empty() {
+                    return Err(anyhow::anyhow!("Jäsenen etu- ja sukunimi ovat pakollisia."));
+                }
+                if normalized.y_tunnus.is_some() {
+                    return Err(anyhow::anyhow!("Henkilöjäsenellä ei voi olla y-tunnusta."));
+                }
+                // Yhteyshenkilö ja verkkolaskuosoite koskevat vain yhteisöjä
+                normalized.yhteyshenkilo = None;
+                normalized.verkkolaskuosoite = None;
+                normalized.valittajatunnus = None;
+            }
+            MemberKind::Yhteiso => {
+                // Yhteisön nimi voi tulla kumpaan tahansa nimikenttään
+                let nimi = format!("{} {}", normalized.etunimi, normalized.sukunimi)
+                    .trim()
+                    .to_string();
+                if nimi.is_empty() {
+                    return Err(anyhow::anyhow!("Yhteisöjäsenen nimi on pakollinen."));
+                }
+                if normalized.henkilotunnus.is_some() || normalized.syntymaaika.is_some() {
+                    return Err(anyhow::anyhow!(
+                        "Yhteisöjäsenellä ei voi olla henkilötunnusta tai syntymäaikaa."
+                    ));
+                }
+                normalized.etunimi = String::new();
+                normalized.sukunimi = nimi;
+            }
+        }
+
+        if let Some(y_tunnus) = &normalized.y_tunnus {
+            if !is_valid_business_id(y_tunnus) {
+                return Err(anyhow::anyhow!("Virheellinen y-tunnus: {}", y_tunnus));
+            }
+        }
+        if normalized.verkkolaskuosoite.is_some() != normalized.valittajatunnus.is_some() {
+            return Err(anyhow::anyhow!(
+                "Verkkolaskuosoitteelle on annettava myös välittäjätunnus."
+            ));
+        }
+
+        Ok(normalized)
+    }
+
+    fn check_member_type_age(
+        member_type: &MemberType,
+        syntymaaika: Option<chrono::NaiveDate>,
+    ) -> Result<()> {
+        let birth_date = match syntymaaika {
+            Some(date) => date,
+            None => return Ok(()),
+        };
+        let age = age_on(birth_date, chrono::Utc::now().date_naive());
+
+        if member_type.ika_min.is_some_and(|min| age < min)
+            || member_type.ika_max.is_some_and(|max| age > max)
+        {
+            let range = match (member_type.ika_min, member_type.ika_max) {
+                (Some(min), Some(max)) => format!("{}-{} v", min, max),
+                (Some(min), None) => format!("vähintään {} v", min),
+                (None, Some(max)) => format!("enintään {} v", max),
+                (None, None) => String::new(),
+            };
+            return Err(anyhow::anyhow!(
+                "Jäsentyypin {} ikäraja on {}, jäsenen ikä on {} v.",
+                member_type.nimi,
+                range,
+                age
+            ));
+        }
+
+        Ok(())
+    }
+
+    async fn validate_member_type(&self, member_type: &CreateMemberType) -> Result<String> {
+        let nimi = member_type.nimi.trim().to_lowercase();
+        if nimi.is_empty() {
+            return Err(anyhow::anyhow!("Jäsentyypin nimi on pakollinen."));
+        }
+
+        if let (Some(min), Some(max)) = (member_type.ika_min, member_type.ika_max) {
+            if min > max {
+                return Err(anyhow::anyhow!(
+                    "Jäsentyypin alaikäraja ei voi olla suurempi kuin yläikäraja."
+                ));
+            }
+        }
+
+        Ok(nimi)
+    }
+
+    pub async fn create_member_type(&self, member_type: &CreateMemberType) -> Result<MemberType> {
+        let nimi = self.validate_member_type(member_type).await?;
+
+        let id = sqlx::query(
+            "INSERT INTO member_types (nimi, kuvaus, ika_min, ika_max, laskutettava)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&nimi)
+        .bind(&member_type.kuvaus)
+        .bind(member_type.ika_min)
+        .bind(member_type.ika_max)
+        .bind(member_type.laskutettava)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        let row = sqlx::query("SELECT * FROM member_types WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Self::member_type_from_row(&row))
+    }
+
+    // Nimen muutos päivittyy jäseniin, jäsenmaksuihin ja siirtymäsääntöihin
+    // vierasavaimen ON UPDATE CASCADE -säännöllä
+    pub async fn update_member_type(
+        &self,
+        id: i64,
+        member_type: &CreateMemberType,
+    ) -> Result<MemberType> {
+        let nimi = self.validate_member_type(member_type).await?;
+
+        sqlx::query(
+            "UPDATE member_types SET
+             nimi = ?, kuvaus = ?, ika_min = ?, ika_max = ?, laskutettava = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&nimi)
+        .bind(&member_type.kuvaus)
+        .bind(member_type.ika_min)
+        .bind(member_type.ika_max)
+        .bind(member_type.laskutettava)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query("SELECT * FROM member_types WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Self::member_type_from_row(&row))
+    }
+
+    pub async fn delete_member_type(&self, id: i64) -> Result<()> {
+        let usage = sqlx::query(
+            "SELECT
+                (SELECT COUNT(*) FROM members m WHERE m.jasentyyppi = mt.nimi) as member_count,
+                (SELECT COUNT(*) FROM membership_fees mf WHERE mf.jasentyyppi = mt.nimi) as fee_count,
+                (SELECT COUNT(*) FROM member_type_rules r
+                 WHERE r.lahtotyyppi = mt.nimi OR r.kohdetyyppi = mt.nimi) as rule_count
+             FROM member_types mt WHERE mt.id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let row = match usage {
+            Some(row) => row,
+            None => return Err(anyhow::anyhow!("Jäsentyyppiä ei löytynyt ID:llä {}", id)),
+        };
+
+        if row.get::<i64, _>("member_count") > 0 || row.get::<i64, _>("fee_count") > 0 {
+            return Err(anyhow::anyhow!(
+                "Jäsentyyppiä ei voi poistaa, koska sitä käyttävät jäsenet tai jäsenmaksut."
+            ));
+        }
+
+        if row.get::<i64, _>("rule_count") > 0 {
+            return Err(anyhow::anyhow!(
+                "Jäsentyyppiä ei voi poistaa, koska siirtymäsäännöt käyttävät sitä."
+            ));
+        }
+
+        sqlx::query("DELETE FROM member_types WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn member_type_rule_from_row(row: &sqlx::sqlite::SqliteRow) -> MemberTypeRule {
+        MemberTypeRule {
+            id: row.get("id"),
+            nimi: row.get("nimi"),
+            lahtotyyppi: row.get("lahtotyyppi"),
+            kohdetyyppi: row.get("kohdetyyppi"),
+            ika_min: row.get("ika_min"),
+            ika_max: row.get("ika_max"),
+            laskentatapa: row.get("laskentatapa"),
+            leikkuripaiva: row.get("leikkuripaiva"),
+            jarjestys: row.get("jarjestys"),
+            aktiivinen: row.get("aktiivinen"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_member_type_rules(&self) -> Result<Vec<MemberTypeRule>> {
+        let rows = sqlx::query("SELECT * FROM member_type_rules ORDER BY jarjestys, id")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::member_type_rule_from_row).collect())
+    }
+
+    async fn get_member_type_rule(&self, id: i64) -> Result<MemberTypeRule> {
+        let row = sqlx::query("SELECT * FROM member_type_rules WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        match row {
+            Some(row) => Ok(Self::member_type_rule_from_row(&row)),
+            None => Err(anyhow::anyhow!("Siirtymäsääntöä ei löytynyt ID:llä {}", id)),
+        }
+    }
+
+    // Tarkistaa säännön ja palauttaa sen tyyppien nimet tietokannan muodossa
+    async fn validate_member_type_rule(
+        &self,
+        rule: &CreateMemberTypeRule,
+    ) -> Result<CreateMemberTypeRule> {
+        let lahtotyyppi = self.resolve_member_type(&rule.lahtotyyppi).await?.nimi;
+        let kohdetyyppi = self.resolve_member_type(&rule.kohdetyyppi).await?.nimi;
+        if lahtotyyppi == kohdetyyppi {
+            return Err(anyhow::anyhow!(
+                "Siirtymäsäännön lähtö- ja kohdetyyppi eivät voi olla samat."
+            ));
+        }
+
+        match (rule.ika_min, rule.ika_max) {
+            (None, None) => {
+                return Err(anyhow::anyhow!(
+                    "Siirtymäsäännölle on annettava ala- tai yläikäraja."
+                ))
+            }
+            (Some(min), Some(max)) if min > max => {
+                return Err(anyhow::anyhow!(
+                    "Siirtymäsäännön alaikäraja ei voi olla suurempi kuin yläikäraja."
+                ))
+            }
+            _ => {}
+        }
+
+        // Leikkuripäivä koskee vain päivämääräkohtaista laskentaa
+        let leikkuripaiva = match rule.laskentatapa {
+            AgeBasis::Vuosi => None,
+            AgeBasis::Paiva => rule
+                .leikkuripaiva
+                .as_deref()
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .map(|d| d.to_string()),
+        };
+        if let Some(date) = &leikkuripaiva {
+            // Tarkistus karkausvuodettomalla vuodella: 29.2. ei kelpaa leikkuripäiväksi
+            if cutoff_date(2023, date).is_none() {
+                return Err(anyhow::anyhow!(
+                    "Virheellinen leikkuripäivä {}: anna muodossa KK-PP, esim. 12-31.",
+                    date
+                ));
+            }
+        }
+
+        let nimi = match rule.nimi.trim() {
+            "" => format!("{} -> {}", lahtotyyppi, kohdetyyppi),
+            nimi => nimi.to_string(),
+        };
+
+        Ok(CreateMemberTypeRule {
+            nimi,
+            lahtotyyppi,
+            kohdetyyppi,
+            leikkuripaiva,
+            ..rule.clone()
+        })
+    }
+
+    pub async fn create_member_type_rule(
+        &self,
+        rule: &CreateMemberTypeRule,
+    ) -> Result<MemberTypeRule> {
+        let rule = self.validate_member_type_rule(rule).await?;
+
+        let id = sqlx::query(
+            "INSERT INTO member_type_rules (nimi, lahtotyyppi, kohdetyyppi, ika_min, ika_max,
+             laskentatapa, leikkuripaiva, jarjestys, aktiivinen)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&rule.nimi)
+        .bind(&rule.lahtotyyppi)
+        .bind(&rule.kohdetyyppi)
+        .bind(rule.ika_min)
+        .bind(rule.ika_max)
+        .bind(rule.laskentatapa.to_string())
+        .bind(&rule.leikkuripaiva)
+        .bind(rule.jarjestys)
+        .bind(rule.aktiivinen)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        self.get_member_type_rule(id).await
+    }
+
+    pub async fn update_member_type_rule(
+        &self,
+        id: i64,
+        rule: &CreateMemberTypeRule,
+    ) -> Result<MemberTypeRule> {
+        let rule = self.validate_member_type_rule(rule).await?;
+
+        let result = sqlx::query(
+            "UPDATE member_type_rules SET
+             nimi = ?, lahtotyyppi = ?, kohdetyyppi = ?, ika_min = ?, ika_max = ?,
+             laskentatapa = ?, leikkuripaiva = ?, jarjestys = ?, aktiivinen = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&rule.nimi)
+        .bind(&rule.lahtotyyppi)
+        .bind(&rule.kohdetyyppi)
+        .bind(rule.ika_min)
+        .bind(rule.ika_max)
+        .bind(rule.laskentatapa.to_string())
+        .bind(&rule.leikkuripaiva)
+        .bind(rule.jarjestys)
+        .bind(rule.aktiivinen)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Siirtymäsääntöä ei löytynyt ID:llä {}", id));
+        }
+
+        self.get_member_type_rule(id).await
+    }
+
+    // Säännöllä tehdyt muutokset jäävät lokiin ilman viittausta sääntöön
+    pub async fn delete_member_type_rule(&self, id: i64) -> Result<()> {
+        let result = sqlx::query("DELETE FROM member_type_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Siirtymäsääntöä ei löytynyt ID:llä {}", id));
+        }
+        Ok(())
+    }
+
     pub async fn get_membership_fees(&self) -> Result<Vec<MembershipFee>> {
         let rows = sqlx::query("SELECT * FROM membership_fees ORDER BY vuosi DESC, jasentyyppi")
             .fetch_all(&self.pool)
@@ -569,12 +1250,14 @@ impl Database {
     }
 
     pub async fn create_membership_fee(&self, fee: &CreateMembershipFee) -> Result<MembershipFee> {
+        let member_type = self.resolve_member_type(&fee.jasentyyppi).await?;
+
         let id = sqlx::query(
             "INSERT INTO membership_fees (vuosi, jasentyyppi, summa)
              VALUES (?, ?, ?)",
         )
         .bind(fee.vuosi)
-        .bind(&fee.jasentyyppi.to_string())
+        .bind(&member_type.nimi)
         .bind(fee.summa)
         .execute(&self.pool)
         .await?
@@ -583,7 +1266,7 @@ impl Database {
         Ok(MembershipFee {
             id,
             vuosi: fee.vuosi,
-            jasentyyppi: fee.jasentyyppi.to_string(),
+            jasentyyppi: member_type.nimi,
             summa: fee.summa,
             created_at: chrono::Utc::now(),
             updated_at: chrono::Utc::now(),
@@ -624,672 +1307,4355 @@ impl Database {
         })
     }
 
-    pub async fn get_invoices(&self) -> Result<Vec<InvoiceWithDetails>> {
-        let rows = sqlx::query(
-            "SELECT 
-                i.id as invoice_id, i.talous_id, i.luontipaiva, i.erapaiva, i.summa, 
-                i.viitenumero, i.laskunumero, i.maksettu, i.maksupaiva, 
-                i.created_at as invoice_created_at, i.updated_at as invoice_updated_at,
-                h.id as household_id, h.talouden_nimi, h.vastaanottaja, h.laskutusosoite_sama, h.laskutusosoite_id,
-                h.created_at as household_created_at, h.updated_at as household_updated_at,
-                a.id as address_id, a.katuosoite, a.postinumero, a.postitoimipaikka,
-                a.created_at as address_created_at, a.updated_at as address_updated_at
-            FROM invoices i 
-            JOIN households h ON i.talous_id = h.id
-            JOIN addresses a ON h.id = a.talous_id
-            ORDER BY i.luontipaiva DESC"
-        )
-        .fetch_all(&self.pool)
-        .await?;
-
-        let mut invoices_with_details = Vec::new();
+    fn fee_rule_from_row(row: &sqlx::sqlite::SqliteRow) -> FeeRule {
+        let alennus_prosentti = match row.try_get::<Option<f64>, _>("alennus_prosentti") {
+            Ok(val) => val,
+            Err(_) => row
+                .get::<Option<i64>, _>("alennus_prosentti")
+                .map(|v| v as f64),
+        };
+        let summa = match row.try_get::<Option<f64>, _>("summa") {
+            Ok(val) => val,
+            Err(_) => row.get::<Option<i64>, _>("summa").map(|v| v as f64),
+        };
 
-        for row in rows {
-            let summa = match row.try_get::<f64, _>("summa") {
-                Ok(val) => val,
-                Err(_) => row.get::<i64, _>("summa") as f64,
-            };
+        FeeRule {
+            id: row.get("id"),
+            nimi: row.get("nimi"),
+            saantotyyppi: row.get("saantotyyppi"),
+            vuosi: row.get("vuosi"),
+            taydet_maksut: row.get("taydet_maksut"),
+            jasen_nro: row.get("jasen_nro"),
+            alennus_prosentti,
+            summa,
+            vahimmaisjasenet: row.get("vahimmaisjasenet"),
+            aktiivinen: row.get("aktiivinen"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
 
-            let invoice = Invoice {
-                id: row.get("invoice_id"),
-                talous_id: row.get("talous_id"),
-                luontipaiva: row.get("luontipaiva"),
-                erapaiva: row.get("erapaiva"),
-                summa,
-                viitenumero: row.get("viitenumero"),
-                laskunumero: row.try_get("laskunumero").ok().flatten(),
-                maksettu: row.get("maksettu"),
-                maksupaiva: row.get("maksupaiva"),
-                created_at: row.get("invoice_created_at"),
-                updated_at: row.get("invoice_updated_at"),
-            };
+    pub async fn get_fee_rules(&self) -> Result<Vec<FeeRule>> {
+        let rows = sqlx::query("SELECT * FROM fee_rules ORDER BY vuosi DESC, id")
+            .fetch_all(&self.pool)
+            .await?;
 
-            let household = Household {
-                id: row.get("household_id"),
-                talouden_nimi: row.get("talouden_nimi"),
-                vastaanottaja: row.try_get("vastaanottaja").ok().flatten(),
-                laskutusosoite_sama: row.get("laskutusosoite_sama"),
-                laskutusosoite_id: row.get("laskutusosoite_id"),
-                created_at: row.get("household_created_at"),
-                updated_at: row.get("household_updated_at"),
-            };
+        Ok(rows.iter().map(Self::fee_rule_from_row).collect())
+    }
 
-            let address = Address {
-                id: row.get("address_id"),
-                katuosoite: row.get("katuosoite"),
-                postinumero: row.get("postinumero"),
-                postitoimipaikka: row.get("postitoimipaikka"),
-                talous_id: row.get("talous_id"),
-                created_at: row.get("address_created_at"),
-                updated_at: row.get("address_updated_at"),
-            };
+    // Vuodelle voimassa olevat säännöt: vuosikohtaiset sekä kaikkia vuosia koskevat
+    async fn get_fee_rules_for_year(
+        conn: &mut SqliteConnection,
+        year: i32,
+    ) -> Result<Vec<FeeRule>> {
+        let rows = sqlx::query(
+            "SELECT * FROM fee_rules
+             WHERE aktiivinen = 1 AND (vuosi IS NULL OR vuosi = ?)
+             ORDER BY id",
+        )
+        .bind(year)
+        .fetch_all(conn)
+        .await?;
 
-            // Hae laskurivit tälle laskulle
-            let line_rows = sqlx::query(
-                "SELECT 
-                    il.id, il.lasku_id, il.jasen_id, il.kuvaus, il.summa, il.created_at,
-                    m.id as member_id, m.etunimi, m.sukunimi, m.henkilotunnus, m.syntymaaika,
-                    m.puhelinnumero, m.sahkoposti, m.osoite_id, m.liittymispaiva, m.jasentyyppi,
-                    m.aktiivinen, m.created_at as member_created_at, m.updated_at as member_updated_at
-                FROM invoice_lines il
-                JOIN members m ON il.jasen_id = m.id
-                WHERE il.lasku_id = ?
-                ORDER BY m.sukunimi, m.etunimi"
-            )
-            .bind(invoice.id)
+        Ok(rows.iter().map(Self::fee_rule_from_row).collect())
+    }
+
+    fn validate_fee_rule(rule: &CreateFeeRule) -> Result<()> {
+        if rule.nimi.trim().is_empty() {
+            return Err(anyhow::anyhow!("Maksusäännön nimi on pakollinen."));
+        }
+
+        match rule.saantotyyppi {
+            FeeRuleType::Katto => {
+                if rule.taydet_maksut.is_none_or(|n| n < 0) {
+                    return Err(anyhow::anyhow!(
+                        "Maksukatolle on määriteltävä täysien maksujen enimmäismäärä."
+                    ));
+                }
+            }
+            FeeRuleType::NnesJasen => {
+                if rule.jasen_nro.is_none_or(|n| n < 1) {
+                    return Err(anyhow::anyhow!(
+                        "Alennukselle on määriteltävä jäsen, josta alkaen alennus annetaan."
+                    ));
+                }
+                if rule
+                    .alennus_prosentti
+                    .is_none_or(|p| !(0.0..=100.0).contains(&p))
+                {
+                    return Err(anyhow::anyhow!(
+                        "Alennusprosentin on oltava välillä 0-100."
+                    ));
+                }
+            }
+            FeeRuleType::Perhemaksu => {
+                if rule.summa.is_none_or(|s| s < 0.0) {
+                    return Err(anyhow::anyhow!("Perhemaksulle on määriteltävä summa."));
+                }
+                if rule.vahimmaisjasenet.is_some_and(|n| n < 1) {
+                    return Err(anyhow::anyhow!(
+                        "Perhemaksun vähimmäisjäsenmäärän on oltava vähintään 1."
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_fee_rule(&self, rule: &CreateFeeRule) -> Result<FeeRule> {
+        Self::validate_fee_rule(rule)?;
+
+        let id = sqlx::query(
+            "INSERT INTO fee_rules (nimi, saantotyyppi, vuosi, taydet_maksut, jasen_nro,
+             alennus_prosentti, summa, vahimmaisjasenet, aktiivinen)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&rule.nimi)
+        .bind(rule.saantotyyppi.to_string())
+        .bind(rule.vuosi)
+        .bind(rule.taydet_maksut)
+        .bind(rule.jasen_nro)
+        .bind(rule.alennus_prosentti)
+        .bind(rule.summa)
+        .bind(rule.vahimmaisjasenet)
+        .bind(rule.aktiivinen)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        let row = sqlx::query("SELECT * FROM fee_rules WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Self::fee_rule_from_row(&row))
+    }
+
+    pub async fn update_fee_rule(&self, id: i64, rule: &CreateFeeRule) -> Result<FeeRule> {
+        Self::validate_fee_rule(rule)?;
+
+        sqlx::query(
+            "UPDATE fee_rules SET
+             nimi = ?, saantotyyppi = ?, vuosi = ?, taydet_maksut = ?, jasen_nro = ?,
+             alennus_prosentti = ?, summa = ?, vahimmaisjasenet = ?, aktiivinen = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&rule.nimi)
+        .bind(rule.saantotyyppi.to_string())
+        .bind(rule.vuosi)
+        .bind(rule.taydet_maksut)
+        .bind(rule.jasen_nro)
+        .bind(rule.alennus_prosentti)
+        .bind(rule.summa)
+        .bind(rule.vahimmaisjasenet)
+        .bind(rule.aktiivinen)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query("SELECT * FROM fee_rules WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Self::fee_rule_from_row(&row))
+    }
+
+    pub async fn delete_fee_rule(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM fee_rules WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn fee_item_from_row(row: &sqlx::sqlite::SqliteRow) -> FeeItem {
+        let summa = match row.try_get::<f64, _>("summa") {
+            Ok(val) => val,
+            Err(_) => row.get::<i64, _>("summa") as f64,
+        };
+
+        FeeItem {
+            id: row.get("id"),
+            nimi: row.get("nimi"),
+            summa,
+            peruste: row.get("peruste"),
+            jasentyyppi: row.get("jasentyyppi"),
+            vuosi: row.get("vuosi"),
+            aktiivinen: row.get("aktiivinen"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_fee_items(&self) -> Result<Vec<FeeItem>> {
+        let rows = sqlx::query("SELECT * FROM fee_items ORDER BY nimi")
             .fetch_all(&self.pool)
             .await?;
 
-            let mut lines = Vec::new();
-            for line_row in line_rows {
-                let line_summa = match line_row.try_get::<f64, _>("summa") {
-                    Ok(val) => val,
-                    Err(_) => line_row.get::<i64, _>("summa") as f64,
-                };
+        Ok(rows.iter().map(Self::fee_item_from_row).collect())
+    }
 
-                let line = InvoiceLine {
-                    id: line_row.get("id"),
-                    lasku_id: line_row.get("lasku_id"),
-                    jasen_id: line_row.get("jasen_id"),
-                    kuvaus: line_row.get("kuvaus"),
-                    summa: line_summa,
-                    created_at: line_row.get("created_at"),
-                };
+    async fn get_fee_items_for_year(
+        conn: &mut SqliteConnection,
+        year: i32,
+    ) -> Result<Vec<FeeItem>> {
+        let rows = sqlx::query(
+            "SELECT * FROM fee_items
+             WHERE aktiivinen = 1 AND (vuosi IS NULL OR vuosi = ?)
+             ORDER BY id",
+        )
+        .bind(year)
+        .fetch_all(conn)
+        .await?;
 
-                let member = Member {
-                    id: line_row.get("member_id"),
-                    etunimi: line_row.get("etunimi"),
-                    sukunimi: line_row.get("sukunimi"),
-                    henkilotunnus: line_row.get("henkilotunnus"),
-                    syntymaaika: line_row.get("syntymaaika"),
-                    puhelinnumero: line_row.get("puhelinnumero"),
-                    sahkoposti: line_row.get("sahkoposti"),
-                    osoite_id: line_row.get("osoite_id"),
-                    liittymispaiva: line_row.get("liittymispaiva"),
-                    jasentyyppi: line_row.get("jasentyyppi"),
-                    aktiivinen: line_row.get("aktiivinen"),
-                    created_at: line_row.get("member_created_at"),
-                    updated_at: line_row.get("member_updated_at"),
-                };
+        Ok(rows.iter().map(Self::fee_item_from_row).collect())
+    }
 
-                lines.push(InvoiceLineWithMember { line, member });
-            }
+    async fn validate_fee_item(&self, item: &CreateFeeItem) -> Result<Option<String>> {
+        if item.nimi.trim().is_empty() {
+            return Err(anyhow::anyhow!("Lisämaksun nimi on pakollinen."));
+        }
+        if item.summa < 0.0 {
+            return Err(anyhow::anyhow!("Lisämaksun summa ei voi olla negatiivinen."));
+        }
 
-            invoices_with_details.push(InvoiceWithDetails {
-                invoice,
-                household,
-                address,
-                lines,
-                billing_address: None, // TODO: Jos laskutusosoite on eri
-            });
+        match &item.jasentyyppi {
+            Some(t) if !t.trim().is_empty() => Ok(Some(self.resolve_member_type(t).await?.nimi)),
+            _ => Ok(None),
         }
+    }
 
-        Ok(invoices_with_details)
+    pub async fn create_fee_item(&self, item: &CreateFeeItem) -> Result<FeeItem> {
+        let jasentyyppi = self.validate_fee_item(item).await?;
+
+        let id = sqlx::query(
+            "INSERT INTO fee_items (nimi, summa, peruste, jasentyyppi, vuosi, aktiivinen)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(&item.nimi)
+        .bind(item.summa)
+        .bind(item.peruste.to_string())
+        .bind(&jasentyyppi)
+        .bind(item.vuosi)
+        .bind(item.aktiivinen)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        let row = sqlx::query("SELECT * FROM fee_items WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Self::fee_item_from_row(&row))
     }
 
-    pub async fn validate_invoice_creation(&self, year: i32) -> Result<String> {
-        // Tarkista että jäsenmaksut on määritelty kaikille jäsentyypeille
-        let member_types =
-            sqlx::query("SELECT DISTINCT jasentyyppi FROM members WHERE aktiivinen = 1")
-                .fetch_all(&self.pool)
-                .await?;
+    pub async fn update_fee_item(&self, id: i64, item: &CreateFeeItem) -> Result<FeeItem> {
+        let jasentyyppi = self.validate_fee_item(item).await?;
 
-        let mut missing_fees = Vec::new();
-        for type_row in member_types {
-            let member_type: String = type_row.get("jasentyyppi");
-            let fee_exists = sqlx::query(
-                "SELECT COUNT(*) as count FROM membership_fees WHERE jasentyyppi = ? AND vuosi = ?",
-            )
-            .bind(&member_type)
-            .bind(year)
+        sqlx::query(
+            "UPDATE fee_items SET
+             nimi = ?, summa = ?, peruste = ?, jasentyyppi = ?, vuosi = ?, aktiivinen = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&item.nimi)
+        .bind(item.summa)
+        .bind(item.peruste.to_string())
+        .bind(&jasentyyppi)
+        .bind(item.vuosi)
+        .bind(item.aktiivinen)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        let row = sqlx::query("SELECT * FROM fee_items WHERE id = ?")
+            .bind(id)
             .fetch_one(&self.pool)
-            .await?
-            .get::<i64, _>("count")
-                > 0;
+            .await?;
 
-            if !fee_exists {
-                missing_fees.push(member_type);
+        Ok(Self::fee_item_from_row(&row))
+    }
+
+    // Laskutetut rivit säilyvät, niiden viittaus lisämaksuun nollautuu (ON DELETE SET NULL)
+    pub async fn delete_fee_item(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM fee_items WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn member_guardian_from_row(row: &sqlx::sqlite::SqliteRow) -> MemberGuardian {
+        MemberGuardian {
+            id: row.get("id"),
+            jasen_id: row.get("jasen_id"),
+            huoltaja_jasen_id: row.get("huoltaja_jasen_id"),
+            nimi: row.get("nimi"),
+            katuosoite: row.get("katuosoite"),
+            postinumero: row.get("postinumero"),
+            postitoimipaikka: row.get("postitoimipaikka"),
+            sahkoposti: row.get("sahkoposti"),
+            puhelinnumero: row.get("puhelinnumero"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_member_guardian(&self, member_id: i64) -> Result<Option<MemberGuardian>> {
+        let row = sqlx::query("SELECT * FROM member_guardians WHERE jasen_id = ?")
+            .bind(member_id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+        Ok(row.as_ref().map(Self::member_guardian_from_row))
+    }
+
+    // Asettaa jäsenen maksajaksi huoltajan; aiempi huoltaja korvataan
+    pub async fn set_member_guardian(
+        &self,
+        member_id: i64,
+        guardian: &CreateMemberGuardian,
+    ) -> Result<MemberGuardian> {
+        let trimmed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+        let nimi = trimmed(&guardian.nimi);
+
+        match guardian.huoltaja_jasen_id {
+            Some(guardian_member_id) => {
+                if guardian_member_id == member_id {
+                    return Err(anyhow::anyhow!("Jäsen ei voi olla oma huoltajansa."));
+                }
+                sqlx::query("SELECT id FROM members WHERE id = ?")
+                    .bind(guardian_member_id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Jäsentä ei löytynyt ID:llä {}", guardian_member_id)
+                    })?;
+            }
+            None => {
+                // Jäsenrekisterin ulkopuoliselle huoltajalle lähetetään laskut, joten
+                // nimi ja postiosoite ovat pakollisia
+                if nimi.is_none()
+                    || trimmed(&guardian.katuosoite).is_none()
+                    || trimmed(&guardian.postinumero).is_none()
+                    || trimmed(&guardian.postitoimipaikka).is_none()
+                {
+                    return Err(anyhow::anyhow!(
+                        "Huoltajaksi on valittava jäsen tai annettava huoltajan nimi ja osoite."
+                    ));
+                }
             }
         }
 
-        if !missing_fees.is_empty() {
+        sqlx::query(
+            "INSERT INTO member_guardians (jasen_id, huoltaja_jasen_id, nimi, katuosoite,
+             postinumero, postitoimipaikka, sahkoposti, puhelinnumero)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT(jasen_id) DO UPDATE SET
+             huoltaja_jasen_id = excluded.huoltaja_jasen_id, nimi = excluded.nimi,
+             katuosoite = excluded.katuosoite, postinumero = excluded.postinumero,
+             postitoimipaikka = excluded.postitoimipaikka, sahkoposti = excluded.sahkoposti,
+             puhelinnumero = excluded.puhelinnumero, updated_at = CURRENT_TIMESTAMP",
+        )
+        .bind(member_id)
+        .bind(guardian.huoltaja_jasen_id)
+        .bind(&nimi)
+        .bind(trimmed(&guardian.katuosoite))
+        .bind(trimmed(&guardian.postinumero))
+        .bind(trimmed(&guardian.postitoimipaikka))
+        .bind(trimmed(&guardian.sahkoposti))
+        .bind(trimmed(&guardian.puhelinnumero))
+        .execute(&self.pool)
+        .await?;
+
+        self.get_member_guardian(member_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Huoltajan tallennus epäonnistui."))
+    }
+
+    // Jo tehtyjen laskujen maksajaviittaus nollautuu (ON DELETE SET NULL), mutta
+    // laskulle tallennetut maksajan tiedot säilyvät
+    pub async fn remove_member_guardian(&self, member_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM member_guardians WHERE jasen_id = ?")
+            .bind(member_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    // Huoltajan nimi ja yhteystiedot laskulle; jäsenhuoltajan tiedot jäsenrekisteristä
+    async fn get_guardian_payer(
+        conn: &mut SqliteConnection,
+        guardian_id: i64,
+    ) -> Result<Option<InvoicePayer>> {
+        let row = sqlx::query(
+            "SELECT g.id,
+                    COALESCE(TRIM(gm.etunimi || ' ' || gm.sukunimi), g.nimi) as nimi,
+                    COALESCE(a.katuosoite, g.katuosoite) as katuosoite,
+                    COALESCE(a.postinumero, g.postinumero) as postinumero,
+                    COALESCE(a.postitoimipaikka, g.postitoimipaikka) as postitoimipaikka,
+                    COALESCE(gm.sahkoposti, g.sahkoposti) as sahkoposti
+             FROM member_guardians g
+             LEFT JOIN members gm ON g.huoltaja_jasen_id = gm.id
+             LEFT JOIN addresses a ON gm.osoite_id = a.id
+             WHERE g.id = ?",
+        )
+        .bind(guardian_id)
+        .fetch_optional(conn)
+        .await?;
+
+        Ok(row.map(|row| InvoicePayer {
+            huoltaja_id: Some(row.get("id")),
+            nimi: row.get("nimi"),
+            katuosoite: row.get("katuosoite"),
+            postinumero: row.get("postinumero"),
+            postitoimipaikka: row.get("postitoimipaikka"),
+            sahkoposti: row.get("sahkoposti"),
+        }))
+    }
+
+    pub async fn get_member_fee_items(&self, member_id: i64) -> Result<Vec<FeeItem>> {
+        let rows = sqlx::query(
+            "SELECT fi.* FROM fee_items fi
+             JOIN member_fee_items mfi ON mfi.maksu_id = fi.id
+             WHERE mfi.jasen_id = ?
+             ORDER BY fi.nimi",
+        )
+        .bind(member_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::fee_item_from_row).collect())
+    }
+
+    pub async fn subscribe_member_fee_item(&self, member_id: i64, fee_item_id: i64) -> Result<()> {
+        let peruste: Option<String> = sqlx::query("SELECT peruste FROM fee_items WHERE id = ?")
+            .bind(fee_item_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .map(|row| row.get("peruste"));
+
+        match peruste.as_deref() {
+            None => return Err(anyhow::anyhow!("Lisämaksua ei löytynyt ID:llä {}", fee_item_id)),
+            Some("valinnainen") => {}
+            Some(_) => {
+                return Err(anyhow::anyhow!(
+                    "Vain valinnaisia lisämaksuja voi tilata jäsenelle."
+                ))
+            }
+        }
+
+        sqlx::query("INSERT OR IGNORE INTO member_fee_items (jasen_id, maksu_id) VALUES (?, ?)")
+            .bind(member_id)
+            .bind(fee_item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn unsubscribe_member_fee_item(&self, member_id: i64, fee_item_id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM member_fee_items WHERE jasen_id = ? AND maksu_id = ?")
+            .bind(member_id)
+            .bind(fee_item_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn billing_period_from_row(row: &sqlx::sqlite::SqliteRow) -> BillingPeriod {
+        BillingPeriod {
+            id: row.get("id"),
+            nimi: row.get("nimi"),
+            tyyppi: row.get("tyyppi"),
+            maksuvuosi: row.get("maksuvuosi"),
+            lukukausi: row.get("lukukausi"),
+            oletuserapaiva: row.get("oletuserapaiva"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_billing_periods(&self) -> Result<Vec<BillingPeriod>> {
+        let rows = sqlx::query(
+            "SELECT * FROM billing_periods ORDER BY maksuvuosi DESC, COALESCE(lukukausi, 0) DESC",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::billing_period_from_row).collect())
+    }
+
+    pub async fn get_billing_period(&self, id: i64) -> Result<BillingPeriod> {
+        let row = sqlx::query("SELECT * FROM billing_periods WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Laskutuskautta ei löytynyt ID:llä {}", id))?;
+
+        Ok(Self::billing_period_from_row(&row))
+    }
+
+    // Vuoden vuosikausi, jos se on jo luotu
+    async fn find_year_period(&self, year: i32) -> Result<Option<BillingPeriod>> {
+        let row = sqlx::query(
+            "SELECT * FROM billing_periods WHERE tyyppi = 'vuosi' AND maksuvuosi = ? ORDER BY id LIMIT 1",
+        )
+        .bind(year)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(row.as_ref().map(Self::billing_period_from_row))
+    }
+
+    // Tallentamaton vuosikausi tarkistusta ja esikatselua varten. Id 0 ei viittaa
+    // mihinkään kauteen, joten kaudella ei ole laskuja.
+    fn unsaved_year_period(year: i32) -> BillingPeriod {
+        BillingPeriod {
+            id: 0,
+            nimi: year.to_string(),
+            tyyppi: BillingPeriodType::Vuosi.to_string(),
+            maksuvuosi: year,
+            lukukausi: None,
+            oletuserapaiva: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        }
+    }
+
+    // Vuoden vuosikausi; luodaan nimellä "2025", jos sitä ei vielä ole
+    pub async fn get_or_create_year_period(&self, year: i32) -> Result<BillingPeriod> {
+        if let Some(period) = self.find_year_period(year).await? {
+            return Ok(period);
+        }
+
+        self.create_billing_period(&CreateBillingPeriod {
+            nimi: year.to_string(),
+            tyyppi: BillingPeriodType::Vuosi,
+            maksuvuosi: year,
+            lukukausi: None,
+            oletuserapaiva: None,
+        })
+        .await
+    }
+
+    async fn validate_billing_period(
+        &self,
+        id: Option<i64>,
+        period: &CreateBillingPeriod,
+    ) -> Result<()> {
+        if period.nimi.trim().is_empty() {
+            return Err(anyhow::anyhow!("Laskutuskauden nimi on pakollinen."));
+        }
+        match (&period.tyyppi, period.lukukausi) {
+            (BillingPeriodType::Vuosi, None) => {}
+            (BillingPeriodType::Lukukausi, Some(1 | 2)) => {}
+            (BillingPeriodType::Vuosi, Some(_)) => {
+                return Err(anyhow::anyhow!("Vuosikaudella ei ole lukukautta."))
+            }
+            (BillingPeriodType::Lukukausi, _) => {
+                return Err(anyhow::anyhow!("Lukukauden on oltava 1 (kevät) tai 2 (syksy)."))
+            }
+        }
+
+        // Sama kausi (esim. vuosi 2025 tai kevät 2025) vain kerran
+        let duplicate = sqlx::query(
+            "SELECT COUNT(*) as count FROM billing_periods
+             WHERE tyyppi = ? AND maksuvuosi = ? AND COALESCE(lukukausi, 0) = ? AND id != ?",
+        )
+        .bind(period.tyyppi.to_string())
+        .bind(period.maksuvuosi)
+        .bind(period.lukukausi.unwrap_or(0))
+        .bind(id.unwrap_or(0))
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("count")
+            > 0;
+
+        if duplicate {
+            return Err(anyhow::anyhow!(
+                "Laskutuskausi on jo olemassa vuodelle {}.",
+                period.maksuvuosi
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_billing_period(&self, period: &CreateBillingPeriod) -> Result<BillingPeriod> {
+        self.validate_billing_period(None, period).await?;
+
+        let id = sqlx::query(
+            "INSERT INTO billing_periods (nimi, tyyppi, maksuvuosi, lukukausi, oletuserapaiva)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(period.nimi.trim())
+        .bind(period.tyyppi.to_string())
+        .bind(period.maksuvuosi)
+        .bind(period.lukukausi)
+        .bind(period.oletuserapaiva)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        self.get_billing_period(id).await
+    }
+
+    pub async fn update_billing_period(
+        &self,
+        id: i64,
+        period: &CreateBillingPeriod,
+    ) -> Result<BillingPeriod> {
+        let current = self.get_billing_period(id).await?;
+        self.validate_billing_period(Some(id), period).await?;
+
+        // Laskutetun kauden maksuvuotta ei voi enää vaihtaa
+        let invoice_count = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE kausi_id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+        if invoice_count > 0
+            && (current.maksuvuosi != period.maksuvuosi
+                || current.tyyppi != period.tyyppi.to_string()
+                || current.lukukausi != period.lukukausi)
+        {
+            return Err(anyhow::anyhow!(
+                "Kaudelle {} on jo luotu laskuja, joten vain nimeä ja oletuseräpäivää voi muuttaa.",
+                current.nimi
+            ));
+        }
+
+        sqlx::query(
+            "UPDATE billing_periods SET
+             nimi = ?, tyyppi = ?, maksuvuosi = ?, lukukausi = ?, oletuserapaiva = ?,
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(period.nimi.trim())
+        .bind(period.tyyppi.to_string())
+        .bind(period.maksuvuosi)
+        .bind(period.lukukausi)
+        .bind(period.oletuserapaiva)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_billing_period(id).await
+    }
+
+    pub async fn delete_billing_period(&self, id: i64) -> Result<()> {
+        let invoice_count = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE kausi_id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+
+        if invoice_count > 0 {
+            return Err(anyhow::anyhow!(
+                "Laskutuskautta ei voi poistaa, koska sille on luotu {} laskua.",
+                invoice_count
+            ));
+        }
+
+        sqlx::query("DELETE FROM billing_periods WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    fn customer_from_row(row: &sqlx::sqlite::SqliteRow) -> Customer {
+        Customer {
+            id: row.get("id"),
+            tyyppi: row.get("tyyppi"),
+            nimi: row.get("nimi"),
+            y_tunnus: row.get("y_tunnus"),
+            verkkolaskuosoite: row.get("verkkolaskuosoite"),
+            valittajatunnus: row.get("valittajatunnus"),
+            yhteyshenkilo: row.get("yhteyshenkilo"),
+            sahkoposti: row.get("sahkoposti"),
+            puhelinnumero: row.get("puhelinnumero"),
+            katuosoite: row.get("katuosoite"),
+            postinumero: row.get("postinumero"),
+            postitoimipaikka: row.get("postitoimipaikka"),
+            aktiivinen: row.get("aktiivinen"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    pub async fn get_customers(&self) -> Result<Vec<Customer>> {
+        let rows = sqlx::query("SELECT * FROM customers ORDER BY nimi")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::customer_from_row).collect())
+    }
+
+    pub async fn get_customer(&self, id: i64) -> Result<Customer> {
+        let row = sqlx::query("SELECT * FROM customers WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Asiakasta ei löytynyt ID:llä {}", id))?;
+
+        Ok(Self::customer_from_row(&row))
+    }
+
+    // Tyhjät kentät tallennetaan NULL-arvoina; y-tunnus tarkistetaan tarkistusnumeroineen
+    fn normalize_customer(customer: &CreateCustomer) -> Result<CreateCustomer> {
+        let trimmed = |value: &Option<String>| {
+            value
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string())
+        };
+
+        let nimi = customer.nimi.trim().to_string();
+        if nimi.is_empty() {
+            return Err(anyhow::anyhow!("Asiakkaan nimi on pakollinen."));
+        }
+
+        let y_tunnus = trimmed(&customer.y_tunnus);
+        if let Some(y_tunnus) = &y_tunnus {
+            if !is_valid_business_id(y_tunnus) {
+                return Err(anyhow::anyhow!("Virheellinen y-tunnus: {}", y_tunnus));
+            }
+        }
+
+        let verkkolaskuosoite = trimmed(&customer.verkkolaskuosoite);
+        let valittajatunnus = trimmed(&customer.valittajatunnus);
+        if verkkolaskuosoite.is_some() != valittajatunnus.is_some() {
+            return Err(anyhow::anyhow!(
+                "Verkkolaskuosoitteelle on annettava myös välittäjätunnus."
+            ));
+        }
+
+        Ok(CreateCustomer {
+            tyyppi: customer.tyyppi.clone(),
+            nimi,
+            y_tunnus,
+            verkkolaskuosoite,
+            valittajatunnus,
+            yhteyshenkilo: trimmed(&customer.yhteyshenkilo),
+            sahkoposti: trimmed(&customer.sahkoposti),
+            puhelinnumero: trimmed(&customer.puhelinnumero),
+            katuosoite: trimmed(&customer.katuosoite),
+            postinumero: trimmed(&customer.postinumero),
+            postitoimipaikka: trimmed(&customer.postitoimipaikka),
+            aktiivinen: customer.aktiivinen,
+        })
+    }
+
+    pub async fn create_customer(&self, customer: &CreateCustomer) -> Result<Customer> {
+        let customer = Self::normalize_customer(customer)?;
+
+        let id = sqlx::query(
+            "INSERT INTO customers (tyyppi, nimi, y_tunnus, verkkolaskuosoite, valittajatunnus,
+             yhteyshenkilo, sahkoposti, puhelinnumero, katuosoite, postinumero, postitoimipaikka, aktiivinen)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(customer.tyyppi.to_string())
+        .bind(&customer.nimi)
+        .bind(&customer.y_tunnus)
+        .bind(&customer.verkkolaskuosoite)
+        .bind(&customer.valittajatunnus)
+        .bind(&customer.yhteyshenkilo)
+        .bind(&customer.sahkoposti)
+        .bind(&customer.puhelinnumero)
+        .bind(&customer.katuosoite)
+        .bind(&customer.postinumero)
+        .bind(&customer.postitoimipaikka)
+        .bind(customer.aktiivinen)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        self.get_customer(id).await
+    }
+
+    pub async fn update_customer(&self, id: i64, customer: &CreateCustomer) -> Result<Customer> {
+        let customer = Self::normalize_customer(customer)?;
+
+        let result = sqlx::query(
+            "UPDATE customers SET
+             tyyppi = ?, nimi = ?, y_tunnus = ?, verkkolaskuosoite = ?, valittajatunnus = ?,
+             yhteyshenkilo = ?, sahkoposti = ?, puhelinnumero = ?, katuosoite = ?,
+             postinumero = ?, postitoimipaikka = ?, aktiivinen = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(customer.tyyppi.to_string())
+        .bind(&customer.nimi)
+        .bind(&customer.y_tunnus)
+        .bind(&customer.verkkolaskuosoite)
+        .bind(&customer.valittajatunnus)
+        .bind(&customer.yhteyshenkilo)
+        .bind(&customer.sahkoposti)
+        .bind(&customer.puhelinnumero)
+        .bind(&customer.katuosoite)
+        .bind(&customer.postinumero)
+        .bind(&customer.postitoimipaikka)
+        .bind(customer.aktiivinen)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(anyhow::anyhow!("Asiakasta ei löytynyt ID:llä {}", id));
+        }
+
+        self.get_customer(id).await
+    }
+
+    // Laskutettua asiakasta ei poisteta; sen voi merkitä passiiviseksi
+    pub async fn delete_customer(&self, id: i64) -> Result<()> {
+        let invoice_count = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE asiakas_id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+
+        if invoice_count > 0 {
+            return Err(anyhow::anyhow!(
+                "Asiakkaalla on {} laskua. Merkitse asiakas passiiviseksi poistamisen sijaan.",
+                invoice_count
+            ));
+        }
+
+        sqlx::query("DELETE FROM customers WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn get_invoices(&self) -> Result<Vec<InvoiceWithDetails>> {
+        let rows = sqlx::query(
+            "SELECT 
+                i.id as invoice_id, i.talous_id, i.asiakas_id, i.luontipaiva, i.erapaiva, i.summa, 
+                i.viitenumero, i.laskunumero, i.maksettu, i.maksupaiva, i.viesti, i.lahetetty,
+                i.kausi_id, i.lisalasku, i.ajo_id, i.jasen_id, i.huoltaja_id, i.luottotappio_pvm, i.luottotappio_syy,
+                i.maksaja_nimi, i.maksaja_katuosoite, i.maksaja_postinumero, i.maksaja_postitoimipaikka, i.maksaja_sahkoposti,
+                i.created_at as invoice_created_at, i.updated_at as invoice_updated_at,
+                h.id as household_id, h.talouden_nimi, h.vastaanottaja, h.laskutusosoite_sama, h.laskutusosoite_id,
+                h.created_at as household_created_at, h.updated_at as household_updated_at,
+                a.id as address_id, a.katuosoite, a.postinumero, a.postitoimipaikka,
+                a.created_at as address_created_at, a.updated_at as address_updated_at
+            FROM invoices i 
+            LEFT JOIN households h ON i.talous_id = h.id
+            LEFT JOIN addresses a ON h.id = a.talous_id
+            ORDER BY i.luontipaiva DESC"
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut invoices_with_details = Vec::new();
+
+        for row in rows {
+            let summa = match row.try_get::<f64, _>("summa") {
+                Ok(val) => val,
+                Err(_) => row.get::<i64, _>("summa") as f64,
+            };
+
+            let invoice = Invoice {
+                id: row.get("invoice_id"),
+                talous_id: row.get("talous_id"),
+                asiakas_id: row.get("asiakas_id"),
+                luontipaiva: row.get("luontipaiva"),
+                erapaiva: row.get("erapaiva"),
+                summa,
+                viitenumero: row.get("viitenumero"),
+                laskunumero: row.try_get("laskunumero").ok().flatten(),
+                maksettu: row.get("maksettu"),
+                maksupaiva: row.get("maksupaiva"),
+                viesti: row.try_get("viesti").ok().flatten(),
+                lahetetty: row.try_get("lahetetty").ok().flatten(),
+                kausi_id: row.try_get("kausi_id").ok().flatten(),
+                lisalasku: row.try_get("lisalasku").unwrap_or(false),
+                ajo_id: row.try_get("ajo_id").ok().flatten(),
+                jasen_id: row.try_get("jasen_id").ok().flatten(),
+                huoltaja_id: row.try_get("huoltaja_id").ok().flatten(),
+                luottotappio_pvm: row.try_get("luottotappio_pvm").ok().flatten(),
+                luottotappio_syy: row.try_get("luottotappio_syy").ok().flatten(),
+                created_at: row.get("invoice_created_at"),
+                updated_at: row.get("invoice_updated_at"),
+            };
+
+            // Asiakkaalle tehdyllä laskulla ei välttämättä ole taloutta
+            let household_id: Option<i64> = row.get("household_id");
+            let household = household_id.map(|household_id| Household {
+                id: household_id,
+                talouden_nimi: row.get("talouden_nimi"),
+                vastaanottaja: row.try_get("vastaanottaja").ok().flatten(),
+                laskutusosoite_sama: row.get("laskutusosoite_sama"),
+                laskutusosoite_id: row.get("laskutusosoite_id"),
+                created_at: row.get("household_created_at"),
+                updated_at: row.get("household_updated_at"),
+            });
+
+            let address_id: Option<i64> = row.get("address_id");
+            let address = address_id.map(|address_id| Address {
+                id: address_id,
+                katuosoite: row.get("katuosoite"),
+                postinumero: row.get("postinumero"),
+                postitoimipaikka: row.get("postitoimipaikka"),
+                talous_id: row.get("talous_id"),
+                created_at: row.get("address_created_at"),
+                updated_at: row.get("address_updated_at"),
+            });
+
+            // Hae laskurivit tälle laskulle
+            let line_rows = sqlx::query(
+                "SELECT 
+                    il.id, il.lasku_id, il.jasen_id, il.maksu_id, il.kuvaus, il.summa, il.created_at,
+                    m.id as member_id, m.etunimi, m.sukunimi, m.henkilotunnus, m.syntymaaika,
+                    m.puhelinnumero, m.sahkoposti, m.osoite_id, m.liittymispaiva, m.jasentyyppi,
+                    m.aktiivinen, m.oma_lasku, m.jasenlaji, m.y_tunnus, m.yhteyshenkilo, m.laskuviite,
+                    m.verkkolaskuosoite, m.valittajatunnus, m.erottaminen_esitetty, m.eroamispaiva, m.created_at as member_created_at, m.updated_at as member_updated_at
+                FROM invoice_lines il
+                LEFT JOIN members m ON il.jasen_id = m.id
+                WHERE il.lasku_id = ?
+                ORDER BY il.id"
+            )
+            .bind(invoice.id)
+            .fetch_all(&self.pool)
+            .await?;
+
+            let mut lines = Vec::new();
+            for line_row in line_rows {
+                let line_summa = match line_row.try_get::<f64, _>("summa") {
+                    Ok(val) => val,
+                    Err(_) => line_row.get::<i64, _>("summa") as f64,
+                };
+
+                let line = InvoiceLine {
+                    id: line_row.get("id"),
+                    lasku_id: line_row.get("lasku_id"),
+                    jasen_id: line_row.get("jasen_id"),
+                    maksu_id: line_row.get("maksu_id"),
+                    kuvaus: line_row.get("kuvaus"),
+                    summa: line_summa,
+                    created_at: line_row.get("created_at"),
+                };
+
+                // Talouskohtaisilla riveillä ei ole jäsentä
+                let member_id: Option<i64> = line_row.get("member_id");
+                let member = member_id.map(|member_id| Member {
+                    id: member_id,
+                    etunimi: line_row.get("etunimi"),
+                    sukunimi: line_row.get("sukunimi"),
+                    henkilotunnus: line_row.get("henkilotunnus"),
+                    syntymaaika: line_row.get("syntymaaika"),
+                    puhelinnumero: line_row.get("puhelinnumero"),
+                    sahkoposti: line_row.get("sahkoposti"),
+                    osoite_id: line_row.get("osoite_id"),
+                    liittymispaiva: line_row.get("liittymispaiva"),
+                    jasentyyppi: line_row.get("jasentyyppi"),
+                    aktiivinen: line_row.get("aktiivinen"),
+                    oma_lasku: line_row.get("oma_lasku"),
+                    jasenlaji: line_row.get("jasenlaji"),
+                    y_tunnus: line_row.get("y_tunnus"),
+                    yhteyshenkilo: line_row.get("yhteyshenkilo"),
+                    laskuviite: line_row.get("laskuviite"),
+                    verkkolaskuosoite: line_row.get("verkkolaskuosoite"),
+                    valittajatunnus: line_row.get("valittajatunnus"),
+                    erottaminen_esitetty: line_row.try_get("erottaminen_esitetty").ok().flatten(),
+                    eroamispaiva: line_row.try_get("eroamispaiva").ok().flatten(),
+                    created_at: line_row.get("member_created_at"),
+                    updated_at: line_row.get("member_updated_at"),
+                });
+
+                lines.push(InvoiceLineWithMember { line, member });
+            }
+
+            // Jäsenkohtaisen laskun saaja on laskun rivien jäsenissä
+            let member = invoice.jasen_id.and_then(|member_id| {
+                lines
+                    .iter()
+                    .filter_map(|l| l.member.as_ref())
+                    .find(|m| m.id == member_id)
+                    .cloned()
+            });
+
+            // Maksajan tiedot laskun luontihetkeltä, vaikka huoltaja olisi sittemmin poistettu
+            let payer_name: Option<String> = row.get("maksaja_nimi");
+            let payer = payer_name.map(|nimi| InvoicePayer {
+                huoltaja_id: invoice.huoltaja_id,
+                nimi,
+                katuosoite: row.get("maksaja_katuosoite"),
+                postinumero: row.get("maksaja_postinumero"),
+                postitoimipaikka: row.get("maksaja_postitoimipaikka"),
+                sahkoposti: row.get("maksaja_sahkoposti"),
+            });
+            let customer = match invoice.asiakas_id {
+                Some(customer_id) => Some(self.get_customer(customer_id).await?),
+                None => None,
+            };
+
+            invoices_with_details.push(InvoiceWithDetails {
+                invoice,
+                household,
+                address,
+                lines,
+                billing_address: None, // TODO: Jos laskutusosoite on eri
+                member,
+                payer,
+                customer,
+            });
+        }
+
+        Ok(invoices_with_details)
+    }
+
+    // Vuosilaskutus käyttää vuoden vuosikautta. Tarkistus ei luo kautta, vaan
+    // puuttuva kausi luodaan vasta laskujen luonnissa.
+    pub async fn validate_invoice_creation(&self, year: i32) -> Result<String> {
+        match self.find_year_period(year).await? {
+            Some(period) => self.validate_invoicing(&period).await,
+            None => {
+                let message = self
+                    .validate_invoicing(&Self::unsaved_year_period(year))
+                    .await?;
+                Ok(format!(
+                    "Vuoden {} laskutuskautta ei ole vielä luotu; se luodaan laskujen luonnin yhteydessä. {}",
+                    year, message
+                ))
+            }
+        }
+    }
+
+    pub async fn validate_period_invoicing(&self, period_id: i64) -> Result<String> {
+        let period = self.get_billing_period(period_id).await?;
+        self.validate_invoicing(&period).await
+    }
+
+    async fn validate_invoicing(&self, period: &BillingPeriod) -> Result<String> {
+        let year = period.maksuvuosi;
+
+        // Tarkista että jäsenmaksut on määritelty kaikille laskutettaville jäsentyypeille
+        let member_types = sqlx::query(
+            "SELECT DISTINCT m.jasentyyppi FROM members m
+             JOIN member_types mt ON mt.nimi = m.jasentyyppi
+             WHERE m.aktiivinen = 1 AND mt.laskutettava = 1",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut missing_fees = Vec::new();
+        for type_row in member_types {
+            let member_type: String = type_row.get("jasentyyppi");
+            let fee_exists = sqlx::query(
+                "SELECT COUNT(*) as count FROM membership_fees WHERE jasentyyppi = ? AND vuosi = ?",
+            )
+            .bind(&member_type)
+            .bind(year)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count")
+                > 0;
+
+            if !fee_exists {
+                missing_fees.push(member_type);
+            }
+        }
+
+        if !missing_fees.is_empty() {
+            return Err(anyhow::anyhow!(
+                "Jäsenmaksut puuttuvat vuodelle {} jäsentyypeille: {}. Määrittele jäsenmaksut ennen laskujen luontia.",
+                year,
+                missing_fees.join(", ")
+            ));
+        }
+
+        // Tarkista että on aktiivisia jäseniä
+        let active_members =
+            sqlx::query("SELECT COUNT(*) as count FROM members WHERE aktiivinen = 1")
+                .fetch_one(&self.pool)
+                .await?
+                .get::<i64, _>("count");
+
+        if active_members == 0 {
+            return Err(anyhow::anyhow!("Ei aktiivisia jäseniä laskutettavaksi."));
+        }
+
+        // Laske montako laskua luotaisiin: uudet taloudet ja lisälaskut erikseen
+        let mut conn = self.pool.acquire().await?;
+        let plan =
+            Self::plan_invoices_for_period(&mut conn, period, &InvoiceSelection::default()).await?;
+        let households_without_invoice = plan.iter().filter(|d| !d.lisalasku).count();
+        let supplementary: Vec<&InvoiceDraft> = plan.iter().filter(|d| d.lisalasku).collect();
+        let supplementary_members = supplementary
+            .iter()
+            .flat_map(|d| d.lines.iter().filter_map(|l| l.jasen_id))
+            .collect::<std::collections::HashSet<i64>>()
+            .len();
+
+        // Tarkista onko jo kaikille luotu laskut
+        let existing_invoices = sqlx::query(
+            "SELECT COUNT(*) as count FROM invoices WHERE kausi_id = ?",
+        )
+        .bind(period.id)
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("count");
+
+        if plan.is_empty() {
+            if existing_invoices > 0 {
+                return Err(anyhow::anyhow!(
+                    "Laskut on jo luotu kaudelle {}. {} laskua olemassa.",
+                    period.nimi,
+                    existing_invoices
+                ));
+            } else {
+                return Err(anyhow::anyhow!(
+                    "Ei laskutettavia jäseniä kaudelle {}.",
+                    period.nimi
+                ));
+            }
+        }
+
+        let mut message = if existing_invoices > 0 {
+            format!(
+                "Valmis luomaan {} uutta laskua kaudelle {}. {} laskua on jo olemassa. Luodaan laskut vain niille jäsenille, joilla ei ole vielä laskua.",
+                households_without_invoice, period.nimi, existing_invoices
+            )
+        } else {
+            format!(
+                "Valmis luomaan {} laskua {} aktiiviselle jäsenelle kaudelle {}.",
+                households_without_invoice, active_members, period.nimi
+            )
+        };
+
+        if !supplementary.is_empty() {
+            message.push_str(&format!(
+                " Lisäksi {} lisälaskua jo laskutettuihin talouksiin {} uudelle jäsenelle.",
+                supplementary.len(),
+                supplementary_members
+            ));
+        }
+
+        Ok(message)
+    }
+
+    // Esikatselu ei luo vuosikautta; puuttuvan kauden tilalla on tallentamaton kausi (id 0)
+    pub async fn preview_invoice_creation(&self, year: i32) -> Result<InvoicePreview> {
+        let period = match self.find_year_period(year).await? {
+            Some(period) => period,
+            None => Self::unsaved_year_period(year),
+        };
+        self.preview_for_period(period, &InvoiceSelection::default())
+            .await
+    }
+
+    pub async fn preview_period_invoicing(&self, period_id: i64) -> Result<InvoicePreview> {
+        self.preview_invoices_for_selection(period_id, &InvoiceSelection::default())
+            .await
+    }
+
+    // Kauden laskutuksen esikatselu: laskut riveineen, ohitetut jäsenet syineen ja
+    // käsittelemättömät ikään perustuvat tyyppimuutokset, jotka eivät vaikuta
+    // laskuihin ennen hyväksyntää. Ei muuta tietokantaa.
+    // Rajatussa laskutuksessa ohitetuiksi listataan vain valintaan osuvat jäsenet.
+    pub async fn preview_invoices_for_selection(
+        &self,
+        period_id: i64,
+        selection: &InvoiceSelection,
+    ) -> Result<InvoicePreview> {
+        let period = self.get_billing_period(period_id).await?;
+        self.preview_for_period(period, selection).await
+    }
+
+    async fn preview_for_period(
+        &self,
+        period: BillingPeriod,
+        selection: &InvoiceSelection,
+    ) -> Result<InvoicePreview> {
+        let year = period.maksuvuosi;
+        let mut conn = self.pool.acquire().await?;
+        let (type_changes, plan) = Self::plan_selection(&mut conn, &period, selection).await?;
+
+        let member_types = self.get_member_types().await?;
+        let fee_types: Vec<String> = sqlx::query("SELECT jasentyyppi FROM membership_fees WHERE vuosi = ?")
+            .bind(year)
+            .fetch_all(&self.pool)
+            .await?
+            .iter()
+            .map(|row| row.get("jasentyyppi"))
+            .collect();
+
+        let households = sqlx::query("SELECT id, talouden_nimi, vastaanottaja FROM households")
+            .fetch_all(&self.pool)
+            .await?;
+
+        let member_rows = sqlx::query(
+            "SELECT m.id, m.etunimi, m.sukunimi, m.jasentyyppi, m.aktiivinen, m.liittymispaiva,
+                    a.talous_id,
+                    EXISTS (
+                        SELECT 1 FROM member_fee_items mfi
+                        JOIN fee_items fi ON fi.id = mfi.maksu_id
+                        WHERE mfi.jasen_id = m.id AND fi.aktiivinen = 1
+                    ) as tilaaja,
+                    EXISTS (
+                        SELECT 1 FROM invoice_lines il
+                        JOIN invoices i ON il.lasku_id = i.id
+                        WHERE il.jasen_id = m.id AND i.kausi_id = ?
+                    ) as laskutettu
+             FROM members m
+             JOIN addresses a ON m.osoite_id = a.id
+             ORDER BY m.sukunimi, m.etunimi",
+        )
+        .bind(period.id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut member_info = std::collections::HashMap::new();
+        for row in &member_rows {
+            let id: i64 = row.get("id");
+            let name = fee_rules::full_name(
+                &row.get::<String, _>("etunimi"),
+                &row.get::<String, _>("sukunimi"),
+            );
+            member_info.insert(id, (name, row.get::<String, _>("jasentyyppi")));
+        }
+
+        let mut laskut = Vec::new();
+        for draft in &plan {
+            let household = households
+                .iter()
+                .find(|h| Some(h.get::<i64, _>("id")) == draft.talous_id);
+            let rivit = draft
+                .lines
+                .iter()
+                .map(|line| {
+                    let info = line.jasen_id.and_then(|id| member_info.get(&id));
+                    InvoicePreviewLine {
+                        jasen_id: line.jasen_id,
+                        jasen: info.map(|(name, _)| name.clone()),
+                        jasentyyppi: info.map(|(_, t)| t.clone()),
+                        maksu_id: line.maksu_id,
+                        kuvaus: line.kuvaus.clone(),
+                        summa: line.summa,
+                    }
+                })
+                .collect();
+
+            // Jäsenkohtaisen laskun saaja on jäsen itse tai hänen huoltajansa
+            let vastaanottaja = match (draft.huoltaja_id, draft.jasen_id) {
+                (Some(guardian_id), _) => Self::get_guardian_payer(&mut conn, guardian_id)
+                    .await?
+                    .map(|payer| payer.nimi),
+                (None, Some(id)) => member_info.get(&id).map(|(name, _)| name.clone()),
+                (None, None) => household.and_then(|h| h.get("vastaanottaja")),
+            };
+
+            laskut.push(InvoicePreviewInvoice {
+                // Kauden laskuilla on aina talous
+                talous_id: draft.talous_id.unwrap_or_default(),
+                jasen_id: draft.jasen_id,
+                talouden_nimi: household.and_then(|h| h.get("talouden_nimi")),
+                vastaanottaja,
+                lisalasku: draft.lisalasku,
+                rivit,
+                summa: draft.total(),
+            });
+        }
+
+        // Jäsenet, joilla on laskulla vähintään yksi nollasta poikkeava rivi
+        let invoiced: std::collections::HashSet<i64> = plan
+            .iter()
+            .flat_map(|d| d.lines.iter())
+            .filter(|l| l.summa != 0.0)
+            .filter_map(|l| l.jasen_id)
+            .collect();
+
+        let mut ohitetut = Vec::new();
+        for row in &member_rows {
+            let id: i64 = row.get("id");
+            let jasentyyppi: String = row.get("jasentyyppi");
+            if !selection.matches(
+                row.get("talous_id"),
+                id,
+                row.get("liittymispaiva"),
+                &jasentyyppi,
+            ) {
+                continue;
+            }
+            let subscriber: bool = row.get("tilaaja");
+            let billable = member_types
+                .iter()
+                .find(|t| t.nimi == jasentyyppi)
+                .is_some_and(|t| t.laskutettava);
+
+            let (syy, selite) = if !row.get::<bool, _>("aktiivinen") {
+                ("ei_aktiivinen", "Jäsen ei ole aktiivinen.".to_string())
+            } else if row.get::<bool, _>("laskutettu") {
+                (
+                    "jo_laskutettu",
+                    format!("Jäsen on jo laskutettu kaudella {}.", period.nimi),
+                )
+            } else if invoiced.contains(&id) {
+                continue;
+            } else if !billable && !subscriber {
+                (
+                    "ei_laskutettava",
+                    format!("Jäsentyyppiä {} ei laskuteta.", jasentyyppi),
+                )
+            } else if billable && !fee_types.contains(&jasentyyppi) {
+                (
+                    "ei_jasenmaksua",
+                    format!(
+                        "Jäsenmaksua ei ole määritelty jäsentyypille {} vuodelle {}.",
+                        jasentyyppi, year
+                    ),
+                )
+            } else {
+                ("nolla_summa", "Laskutettava summa on nolla.".to_string())
+            };
+
+            ohitetut.push(SkippedMember {
+                jasen_id: id,
+                etunimi: row.get("etunimi"),
+                sukunimi: row.get("sukunimi"),
+                jasentyyppi,
+                talous_id: row.get("talous_id"),
+                syy: syy.to_string(),
+                selite,
+            });
+        }
+
+        let yhteensa: f64 = laskut.iter().map(|l| l.summa).sum();
+        Ok(InvoicePreview {
+            laskuja: laskut.iter().filter(|l| !l.lisalasku).count() as i64,
+            lisalaskuja: laskut.iter().filter(|l| l.lisalasku).count() as i64,
+            yhteensa: (yhteensa * 100.0).round() / 100.0,
+            kausi: period,
+            laskut,
+            ohitetut,
+            tyyppimuutokset: type_changes,
+        })
+    }
+
+    // Siirtymäsääntöjen mukaiset, vielä tekemättömät jäsentyyppimuutokset
+    // maksuvuodelle. Jäseneen sovelletaan ensimmäistä osuvaa aktiivista sääntöä;
+    // samalle vuodelle jo hylättyä muutosta ei ehdoteta uudelleen.
+    pub async fn get_pending_member_type_changes(&self, year: i32) -> Result<Vec<MemberTypeChange>> {
+        let mut conn = self.pool.acquire().await?;
+        Self::pending_member_type_changes(&mut conn, year).await
+    }
+
+    async fn pending_member_type_changes(
+        conn: &mut SqliteConnection,
+        year: i32,
+    ) -> Result<Vec<MemberTypeChange>> {
+        let rules: Vec<MemberTypeRule> = sqlx::query(
+            "SELECT * FROM member_type_rules WHERE aktiivinen = 1 ORDER BY jarjestys, id",
+        )
+        .fetch_all(&mut *conn)
+        .await?
+        .iter()
+        .map(Self::member_type_rule_from_row)
+        .collect();
+        if rules.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let rows = sqlx::query(
+            "SELECT id, etunimi, sukunimi, jasentyyppi, syntymaaika FROM members
+             WHERE aktiivinen = 1 AND syntymaaika IS NOT NULL
+             ORDER BY sukunimi, etunimi",
+        )
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let rejected: Vec<(i64, Option<i64>)> = sqlx::query(
+            "SELECT jasen_id, saanto_id FROM member_type_changes WHERE tila = 'hylatty' AND vuosi = ?",
+        )
+        .bind(year)
+        .fetch_all(&mut *conn)
+        .await?
+        .iter()
+        .map(|row| (row.get("jasen_id"), row.get("saanto_id")))
+        .collect();
+
+        let mut changes = Vec::new();
+        for row in rows {
+            let jasen_id: i64 = row.get("id");
+            let jasentyyppi: String = row.get("jasentyyppi");
+            let birth_date: chrono::NaiveDate = row.get("syntymaaika");
+
+            let matched = rules.iter().find_map(|rule| {
+                let ika = transition_age(rule, birth_date, year);
+                let applies = rule.lahtotyyppi == jasentyyppi
+                    && rule.ika_min.is_none_or(|min| ika >= min)
+                    && rule.ika_max.is_none_or(|max| ika <= max)
+                    && !rejected.contains(&(jasen_id, Some(rule.id)));
+                applies.then_some((rule, ika))
+            });
+
+            if let Some((rule, ika)) = matched {
+                changes.push(MemberTypeChange {
+                    jasen_id,
+                    etunimi: row.get("etunimi"),
+                    sukunimi: row.get("sukunimi"),
+                    ika,
+                    vanha_tyyppi: jasentyyppi,
+                    uusi_tyyppi: rule.kohdetyyppi.clone(),
+                    saanto_id: Some(rule.id),
+                });
+            }
+        }
+
+        Ok(changes)
+    }
+
+    fn member_type_change_log_from_row(row: &sqlx::sqlite::SqliteRow) -> MemberTypeChangeLog {
+        MemberTypeChangeLog {
+            id: row.get("id"),
+            jasen_id: row.get("jasen_id"),
+            etunimi: row.get("etunimi"),
+            sukunimi: row.get("sukunimi"),
+            vanha_tyyppi: row.get("vanha_tyyppi"),
+            uusi_tyyppi: row.get("uusi_tyyppi"),
+            ika: row.get("ika"),
+            vuosi: row.get("vuosi"),
+            saanto_id: row.get("saanto_id"),
+            saanto: row.get("saanto"),
+            tila: row.get("tila"),
+            ajo_id: row.get("ajo_id"),
+            created_at: row.get("created_at"),
+            kasitelty: row.get("kasitelty"),
+        }
+    }
+
+    // Jäsentyyppimuutosten loki uusimmasta alkaen, valinnaisesti tilan mukaan
+    pub async fn get_member_type_change_log(
+        &self,
+        tila: Option<&MemberTypeChangeStatus>,
+    ) -> Result<Vec<MemberTypeChangeLog>> {
+        let tila = tila.map(|t| t.to_string());
+        let rows = sqlx::query(
+            "SELECT c.*, m.etunimi, m.sukunimi, r.nimi as saanto
+             FROM member_type_changes c
+             JOIN members m ON m.id = c.jasen_id
+             LEFT JOIN member_type_rules r ON r.id = c.saanto_id
+             WHERE (? IS NULL OR c.tila = ?)
+             ORDER BY c.created_at DESC, c.id DESC",
+        )
+        .bind(&tila)
+        .bind(&tila)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::member_type_change_log_from_row).collect())
+    }
+
+    async fn get_member_type_change(&self, id: i64) -> Result<MemberTypeChangeLog> {
+        let row = sqlx::query(
+            "SELECT c.*, m.etunimi, m.sukunimi, r.nimi as saanto
+             FROM member_type_changes c
+             JOIN members m ON m.id = c.jasen_id
+             LEFT JOIN member_type_rules r ON r.id = c.saanto_id
+             WHERE c.id = ?",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        match row {
+            Some(row) => Ok(Self::member_type_change_log_from_row(&row)),
+            None => Err(anyhow::anyhow!("Jäsentyyppimuutosta ei löytynyt ID:llä {}", id)),
+        }
+    }
+
+    // Kirjaa vuoden siirtymät lokiin ehdotuksina. Jäsenen tyyppi muuttuu vasta,
+    // kun ehdotus hyväksytään. Jäsenellä, jolla on jo käsittelemätön ehdotus,
+    // ei luoda uutta. Palauttaa kaikki käsittelemättömät ehdotukset.
+    pub async fn propose_member_type_changes(&self, year: i32) -> Result<Vec<MemberTypeChangeLog>> {
+        let changes = self.get_pending_member_type_changes(year).await?;
+
+        let mut tx = self.pool.begin().await?;
+        for change in &changes {
+            sqlx::query(
+                "INSERT OR IGNORE INTO member_type_changes
+                 (jasen_id, vanha_tyyppi, uusi_tyyppi, ika, vuosi, saanto_id, tila)
+                 VALUES (?, ?, ?, ?, ?, ?, 'ehdotettu')",
+            )
+            .bind(change.jasen_id)
+            .bind(&change.vanha_tyyppi)
+            .bind(&change.uusi_tyyppi)
+            .bind(change.ika)
+            .bind(year)
+            .bind(change.saanto_id)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+
+        self.get_member_type_change_log(Some(&MemberTypeChangeStatus::Ehdotettu))
+            .await
+    }
+
+    // Hyväksyy ehdotuksen ja vaihtaa jäsenen tyypin. Jos jäsenen tyyppiä on
+    // muutettu ehdotuksen jälkeen, ehdotusta ei voi enää hyväksyä.
+    pub async fn approve_member_type_change(&self, id: i64) -> Result<MemberTypeChangeLog> {
+        let change = self.get_member_type_change(id).await?;
+        if change.tila != MemberTypeChangeStatus::Ehdotettu.to_string() {
+            return Err(anyhow::anyhow!("Jäsentyyppimuutos {} on jo käsitelty.", id));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        let updated = sqlx::query(
+            "UPDATE members SET jasentyyppi = ?, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ? AND jasentyyppi = ?",
+        )
+        .bind(&change.uusi_tyyppi)
+        .bind(change.jasen_id)
+        .bind(&change.vanha_tyyppi)
+        .execute(&mut *tx)
+        .await?;
+
+        if updated.rows_affected() == 0 {
+            tx.rollback().await?;
+            return Err(anyhow::anyhow!(
+                "Jäsenen {} tyyppi ei ole enää {}. Hylkää ehdotus ja laske muutokset uudelleen.",
+                fee_rules::full_name(&change.etunimi, &change.sukunimi),
+                change.vanha_tyyppi
+            ));
+        }
+
+        sqlx::query(
+            "UPDATE member_type_changes SET tila = 'hyvaksytty', kasitelty = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        self.get_member_type_change(id).await
+    }
+
+    pub async fn reject_member_type_change(&self, id: i64) -> Result<MemberTypeChangeLog> {
+        let change = self.get_member_type_change(id).await?;
+        if change.tila != MemberTypeChangeStatus::Ehdotettu.to_string() {
+            return Err(anyhow::anyhow!("Jäsentyyppimuutos {} on jo käsitelty.", id));
+        }
+
+        sqlx::query(
+            "UPDATE member_type_changes SET tila = 'hylatty', kasitelty = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_member_type_change(id).await
+    }
+
+    // Vuosilaskutus käyttää vuoden vuosikautta, joka luodaan tarvittaessa
+    pub async fn create_invoice_for_year(
+        &self,
+        year: i32,
+        due_date: Option<chrono::NaiveDate>,
+    ) -> Result<InvoiceBatchResult> {
+        let period = self.get_or_create_year_period(year).await?;
+        self.create_invoices_for_period(period.id, due_date).await
+    }
+
+    pub async fn create_invoices_for_period(
+        &self,
+        period_id: i64,
+        due_date: Option<chrono::NaiveDate>,
+    ) -> Result<InvoiceBatchResult> {
+        self.create_invoices_for_selection(period_id, &InvoiceSelection::default(), due_date)
+            .await
+    }
+
+    // Laskutusajo yhtenä transaktiona: laskut ja rivit tallentuvat kaikki tai ei
+    // mitään. Suunnitelma lasketaan samassa transaktiossa samalla tavalla kuin
+    // esikatselussa, joten rinnakkainen ajo ei voi laskuttaa samoja jäseniä.
+    // Ikään perustuvat tyyppimuutokset käsitellään erikseen ennen laskutusta.
+    pub async fn create_invoices_for_selection(
+        &self,
+        period_id: i64,
+        selection: &InvoiceSelection,
+        due_date: Option<chrono::NaiveDate>,
+    ) -> Result<InvoiceBatchResult> {
+        let period = self.get_billing_period(period_id).await?;
+
+        let current_date = chrono::Utc::now().date_naive();
+        // Eräpäivä: annettu, kauden oletus tai 30 päivää
+        let due_date = due_date
+            .or(period.oletuserapaiva)
+            .unwrap_or_else(|| current_date + chrono::Duration::days(30));
+
+        let mut tx = self.pool.begin().await?;
+        let (_, plan) = Self::plan_selection(&mut tx, &period, selection).await?;
+        let (batch_id, created_invoices) = Self::insert_invoice_batch(
+            &mut tx,
+            Some(period.id),
+            period.maksuvuosi,
+            &plan,
+            current_date,
+            due_date,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(InvoiceBatchResult {
+            ajo: self.get_invoice_batch(batch_id).await?,
+            laskut: created_invoices,
+        })
+    }
+
+    // Tallentaa laskutusajon ja sen laskut. Palauttaa ajon tunnisteen ja laskut.
+    async fn insert_invoice_batch(
+        conn: &mut SqliteConnection,
+        period_id: Option<i64>,
+        year: i32,
+        plan: &[InvoiceDraft],
+        luontipaiva: chrono::NaiveDate,
+        erapaiva: chrono::NaiveDate,
+    ) -> Result<(i64, Vec<Invoice>)> {
+        let total: f64 = plan.iter().map(|d| d.total()).sum();
+        let total = (total * 100.0).round() / 100.0;
+
+        let batch_id = sqlx::query(
+            "INSERT INTO invoice_batches (kausi_id, laskuja, summa) VALUES (?, ?, ?)",
+        )
+        .bind(period_id)
+        .bind(plan.len() as i64)
+        .bind(total)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+
+        let mut created_invoices = Vec::new();
+        for draft in plan {
+            let invoice =
+                Self::insert_invoice(conn, year, draft, luontipaiva, erapaiva, Some(batch_id))
+                    .await?;
+            created_invoices.push(invoice);
+        }
+
+        Ok((batch_id, created_invoices))
+    }
+
+    fn invoice_batch_from_row(row: &sqlx::sqlite::SqliteRow) -> InvoiceBatch {
+        InvoiceBatch {
+            id: row.get("id"),
+            kausi_id: row.get("kausi_id"),
+            laskuja: row.get("laskuja"),
+            summa: row
+                .try_get::<f64, _>("summa")
+                .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
+            peruttu: row.get("peruttu"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    pub async fn get_invoice_batches(&self) -> Result<Vec<InvoiceBatch>> {
+        let rows = sqlx::query("SELECT * FROM invoice_batches ORDER BY id DESC")
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok(rows.iter().map(Self::invoice_batch_from_row).collect())
+    }
+
+    pub async fn get_invoice_batch(&self, id: i64) -> Result<InvoiceBatch> {
+        let row = sqlx::query("SELECT * FROM invoice_batches WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Laskutusajoa ei löytynyt ID:llä {}", id))?;
+
+        Ok(Self::invoice_batch_from_row(&row))
+    }
+
+    // Peruu laskutusajon: poistaa sen laskut riveineen. Mahdollista vain, jos
+    // yhtään laskua ei ole lähetetty, maksettu tai viety Tilitiniin. Kokonaan
+    // ennakkomaksulla katettu lasku ei estä perumista, ja ennakkomaksu palautuu
+    // talouden saldoon laskun mukana.
+    pub async fn rollback_invoice_batch(&self, id: i64) -> Result<InvoiceBatch> {
+        let batch = self.get_invoice_batch(id).await?;
+        if batch.peruttu.is_some() {
+            return Err(anyhow::anyhow!("Laskutusajo {} on jo peruttu.", id));
+        }
+
+        let locked = sqlx::query(
+            "SELECT COUNT(*) as count FROM invoices
+             WHERE ajo_id = ?
+               AND ((maksettu = 1 AND NOT (summa = 0 AND id IN (SELECT lasku_id FROM household_credits WHERE lasku_id IS NOT NULL)))
+                    OR lahetetty IS NOT NULL OR luottotappio_pvm IS NOT NULL)",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("count");
+
+        if locked > 0 {
+            return Err(anyhow::anyhow!(
+                "Laskutusajoa ei voi perua: {} laskua on jo lähetetty tai maksettu.",
+                locked
+            ));
+        }
+
+        let exported = sqlx::query(
+            "SELECT COUNT(DISTINCT lasku_id) as count FROM tilitin_exports
+             WHERE lasku_id IN (SELECT id FROM invoices WHERE ajo_id = ?)",
+        )
+        .bind(id)
+        .fetch_one(&self.pool)
+        .await?
+        .get::<i64, _>("count");
+
+        if exported > 0 {
+            return Err(anyhow::anyhow!(
+                "Laskutusajoa ei voi perua: {} laskua on jo viety Tilitiniin. Korjaa kirjanpito ennen perumista.",
+                exported
+            ));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query(
+            "DELETE FROM invoice_lines WHERE lasku_id IN (SELECT id FROM invoices WHERE ajo_id = ?)",
+        )
+        .bind(id)
+        .execute(&mut *tx)
+        .await?;
+
+        sqlx::query("DELETE FROM invoices WHERE ajo_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE invoice_batches SET peruttu = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        self.get_invoice_batch(id).await
+    }
+
+    // Laskutussuunnitelma ja laskutettavien jäsenten käsittelemättömät
+    // tyyppimuutokset, jotka näytetään esikatselussa. Suunnitelma käyttää
+    // jäsenten nykyisiä tyyppejä.
+    async fn plan_selection(
+        conn: &mut SqliteConnection,
+        period: &BillingPeriod,
+        selection: &InvoiceSelection,
+    ) -> Result<(Vec<MemberTypeChange>, Vec<InvoiceDraft>)> {
+        if let Some(member_id) = selection.jasen_id {
+            sqlx::query("SELECT id FROM members WHERE id = ?")
+                .bind(member_id)
+                .fetch_optional(&mut *conn)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Jäsentä ei löytynyt ID:llä {}", member_id))?;
+        }
+
+        let mut type_changes = Self::pending_member_type_changes(conn, period.maksuvuosi).await?;
+        let plan = Self::plan_invoices_for_period(conn, period, selection).await?;
+
+        let unrestricted = selection.talous_idt.is_none() && !selection.is_member_level();
+        if !unrestricted {
+            if plan.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "Valinnalla ei löytynyt laskutettavia jäseniä kaudelle {}.",
+                    period.nimi
+                ));
+            }
+            type_changes.retain(|c| {
+                plan.iter()
+                    .flat_map(|d| d.lines.iter())
+                    .any(|l| l.jasen_id == Some(c.jasen_id))
+            });
+        }
+
+        let mut plan = plan;
+        Self::apply_household_credits(conn, &mut plan).await?;
+
+        Ok((type_changes, plan))
+    }
+
+    // Talouden käyttämätön ennakkomaksu vähennetään talouden seuraavilta
+    // laskuilta omana rivinään. Huoltajalle ja asiakkaalle menevillä laskuilla
+    // hyvitystä ei käytetä, koska maksaja ei ole talous.
+    async fn apply_household_credits(
+        conn: &mut SqliteConnection,
+        plan: &mut [InvoiceDraft],
+    ) -> Result<()> {
+        let mut balances: std::collections::HashMap<i64, f64> = sqlx::query(
+            "SELECT talous_id, CAST(SUM(summa) AS REAL) as saldo FROM household_credits
+             GROUP BY talous_id HAVING SUM(summa) > 0",
+        )
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|row| (row.get("talous_id"), row.get("saldo")))
+        .collect();
+
+        for draft in plan.iter_mut() {
+            if draft.huoltaja_id.is_some() || draft.asiakas_id.is_some() {
+                continue;
+            }
+            let Some(balance) = draft.talous_id.and_then(|id| balances.get_mut(&id)) else {
+                continue;
+            };
+            let total = draft.total();
+            let used = (balance.min(total) * 100.0).round() / 100.0;
+            if used <= 0.0 {
+                continue;
+            }
+            *balance -= used;
+            draft.lines.push(LineDraft {
+                jasen_id: None,
+                maksu_id: None,
+                kuvaus: "Ennakkomaksu".to_string(),
+                summa: -used,
+                ennakko: true,
+            });
+        }
+        Ok(())
+    }
+
+    async fn get_billing_mode(conn: &mut SqliteConnection) -> Result<BillingMode> {
+        let mode: Option<String> = sqlx::query("SELECT laskutustapa FROM organization LIMIT 1")
+            .fetch_optional(conn)
+            .await?
+            .map(|row| row.get("laskutustapa"));
+
+        match mode {
+            Some(mode) => mode.parse().map_err(|e: String| anyhow::anyhow!(e)),
+            None => Ok(BillingMode::default()),
+        }
+    }
+
+    // Jäsenet, jotka laskutetaan omalla laskullaan talouden laskun sijaan
+    async fn get_separately_invoiced_members(conn: &mut SqliteConnection) -> Result<Vec<i64>> {
+        Ok(sqlx::query("SELECT id FROM members WHERE oma_lasku = 1")
+            .fetch_all(conn)
+            .await?
+            .iter()
+            .map(|row| row.get("id"))
+            .collect())
+    }
+
+    // Huoltajan maksamat jäsenet: (jäsen, huoltajarivi, huoltajajäsenen talous)
+    async fn get_guardian_links(
+        conn: &mut SqliteConnection,
+    ) -> Result<Vec<(i64, i64, Option<i64>)>> {
+        Ok(sqlx::query(
+            "SELECT g.jasen_id, g.id, a.talous_id
+             FROM member_guardians g
+             LEFT JOIN members gm ON g.huoltaja_jasen_id = gm.id
+             LEFT JOIN addresses a ON gm.osoite_id = a.id",
+        )
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|row| (row.get("jasen_id"), row.get("id"), row.get("talous_id")))
+        .collect())
+    }
+
+    // Kauden laskutettavat jäsenet: aktiiviset laskutettavan tyypin jäsenet ja
+    // valinnaisen lisämaksun tilanneet, joilla ei vielä ole riviä kauden laskuilla.
+    // Palauttaa (talous, jäsen, onko taloudella jo kauden lasku) talouksittain.
+    async fn get_invoice_candidates(
+        conn: &mut SqliteConnection,
+        period: &BillingPeriod,
+        selection: &InvoiceSelection,
+    ) -> Result<Vec<(i64, i64, bool)>> {
+        let rows = sqlx::query(
+            "SELECT a.talous_id, m.id as member_id, m.liittymispaiva, m.jasentyyppi,
+                    EXISTS (
+                        SELECT 1 FROM invoices i
+                        WHERE i.talous_id = a.talous_id AND i.kausi_id = ?
+                    ) as talous_laskutettu
+             FROM members m
+             JOIN addresses a ON m.osoite_id = a.id
+             JOIN member_types mt ON mt.nimi = m.jasentyyppi
+             WHERE m.aktiivinen = 1
+             AND (mt.laskutettava = 1 OR EXISTS (
+                 SELECT 1 FROM member_fee_items mfi
+                 JOIN fee_items fi ON fi.id = mfi.maksu_id
+                 WHERE mfi.jasen_id = m.id AND fi.aktiivinen = 1
+             ))
+             AND NOT EXISTS (
+                 SELECT 1 FROM invoice_lines il
+                 JOIN invoices i ON il.lasku_id = i.id
+                 WHERE il.jasen_id = m.id
+                 AND i.kausi_id = ?
+             )
+             ORDER BY a.talous_id, m.id",
+        )
+        .bind(period.id)
+        .bind(period.id)
+        .fetch_all(conn)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .filter(|row| {
+                selection.matches(
+                    row.get("talous_id"),
+                    row.get("member_id"),
+                    row.get("liittymispaiva"),
+                    row.get::<&str, _>("jasentyyppi"),
+                )
+            })
+            .map(|row| {
+                (
+                    row.get("talous_id"),
+                    row.get("member_id"),
+                    row.get("talous_laskutettu"),
+                )
+            })
+            .collect())
+    }
+
+    // Talouden jäsenet, joilla on jo rivejä kauden laskuilla, ja heidän saamansa
+    // alennukset (negatiiviset rivit, jotka eivät ole lisämaksuja)
+    async fn get_invoiced_members_for_period(
+        conn: &mut SqliteConnection,
+        household_id: i64,
+        period_id: i64,
+    ) -> Result<Vec<(i64, f64)>> {
+        Ok(sqlx::query(
+            "SELECT il.jasen_id,
+                    CAST(COALESCE(SUM(CASE WHEN il.summa < 0 AND il.maksu_id IS NULL
+                                      THEN -il.summa ELSE 0 END), 0) AS REAL) as alennus
+             FROM invoice_lines il
+             JOIN invoices i ON il.lasku_id = i.id
+             WHERE i.talous_id = ? AND i.kausi_id = ? AND il.jasen_id IS NOT NULL
+             GROUP BY il.jasen_id",
+        )
+        .bind(household_id)
+        .bind(period_id)
+        .fetch_all(conn)
+        .await?
+        .iter()
+        .map(|row| (row.get("jasen_id"), row.get("alennus")))
+        .collect())
+    }
+
+    // Kauden laskutussuunnitelma: laskut uusille talouksille sekä lisälaskut
+    // jo laskutettujen talouksien laskuttamattomille jäsenille.
+    async fn plan_invoices_for_period(
+        conn: &mut SqliteConnection,
+        period: &BillingPeriod,
+        selection: &InvoiceSelection,
+    ) -> Result<Vec<InvoiceDraft>> {
+        let year = period.maksuvuosi;
+
+        let mut by_household: Vec<(i64, bool, Vec<i64>)> = Vec::new();
+        for (household_id, member_id, invoiced) in
+            Self::get_invoice_candidates(conn, period, selection).await?
+        {
+            match by_household.last_mut() {
+                Some((id, _, members)) if *id == household_id => members.push(member_id),
+                _ => by_household.push((household_id, invoiced, vec![member_id])),
+            }
+        }
+
+        let fee_rules = Self::get_fee_rules_for_year(conn, year).await?;
+        let fee_items = Self::get_fee_items_for_year(conn, year).await?;
+        let billing_mode = Self::get_billing_mode(conn).await?;
+        let separate = Self::get_separately_invoiced_members(conn).await?;
+        let guardians = Self::get_guardian_links(conn).await?;
+        let mut plan = Vec::new();
+
+        for (household_id, invoiced, selected) in by_household {
+            let members = Self::get_household_members_for_year(conn, household_id, year).await?;
+            let invoiced_members = if invoiced {
+                Some(Self::get_invoiced_members_for_period(conn, household_id, period.id).await?)
+            } else {
+                None
+            };
+
+            let lines = if !invoiced && !selection.is_member_level() {
+                // Koko talouden lasku
+                fee_rules::household_lines(year, &period.nimi, &fee_rules, &fee_items, &members)
+            } else {
+                fee_rules::member_subset_lines(
+                    year,
+                    &period.nimi,
+                    &fee_rules,
+                    &fee_items,
+                    &members,
+                    &selected,
+                    invoiced_members.as_deref(),
+                )
+            };
+
+            // Jo laskutetun talouden lasku on lisälasku kauden varsinaisen laskun lisäksi
+            let draft = InvoiceDraft {
+                talous_id: Some(household_id),
+                asiakas_id: None,
+                jasen_id: None,
+                huoltaja_id: None,
+                kausi_id: Some(period.id),
+                lisalasku: invoiced,
+                viesti: invoiced.then(|| {
+                    format!(
+                        "Lisälasku kauden {} jäsenmaksuista uusille jäsenille.",
+                        period.nimi
+                    )
+                }),
+                lines,
+            };
+
+            // Huoltajan maksama jäsen saa oman laskun, ellei huoltaja ole saman talouden jäsen
+            let paid_by_guardian = |id: i64| {
+                guardians
+                    .iter()
+                    .any(|&(member_id, _, guardian_household)| {
+                        member_id == id && guardian_household != Some(household_id)
+                    })
+            };
+            let mut drafts = fee_rules::split_by_member(draft, |id| {
+                paid_by_guardian(id)
+                    || match billing_mode {
+                        BillingMode::Talous => false,
+                        BillingMode::Jasen => true,
+                        BillingMode::TalousErikseen => separate.contains(&id),
+                    }
+            });
+            for draft in drafts.iter_mut() {
+                draft.huoltaja_id = draft.jasen_id.and_then(|id| {
+                    guardians
+                        .iter()
+                        .find(|&&(member_id, _, _)| member_id == id)
+                        .map(|&(_, guardian_id, _)| guardian_id)
+                });
+            }
+
+            // Ei laskutettavaa, esim. maksukaton ylittävä jäsen ei kasvata talouden maksua
+            plan.extend(drafts.into_iter().filter(|d| d.total() > 0.0));
+        }
+
+        Ok(plan)
+    }
+
+    // Laskunumero ja viitenumero. Talouden ensimmäinen lasku vuodelle saa muodon
+    // "2025-007" / 202500007X, seuraavat juoksevan lisäosan "2025-007-2" / 20250000702X.
+    // Numero ja viite muodostetaan laskun saajasta. Jäsenkohtaisen laskun numeroon
+    // lisätään jäsenen tunniste; asiakaslaskun viite alkaa vuoden jälkeen numerolla 9.
+    // Jäsenkohtaisen laskun numero "2025-007-2" voi olla myös talouden toinen lasku,
+    // joten numero tarkistetaan kaikista laskuista. Varatut numerot ohitetaan.
+    async fn generate_invoice_numbers(
+        conn: &mut SqliteConnection,
+        year: i32,
+        draft: &InvoiceDraft,
+        reserved: &[String],
+    ) -> Result<(String, String)> {
+        let (reference_base, invoice_number_base) =
+            match (draft.asiakas_id, draft.talous_id, draft.jasen_id) {
+                (Some(customer_id), _, _) => (
+                    format!("{}9{:05}", year, customer_id),
+                    format!("{}-A{:03}", year, customer_id),
+                ),
+                (None, Some(household_id), Some(member_id)) => (
+                    format!("{}{:05}{:05}", year, household_id, member_id),
+                    format!("{}-{:03}-{}", year, household_id, member_id),
+                ),
+                (None, Some(household_id), None) => (
+                    format!("{}{:05}", year, household_id),
+                    format!("{}-{:03}", year, household_id),
+                ),
+                (None, None, _) => {
+                    return Err(anyhow::anyhow!("Laskulla on oltava talous tai asiakas."))
+                }
+            };
+        let mut sequence: i64 = 1;
+
+        loop {
+            let (base_reference, invoice_number) = if sequence == 1 {
+                (reference_base.clone(), invoice_number_base.clone())
+            } else {
+                (
+                    format!("{}{:02}", reference_base, sequence),
+                    format!("{}-{}", invoice_number_base, sequence),
+                )
+            };
+
+            // Generoi viitenumero suomalaisella tarkistussummalla
+            let check_digit = calculate_finnish_check_digit(&base_reference);
+            let reference_number = format!("{}{}", base_reference, check_digit);
+
+            // Ensimmäinen vapaa numero; poistetun laskun numero voi tulla uudelleen käyttöön
+            let taken = sqlx::query(
+                "SELECT COUNT(*) as count FROM invoices WHERE viitenumero = ? OR laskunumero = ?",
+            )
+            .bind(&reference_number)
+            .bind(&invoice_number)
+            .fetch_one(&mut *conn)
+            .await?
+            .get::<i64, _>("count")
+                > 0;
+
+            if !taken && !reserved.contains(&invoice_number) {
+                return Ok((reference_number, invoice_number));
+            }
+            sequence += 1;
+        }
+    }
+
+    // Tallentaa laskun riveineen kutsujan transaktiossa; summa lasketaan riveistä
+    async fn insert_invoice(
+        conn: &mut SqliteConnection,
+        year: i32,
+        draft: &InvoiceDraft,
+        luontipaiva: chrono::NaiveDate,
+        erapaiva: chrono::NaiveDate,
+        batch_id: Option<i64>,
+    ) -> Result<Invoice> {
+        let total_sum = draft.total();
+
+        // Huoltajan tiedot tallennetaan laskulle sellaisinaan
+        let payer = match draft.huoltaja_id {
+            Some(guardian_id) => Self::get_guardian_payer(&mut *conn, guardian_id).await?,
+            None => None,
+        };
+
+        // Luo lasku. Jos rinnakkainen laskutus ehti käyttää numeron, yksilöllinen
+        // indeksi hylkää sen ja laskulle haetaan seuraava vapaa numero.
+        let mut reserved = Vec::new();
+        let (invoice_id, reference_number, invoice_number) = loop {
+            let (reference_number, invoice_number) =
+                Self::generate_invoice_numbers(&mut *conn, year, draft, &reserved).await?;
+
+            let result = sqlx::query(
+                "INSERT INTO invoices (talous_id, asiakas_id, jasen_id, huoltaja_id, maksaja_nimi, maksaja_katuosoite, maksaja_postinumero, maksaja_postitoimipaikka, maksaja_sahkoposti, luontipaiva, erapaiva, summa, viitenumero, laskunumero, maksettu, viesti, kausi_id, lisalasku, ajo_id)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?, ?, ?)"
+            )
+            .bind(draft.talous_id)
+            .bind(draft.asiakas_id)
+            .bind(draft.jasen_id)
+            .bind(draft.huoltaja_id)
+            .bind(payer.as_ref().map(|p| &p.nimi))
+            .bind(payer.as_ref().and_then(|p| p.katuosoite.as_ref()))
+            .bind(payer.as_ref().and_then(|p| p.postinumero.as_ref()))
+            .bind(payer.as_ref().and_then(|p| p.postitoimipaikka.as_ref()))
+            .bind(payer.as_ref().and_then(|p| p.sahkoposti.as_ref()))
+            .bind(luontipaiva)
+            .bind(erapaiva)
+            .bind(total_sum)
+            .bind(&reference_number)
+            .bind(&invoice_number)
+            .bind(&draft.viesti)
+            .bind(draft.kausi_id)
+            .bind(draft.lisalasku)
+            .bind(batch_id)
+            .execute(&mut *conn)
+            .await;
+
+            match result {
+                Ok(result) => break (result.last_insert_rowid(), reference_number, invoice_number),
+                Err(sqlx::Error::Database(e))
+                    if e.is_unique_violation() && e.message().contains("laskunumero") =>
+                {
+                    reserved.push(invoice_number);
+                }
+                Err(e) => return Err(e.into()),
+            }
+        };
+
+        // Luo laskurivit: jäsenmaksut, alennukset ja lisämaksut
+        for line in &draft.lines {
+            let line_id = sqlx::query(
+                "INSERT INTO invoice_lines (lasku_id, jasen_id, maksu_id, kuvaus, summa)
+                 VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(invoice_id)
+            .bind(line.jasen_id)
+            .bind(line.maksu_id)
+            .bind(&line.kuvaus)
+            .bind(line.summa)
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid();
+
+            // Ennakkorivi kirjataan talouden hyvityksistä käytetyksi
+            if line.ennakko {
+                sqlx::query(
+                    "INSERT INTO household_credits (talous_id, paivamaara, summa, selite, lasku_id, rivi_id)
+                     VALUES (?, ?, ?, ?, ?, ?)",
+                )
+                .bind(draft.talous_id)
+                .bind(luontipaiva)
+                .bind(line.summa)
+                .bind(format!("Käytetty laskulla {}", invoice_number))
+                .bind(invoice_id)
+                .bind(line_id)
+                .execute(&mut *conn)
+                .await?;
+            }
+        }
+
+        // Kokonaan ennakkomaksulla katettu lasku on maksettu
+        let prepaid = total_sum == 0.0 && draft.lines.iter().any(|l| l.ennakko);
+        if prepaid {
+            sqlx::query("UPDATE invoices SET maksettu = 1, maksupaiva = ? WHERE id = ?")
+                .bind(luontipaiva)
+                .bind(invoice_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+
+        Ok(Invoice {
+            id: invoice_id,
+            talous_id: draft.talous_id,
+            luontipaiva,
+            erapaiva,
+            summa: total_sum,
+            viitenumero: reference_number,
+            laskunumero: Some(invoice_number),
+            maksettu: prepaid,
+            maksupaiva: prepaid.then_some(luontipaiva),
+            viesti: draft.viesti.clone(),
+            lahetetty: None,
+            kausi_id: draft.kausi_id,
+            lisalasku: draft.lisalasku,
+            ajo_id: batch_id,
+            asiakas_id: draft.asiakas_id,
+            jasen_id: draft.jasen_id,
+            huoltaja_id: draft.huoltaja_id,
+            luottotappio_pvm: None,
+            luottotappio_syy: None,
+            created_at: chrono::Utc::now(),
+            updated_at: chrono::Utc::now(),
+        })
+    }
+
+    // Käsin luotu lasku taloudelle tai jäsenelle (tapahtumat, jäsenkortit ym.).
+    // Negatiivinen summa tekee laskusta hyvityslaskun.
+    pub async fn create_manual_invoice(&self, invoice: &CreateManualInvoice) -> Result<Invoice> {
+        if invoice.lines.is_empty() {
+            return Err(anyhow::anyhow!("Laskulla on oltava vähintään yksi rivi."));
+        }
+
+        // Jäsenelle osoitettu lasku menee jäsenen taloudelle
+        let household_id = match (invoice.talous_id, invoice.jasen_id) {
+            (_, Some(member_id)) => Some(
+                sqlx::query(
+                    "SELECT a.talous_id FROM members m
+                     JOIN addresses a ON m.osoite_id = a.id
+                     WHERE m.id = ?",
+                )
+                .bind(member_id)
+                .fetch_optional(&self.pool)
+                .await?
+                .map(|row| row.get::<i64, _>("talous_id"))
+                .ok_or_else(|| anyhow::anyhow!("Jäsentä ei löytynyt ID:llä {}", member_id))?,
+            ),
+            (Some(household_id), None) => {
+                let exists = sqlx::query("SELECT COUNT(*) as count FROM households WHERE id = ?")
+                    .bind(household_id)
+                    .fetch_one(&self.pool)
+                    .await?
+                    .get::<i64, _>("count")
+                    > 0;
+                if !exists {
+                    return Err(anyhow::anyhow!("Taloutta ei löytynyt ID:llä {}", household_id));
+                }
+                Some(household_id)
+            }
+            (None, None) => None,
+        };
+
+        // Asiakkaalle tehdyn laskun saaja on asiakas; jäsenrivit viittaavat silti jäseniin
+        if let Some(customer_id) = invoice.asiakas_id {
+            if !self.get_customer(customer_id).await?.aktiivinen {
+                return Err(anyhow::anyhow!("Asiakas ei ole aktiivinen."));
+            }
+        } else if household_id.is_none() {
+            return Err(anyhow::anyhow!(
+                "Laskun saajaksi on valittava talous, jäsen tai asiakas."
+            ));
+        }
+
+        let mut lines = Vec::new();
+        for line in &invoice.lines {
+            if line.kuvaus.trim().is_empty() {
+                return Err(anyhow::anyhow!("Laskurivin kuvaus on pakollinen."));
+            }
+
+            lines.push(LineDraft {
+                jasen_id: line.jasen_id.or(invoice.jasen_id),
+                maksu_id: line.maksu_id,
+                kuvaus: line.kuvaus.trim().to_string(),
+                summa: (line.summa * 100.0).round() / 100.0,
+                ennakko: false,
+            });
+        }
+
+        // Jäsenelle tehty lasku on jäsenkohtainen, jos laskutustapa sen mukaan erottaa
+        // jäsenen tai jäsenen maksaa toisen talouden huoltaja
+        let mut conn = self.pool.acquire().await?;
+        let guardian = match invoice.jasen_id {
+            Some(member_id) => Self::get_guardian_links(&mut conn)
+                .await?
+                .into_iter()
+                .find(|&(id, _, _)| id == member_id),
+            None => None,
+        };
+        let recipient = match (Self::get_billing_mode(&mut conn).await?, invoice.jasen_id) {
+            _ if invoice.asiakas_id.is_some() => None,
+            (_, Some(member_id))
+                if guardian.is_some_and(|(_, _, household)| household != household_id) =>
+            {
+                Some(member_id)
+            }
+            (BillingMode::Jasen, Some(member_id)) => Some(member_id),
+            (BillingMode::TalousErikseen, Some(member_id))
+                if Self::get_separately_invoiced_members(&mut conn)
+                    .await?
+                    .contains(&member_id) =>
+            {
+                Some(member_id)
+            }
+            _ => None,
+        };
+
+        // Käsin luotu lasku ei kuulu laskutuskauteen eikä laskutusajoon
+        let draft = InvoiceDraft {
+            talous_id: household_id,
+            asiakas_id: invoice.asiakas_id,
+            jasen_id: recipient,
+            huoltaja_id: recipient.and(guardian).map(|(_, guardian_id, _)| guardian_id),
+            kausi_id: None,
+            lisalasku: false,
+            viesti: invoice
+                .viesti
+                .as_deref()
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| v.to_string()),
+            lines,
+        };
+        if draft.total() == 0.0 {
+            return Err(anyhow::anyhow!("Laskun summa ei voi olla nolla."));
+        }
+
+        let current_date = chrono::Utc::now().date_naive();
+        let mut tx = conn.begin().await?;
+        let created = Self::insert_invoice(
+            &mut tx,
+            current_date.year(),
+            &draft,
+            current_date,
+            invoice.erapaiva,
+            None,
+        )
+        .await?;
+        tx.commit().await?;
+
+        Ok(created)
+    }
+
+    // Talouden aktiiviset jäsenet jäsenmaksuineen, tilauksineen ja aiemmin laskutettuine lisämaksuineen
+    async fn get_household_members_for_year(
+        conn: &mut SqliteConnection,
+        household_id: i64,
+        year: i32,
+    ) -> Result<Vec<HouseholdMember>> {
+        let rows = sqlx::query(
+            "SELECT m.id, m.etunimi, m.sukunimi, m.jasentyyppi, m.liittymispaiva,
+                    mt.laskutettava, COALESCE(mf.summa, 0.0) as summa
+             FROM members m
+             JOIN addresses a ON m.osoite_id = a.id
+             JOIN member_types mt ON mt.nimi = m.jasentyyppi
+             LEFT JOIN membership_fees mf ON m.jasentyyppi = mf.jasentyyppi AND mf.vuosi = ?
+             WHERE a.talous_id = ? AND m.aktiivinen = 1
+             ORDER BY m.sukunimi, m.etunimi",
+        )
+        .bind(year)
+        .bind(household_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let subscriptions = sqlx::query(
+            "SELECT mfi.jasen_id, mfi.maksu_id FROM member_fee_items mfi
+             JOIN members m ON mfi.jasen_id = m.id
+             JOIN addresses a ON m.osoite_id = a.id
+             WHERE a.talous_id = ?",
+        )
+        .bind(household_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let billed_items = sqlx::query(
+            "SELECT DISTINCT il.jasen_id, il.maksu_id FROM invoice_lines il
+             JOIN members m ON il.jasen_id = m.id
+             JOIN addresses a ON m.osoite_id = a.id
+             WHERE a.talous_id = ? AND il.maksu_id IS NOT NULL",
+        )
+        .bind(household_id)
+        .fetch_all(&mut *conn)
+        .await?;
+
+        let mut members = Vec::new();
+        for row in rows {
+            let jasen_id: i64 = row.get("id");
+            // Kokeile ensin f64, sitten i64 ja muunna
+            let jasenmaksu = match row.try_get::<f64, _>("summa") {
+                Ok(val) => val,
+                Err(_) => row.get::<i64, _>("summa") as f64,
+            };
+
+            members.push(HouseholdMember {
+                jasen_id,
+                etunimi: row.get("etunimi"),
+                sukunimi: row.get("sukunimi"),
+                jasentyyppi: row.get("jasentyyppi"),
+                laskutettava: row.get("laskutettava"),
+                liittymispaiva: row.get("liittymispaiva"),
+                jasenmaksu,
+                tilatut_maksut: subscriptions
+                    .iter()
+                    .filter(|r| r.get::<i64, _>("jasen_id") == jasen_id)
+                    .map(|r| r.get("maksu_id"))
+                    .collect(),
+                laskutetut_maksut: billed_items
+                    .iter()
+                    .filter(|r| r.get::<i64, _>("jasen_id") == jasen_id)
+                    .map(|r| r.get("maksu_id"))
+                    .collect(),
+            });
+        }
+
+        Ok(members)
+    }
+
+    pub async fn mark_invoice_paid(
+        &self,
+        id: i64,
+        payment_date: chrono::NaiveDate,
+    ) -> Result<Invoice> {
+        let invoice = self.get_invoice(id).await?;
+        if invoice.luottotappio_pvm.is_some() {
+            return Err(anyhow::anyhow!(
+                "Lasku {} on kirjattu luottotappioksi. Peru luottotappio ennen maksun kirjaamista.",
+                invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero)
+            ));
+        }
+
+        // Päivitä lasku maksetuksi
+        sqlx::query(
+            "UPDATE invoices SET maksettu = 1, maksupaiva = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(payment_date)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        // Hae päivitetty lasku
+        self.get_invoice(id).await
+    }
+
+    fn invoice_from_row(row: &sqlx::sqlite::SqliteRow) -> Invoice {
+        Invoice {
+            id: row.get("id"),
+            talous_id: row.get("talous_id"),
+            asiakas_id: row.try_get("asiakas_id").ok().flatten(),
+            luontipaiva: row.get("luontipaiva"),
+            erapaiva: row.get("erapaiva"),
+            summa: row
+                .try_get::<f64, _>("summa")
+                .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
+            viitenumero: row.get("viitenumero"),
+            laskunumero: row.get("laskunumero"),
+            maksettu: row.get::<i64, _>("maksettu") != 0,
+            maksupaiva: row.get("maksupaiva"),
+            viesti: row.try_get("viesti").ok().flatten(),
+            lahetetty: row.try_get("lahetetty").ok().flatten(),
+            kausi_id: row.try_get("kausi_id").ok().flatten(),
+            lisalasku: row.try_get("lisalasku").unwrap_or(false),
+            ajo_id: row.try_get("ajo_id").ok().flatten(),
+            jasen_id: row.try_get("jasen_id").ok().flatten(),
+            huoltaja_id: row.try_get("huoltaja_id").ok().flatten(),
+            luottotappio_pvm: row.try_get("luottotappio_pvm").ok().flatten(),
+            luottotappio_syy: row.try_get("luottotappio_syy").ok().flatten(),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        }
+    }
+
+    async fn get_invoice(&self, id: i64) -> Result<Invoice> {
+        let row = sqlx::query("SELECT * FROM invoices WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Laskua ei löytynyt ID:llä {}", id))?;
+
+        Ok(Self::invoice_from_row(&row))
+    }
+
+    // Laskua voi muokata vain, kun sitä ei ole maksettu eikä lähetetty
+    async fn get_editable_invoice(&self, id: i64) -> Result<Invoice> {
+        let invoice = self.get_invoice(id).await?;
+        if invoice.maksettu {
+            return Err(anyhow::anyhow!(
+                "Maksettua laskua {} ei voi muokata.",
+                invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero)
+            ));
+        }
+        if invoice.lahetetty.is_some() {
+            return Err(anyhow::anyhow!(
+                "Lähetettyä laskua {} ei voi muokata.",
+                invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero)
+            ));
+        }
+        if invoice.luottotappio_pvm.is_some() {
+            return Err(anyhow::anyhow!(
+                "Luottotappioksi kirjattua laskua {} ei voi muokata.",
+                invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero)
+            ));
+        }
+        self.ensure_invoice_not_exported(&invoice).await?;
+        Ok(invoice)
+    }
+
+    async fn log_invoice_change(
+        conn: &mut SqliteConnection,
+        invoice_id: i64,
+        line_id: Option<i64>,
+        change: &str,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO invoice_changes (lasku_id, rivi_id, muutos, vanha_arvo, uusi_arvo)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(invoice_id)
+        .bind(line_id)
+        .bind(change)
+        .bind(old_value)
+        .bind(new_value)
+        .execute(&mut *conn)
+        .await?;
+        Ok(())
+    }
+
+    async fn invoice_lines_total(conn: &mut SqliteConnection, invoice_id: i64) -> Result<f64> {
+        let total: f64 = sqlx::query(
+            "SELECT CAST(COALESCE(SUM(summa), 0) AS REAL) as total FROM invoice_lines WHERE lasku_id = ?",
+        )
+        .bind(invoice_id)
+        .fetch_one(&mut *conn)
+        .await?
+        .get("total");
+        Ok((total * 100.0).round() / 100.0)
+    }
+
+    // Laskun summa lasketaan aina riveistä
+    async fn recalculate_invoice_total(conn: &mut SqliteConnection, invoice_id: i64) -> Result<()> {
+        let total = Self::invoice_lines_total(conn, invoice_id).await?;
+        sqlx::query("UPDATE invoices SET summa = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(total)
+            .bind(invoice_id)
+            .execute(&mut *conn)
+            .await?;
+        Ok(())
+    }
+
+    // Muokkaus ei saa muuttaa laskua hyvityslaskuksi tai päinvastoin
+    fn check_invoice_total(current: f64, total: f64) -> Result<()> {
+        let total = (total * 100.0).round() / 100.0;
+        if current < 0.0 {
+            if total >= 0.0 {
+                return Err(anyhow::anyhow!("Hyvityslaskun summan on oltava negatiivinen."));
+            }
+        } else if total <= 0.0 {
+            return Err(anyhow::anyhow!("Laskun summan on oltava suurempi kuin nolla."));
+        }
+        Ok(())
+    }
+
+    pub async fn update_invoice(&self, id: i64, update: &UpdateInvoice) -> Result<Invoice> {
+        let invoice = self.get_editable_invoice(id).await?;
+        let viesti = update
+            .viesti
+            .as_deref()
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(|v| v.to_string());
+
+        if update.erapaiva < invoice.luontipaiva {
+            return Err(anyhow::anyhow!("Eräpäivä ei voi olla ennen laskun päivämäärää."));
+        }
+
+        // Muutos ja sen lokirivi tallennetaan yhdessä
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE invoices SET erapaiva = ?, viesti = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(update.erapaiva)
+        .bind(&viesti)
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+
+        if update.erapaiva != invoice.erapaiva {
+            Self::log_invoice_change(
+                &mut transaction,
+                id,
+                None,
+                "erapaiva",
+                Some(invoice.erapaiva.to_string()),
+                Some(update.erapaiva.to_string()),
+            )
+            .await?;
+        }
+        if viesti != invoice.viesti {
+            Self::log_invoice_change(&mut transaction, id, None, "viesti", invoice.viesti, viesti)
+                .await?;
+        }
+        transaction.commit().await?;
+
+        self.get_invoice(id).await
+    }
+
+    fn validate_invoice_line(line: &CreateManualInvoiceLine) -> Result<CreateManualInvoiceLine> {
+        if line.kuvaus.trim().is_empty() {
+            return Err(anyhow::anyhow!("Laskurivin kuvaus on pakollinen."));
+        }
+        Ok(CreateManualInvoiceLine {
+            jasen_id: line.jasen_id,
+            maksu_id: line.maksu_id,
+            kuvaus: line.kuvaus.trim().to_string(),
+            summa: (line.summa * 100.0).round() / 100.0,
+        })
+    }
+
+    fn describe_invoice_line(kuvaus: &str, summa: f64) -> String {
+        format!("{}: {:.2} €", kuvaus, summa)
+    }
+
+    pub async fn add_invoice_line(
+        &self,
+        invoice_id: i64,
+        line: &CreateManualInvoiceLine,
+    ) -> Result<Invoice> {
+        let invoice = self.get_editable_invoice(invoice_id).await?;
+        let line = Self::validate_invoice_line(line)?;
+
+        let mut transaction = self.pool.begin().await?;
+        Self::check_invoice_total(
+            invoice.summa,
+            Self::invoice_lines_total(&mut transaction, invoice_id).await? + line.summa,
+        )?;
+
+        let line_id = sqlx::query(
+            "INSERT INTO invoice_lines (lasku_id, jasen_id, maksu_id, kuvaus, summa)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(invoice_id)
+        .bind(line.jasen_id)
+        .bind(line.maksu_id)
+        .bind(&line.kuvaus)
+        .bind(line.summa)
+        .execute(&mut *transaction)
+        .await?
+        .last_insert_rowid();
+
+        Self::recalculate_invoice_total(&mut transaction, invoice_id).await?;
+        Self::log_invoice_change(
+            &mut transaction,
+            invoice_id,
+            Some(line_id),
+            "rivi_lisatty",
+            None,
+            Some(Self::describe_invoice_line(&line.kuvaus, line.summa)),
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.get_invoice(invoice_id).await
+    }
+
+    // Palauttaa rivin laskun id:n, kuvauksen ja summan
+    async fn get_invoice_line_summary(&self, line_id: i64) -> Result<(i64, String, f64)> {
+        let row = sqlx::query("SELECT lasku_id, kuvaus, summa FROM invoice_lines WHERE id = ?")
+            .bind(line_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Laskuriviä ei löytynyt ID:llä {}", line_id))?;
+
+        let summa = match row.try_get::<f64, _>("summa") {
+            Ok(val) => val,
+            Err(_) => row.get::<i64, _>("summa") as f64,
+        };
+        Ok((row.get("lasku_id"), row.get("kuvaus"), summa))
+    }
+
+    pub async fn update_invoice_line(
+        &self,
+        line_id: i64,
+        line: &CreateManualInvoiceLine,
+    ) -> Result<Invoice> {
+        let (invoice_id, old_kuvaus, old_summa) = self.get_invoice_line_summary(line_id).await?;
+        let invoice = self.get_editable_invoice(invoice_id).await?;
+        let line = Self::validate_invoice_line(line)?;
+        let prepaid = sqlx::query("SELECT 1 FROM household_credits WHERE rivi_id = ?")
+            .bind(line_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+        if prepaid {
+            return Err(anyhow::anyhow!(
+                "Ennakkomaksuriviä ei voi muuttaa. Poista rivi, jolloin ennakkomaksu palautuu talouden saldoon."
+            ));
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        Self::check_invoice_total(
+            invoice.summa,
+            Self::invoice_lines_total(&mut transaction, invoice_id).await? - old_summa + line.summa,
+        )?;
+
+        sqlx::query(
+            "UPDATE invoice_lines SET jasen_id = ?, maksu_id = ?, kuvaus = ?, summa = ? WHERE id = ?",
+        )
+        .bind(line.jasen_id)
+        .bind(line.maksu_id)
+        .bind(&line.kuvaus)
+        .bind(line.summa)
+        .bind(line_id)
+        .execute(&mut *transaction)
+        .await?;
+
+        Self::recalculate_invoice_total(&mut transaction, invoice_id).await?;
+        Self::log_invoice_change(
+            &mut transaction,
+            invoice_id,
+            Some(line_id),
+            "rivi_muutettu",
+            Some(Self::describe_invoice_line(&old_kuvaus, old_summa)),
+            Some(Self::describe_invoice_line(&line.kuvaus, line.summa)),
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.get_invoice(invoice_id).await
+    }
+
+    pub async fn delete_invoice_line(&self, line_id: i64) -> Result<Invoice> {
+        let (invoice_id, old_kuvaus, old_summa) = self.get_invoice_line_summary(line_id).await?;
+        let invoice = self.get_editable_invoice(invoice_id).await?;
+
+        let line_count = sqlx::query("SELECT COUNT(*) as count FROM invoice_lines WHERE lasku_id = ?")
+            .bind(invoice_id)
+            .fetch_one(&self.pool)
+            .await?
+            .get::<i64, _>("count");
+        if line_count <= 1 {
+            return Err(anyhow::anyhow!(
+                "Laskulla on oltava vähintään yksi rivi. Poista tarvittaessa koko lasku."
+            ));
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        Self::check_invoice_total(
+            invoice.summa,
+            Self::invoice_lines_total(&mut transaction, invoice_id).await? - old_summa,
+        )?;
+
+        sqlx::query("DELETE FROM invoice_lines WHERE id = ?")
+            .bind(line_id)
+            .execute(&mut *transaction)
+            .await?;
+
+        Self::recalculate_invoice_total(&mut transaction, invoice_id).await?;
+        Self::log_invoice_change(
+            &mut transaction,
+            invoice_id,
+            Some(line_id),
+            "rivi_poistettu",
+            Some(Self::describe_invoice_line(&old_kuvaus, old_summa)),
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.get_invoice(invoice_id).await
+    }
+
+    // Merkitsee laskun lähetetyksi, minkä jälkeen sitä ei voi enää muokata
+    pub async fn mark_invoice_sent(&self, id: i64, sent_date: chrono::NaiveDate) -> Result<Invoice> {
+        let invoice = self.get_invoice(id).await?;
+        if invoice.lahetetty.is_some() {
+            return Ok(invoice);
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query("UPDATE invoices SET lahetetty = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?")
+            .bind(sent_date)
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+        Self::log_invoice_change(
+            &mut transaction,
+            id,
+            None,
+            "lahetetty",
+            None,
+            Some(sent_date.to_string()),
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.get_invoice(id).await
+    }
+
+    pub async fn get_invoice_changes(&self, invoice_id: i64) -> Result<Vec<InvoiceChange>> {
+        let rows = sqlx::query(
+            "SELECT * FROM invoice_changes WHERE lasku_id = ? ORDER BY created_at, id",
+        )
+        .bind(invoice_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| InvoiceChange {
+                id: row.get("id"),
+                lasku_id: row.get("lasku_id"),
+                rivi_id: row.get("rivi_id"),
+                muutos: row.get("muutos"),
+                vanha_arvo: row.get("vanha_arvo"),
+                uusi_arvo: row.get("uusi_arvo"),
+                created_at: row.get("created_at"),
+            })
+            .collect())
+    }
+
+    // Erääntyneet maksamattomat laskut, jotka voi kirjata luottotappioksi
+    pub async fn get_write_off_candidates(&self, due_before: chrono::NaiveDate) -> Result<Vec<Invoice>> {
+        let rows = sqlx::query(
+            "SELECT * FROM invoices
+             WHERE maksettu = 0 AND luottotappio_pvm IS NULL AND summa > 0 AND erapaiva < ?
+             ORDER BY erapaiva, id",
+        )
+        .bind(due_before)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::invoice_from_row).collect())
+    }
+
+    /// Kirjaa laskut luottotappioksi yhdessä transaktiossa. Laskut säilyvät
+    /// historiassa, mutta eivät ole enää avoimia saatavia. Erottamisehdotuksella
+    /// laskun aktiiviset jäsenet merkitään erottamisen käsittelyyn.
+    pub async fn write_off_invoices(&self, write_off: &CreateWriteOff) -> Result<Vec<Invoice>> {
+        let syy = write_off.syy.trim();
+        if syy.is_empty() {
+            return Err(anyhow::anyhow!("Luottotappion syy on pakollinen."));
+        }
+        if write_off.lasku_idt.is_empty() {
+            return Err(anyhow::anyhow!("Valitse luottotappioksi kirjattavat laskut."));
+        }
+
+        for &id in &write_off.lasku_idt {
+            let invoice = self.get_invoice(id).await?;
+            let tunniste = invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero);
+            if invoice.maksettu {
+                return Err(anyhow::anyhow!(
+                    "Maksettua laskua {} ei voi kirjata luottotappioksi.",
+                    tunniste
+                ));
+            }
+            if invoice.luottotappio_pvm.is_some() {
+                return Err(anyhow::anyhow!("Lasku {} on jo kirjattu luottotappioksi.", tunniste));
+            }
+            if invoice.summa <= 0.0 {
+                return Err(anyhow::anyhow!(
+                    "Hyvityslaskua {} ei voi kirjata luottotappioksi.",
+                    tunniste
+                ));
+            }
+            if write_off.paivamaara < invoice.luontipaiva {
+                return Err(anyhow::anyhow!(
+                    "Luottotappion päivä ei voi olla ennen laskun {} päiväystä.",
+                    tunniste
+                ));
+            }
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        for &id in &write_off.lasku_idt {
+            sqlx::query(
+                "UPDATE invoices SET luottotappio_pvm = ?, luottotappio_syy = ?, updated_at = CURRENT_TIMESTAMP
+                 WHERE id = ?",
+            )
+            .bind(write_off.paivamaara)
+            .bind(syy)
+            .bind(id)
+            .execute(&mut *tx)
+            .await?;
+
+            sqlx::query(
+                "INSERT INTO invoice_changes (lasku_id, rivi_id, muutos, vanha_arvo, uusi_arvo)
+                 VALUES (?, NULL, 'luottotappio', NULL, ?)",
+            )
+            .bind(id)
+            .bind(format!("{}: {}", write_off.paivamaara, syy))
+            .execute(&mut *tx)
+            .await?;
+
+            if write_off.erottamisehdotus {
+                sqlx::query(
+                    "UPDATE members SET erottaminen_esitetty = ?, updated_at = CURRENT_TIMESTAMP
+                     WHERE aktiivinen = 1 AND erottaminen_esitetty IS NULL AND id IN (
+                         SELECT jasen_id FROM invoice_lines WHERE lasku_id = ? AND jasen_id IS NOT NULL
+                         UNION
+                         SELECT jasen_id FROM invoices WHERE id = ? AND jasen_id IS NOT NULL
+                     )",
+                )
+                .bind(write_off.paivamaara)
+                .bind(id)
+                .bind(id)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        tx.commit().await?;
+
+        let mut invoices = Vec::new();
+        for &id in &write_off.lasku_idt {
+            invoices.push(self.get_invoice(id).await?);
+        }
+        Ok(invoices)
+    }
+
+    // Palauttaa laskun avoimeksi saatavaksi, esim. jos perintä onnistuukin
+    pub async fn cancel_invoice_write_off(&self, id: i64) -> Result<Invoice> {
+        let invoice = self.get_invoice(id).await?;
+        let tunniste = invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero);
+        let Some(pvm) = invoice.luottotappio_pvm else {
+            return Err(anyhow::anyhow!("Laskua {} ei ole kirjattu luottotappioksi.", tunniste));
+        };
+
+        let exported = sqlx::query(
+            "SELECT tositenumero FROM tilitin_exports WHERE lasku_id = ? AND tyyppi = 'luottotappio'",
+        )
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(row) = exported {
+            let numero: i64 = row.get("tositenumero");
+            return Err(anyhow::anyhow!(
+                "Laskun {} luottotappio on viety Tilitiniin tositteella {}. Korjaa kirjanpito ennen perumista.",
+                tunniste,
+                numero
+            ));
+        }
+
+        let mut transaction = self.pool.begin().await?;
+        sqlx::query(
+            "UPDATE invoices SET luottotappio_pvm = NULL, luottotappio_syy = NULL, updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+        Self::log_invoice_change(
+            &mut transaction,
+            id,
+            None,
+            "luottotappio_peruttu",
+            Some(format!("{}: {}", pvm, invoice.luottotappio_syy.unwrap_or_default())),
+            None,
+        )
+        .await?;
+        transaction.commit().await?;
+
+        self.get_invoice(id).await
+    }
+
+    // Poistaa jäsenen erottamisehdotuksen, kun asia on käsitelty
+    pub async fn clear_member_expulsion_review(&self, member_id: i64) -> Result<()> {
+        sqlx::query(
+            "UPDATE members SET erottaminen_esitetty = NULL, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        )
+        .bind(member_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    // Tilitiniin viedyn laskun tosite ei enää vastaisi laskua, jos laskua muutettaisiin
+    async fn ensure_invoice_not_exported(&self, invoice: &Invoice) -> Result<()> {
+        let exported = sqlx::query(
+            "SELECT tositenumero FROM tilitin_exports WHERE lasku_id = ? ORDER BY id LIMIT 1",
+        )
+        .bind(invoice.id)
+        .fetch_optional(&self.pool)
+        .await?;
+        if let Some(row) = exported {
+            let numero: i64 = row.get("tositenumero");
+            return Err(anyhow::anyhow!(
+                "Lasku {} on viety Tilitiniin tositteella {}. Korjaa kirjanpito ennen laskun muuttamista tai poistamista.",
+                invoice.laskunumero.as_deref().unwrap_or(&invoice.viitenumero),
+                numero
+            ));
+        }
+        Ok(())
+    }
+
+    pub async fn delete_invoice(&self, id: i64) -> Result<()> {
+        let invoice = self.get_invoice(id).await?;
+        self.ensure_invoice_not_exported(&invoice).await?;
+
+        // Poista ensin laskurivit (foreign key constraint)
+        sqlx::query("DELETE FROM invoice_lines WHERE lasku_id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        // Poista lasku
+        sqlx::query("DELETE FROM invoices WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn delete_membership_fee(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM membership_fees WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn update_address(
+        &self,
+        address_id: i64,
+        katuosoite: &str,
+        postinumero: &str,
+        postitoimipaikka: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE addresses SET katuosoite = ?, postinumero = ?, postitoimipaikka = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        )
+        .bind(katuosoite)
+        .bind(postinumero)
+        .bind(postitoimipaikka)
+        .bind(address_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn get_member_address_id(&self, member_id: i64) -> Result<i64> {
+        let row = sqlx::query("SELECT osoite_id FROM members WHERE id = ?")
+            .bind(member_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("osoite_id"))
+    }
+
+    pub async fn get_household_address_id(&self, household_id: i64) -> Result<i64> {
+        let row = sqlx::query("SELECT id FROM addresses WHERE talous_id = ?")
+            .bind(household_id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("id"))
+    }
+
+    pub async fn cleanup_empty_households(&self) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+        
+        // Find households with no members (and no invoices - invoices reference the household)
+        let empty_households = sqlx::query(
+            "SELECT DISTINCT h.id as household_id, a.id as address_id
+             FROM households h
+             LEFT JOIN addresses a ON a.talous_id = h.id
+             LEFT JOIN members m ON m.osoite_id = a.id
+             WHERE m.id IS NULL AND a.id IS NOT NULL
+             AND NOT EXISTS (SELECT 1 FROM invoices i WHERE i.talous_id = h.id)"
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+
+        for row in empty_households {
+            let household_id: i64 = row.get("household_id");
+            let address_id: i64 = row.get("address_id");
+            
+            // Delete the address first (due to foreign key constraints)
+            sqlx::query("DELETE FROM addresses WHERE id = ?")
+                .bind(address_id)
+                .execute(&mut *transaction)
+                .await?;
+            
+            // Delete the household
+            sqlx::query("DELETE FROM households WHERE id = ?")
+                .bind(household_id)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn update_household(
+        &self,
+        id: i64,
+        household: &CreateHousehold,
+    ) -> Result<Household> {
+        sqlx::query(
+            "UPDATE households SET 
+             talouden_nimi = ?, vastaanottaja = ?, laskutusosoite_sama = ?, laskutusosoite_id = ?, 
+             updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
+        )
+        .bind(&household.talouden_nimi)
+        .bind(&household.vastaanottaja)
+        .bind(household.laskutusosoite_sama)
+        .bind(household.laskutusosoite_id)
+        .bind(id)
+        .execute(&self.pool)
+        .await?;
+
+        // Fetch and return the updated household
+        let row = sqlx::query("SELECT * FROM households WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Household {
+            id: row.get("id"),
+            talouden_nimi: row.get("talouden_nimi"),
+            vastaanottaja: row.try_get("vastaanottaja").ok(),
+            laskutusosoite_sama: row.get("laskutusosoite_sama"),
+            laskutusosoite_id: row.get("laskutusosoite_id"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
+        })
+    }
+
+    pub async fn update_household_address(
+        &self,
+        household_id: i64,
+        katuosoite: &str,
+        postinumero: &str,
+        postitoimipaikka: &str,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE addresses SET 
+             katuosoite = ?, postinumero = ?, postitoimipaikka = ?, 
+             updated_at = CURRENT_TIMESTAMP 
+             WHERE talous_id = ?",
+        )
+        .bind(katuosoite)
+        .bind(postinumero)
+        .bind(postitoimipaikka)
+        .bind(household_id)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn delete_household(&self, id: i64) -> Result<()> {
+        let mut transaction = self.pool.begin().await?;
+
+        // Estä poisto jos taloudella on laskuja
+        let invoice_count = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE talous_id = ?")
+            .bind(id)
+            .fetch_one(&mut *transaction)
+            .await?
+            .get::<i64, _>("count");
+
+        if invoice_count > 0 {
+            transaction.rollback().await?;
+            return Err(anyhow::anyhow!(
+                "Taloutta ei voi poistaa, koska siihen liittyy laskuja. Poista ensin talouden laskut."
+            ));
+        }
+
+        // Estä poisto jos talouden jäsenillä on laskurivejä (esim. toisen talouden laskulla)
+        let line_count = sqlx::query(
+            "SELECT COUNT(*) as count FROM invoice_lines il
+             JOIN members m ON il.jasen_id = m.id
+             WHERE m.osoite_id IN (SELECT id FROM addresses WHERE talous_id = ?)",
+        )
+        .bind(id)
+        .fetch_one(&mut *transaction)
+        .await?
+        .get::<i64, _>("count");
+
+        if line_count > 0 {
+            transaction.rollback().await?;
+            return Err(anyhow::anyhow!(
+                "Taloutta ei voi poistaa, koska sen jäseniin liittyy laskurivejä. Poista ensin laskut."
+            ));
+        }
+
+        // First, delete all members in this household
+        sqlx::query(
+            "DELETE FROM members WHERE osoite_id IN (SELECT id FROM addresses WHERE talous_id = ?)",
+        )
+        .bind(id)
+        .execute(&mut *transaction)
+        .await?;
+
+        // Delete the address
+        sqlx::query("DELETE FROM addresses WHERE talous_id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
+        // Delete the household
+        sqlx::query("DELETE FROM households WHERE id = ?")
+            .bind(id)
+            .execute(&mut *transaction)
+            .await?;
+
+        transaction.commit().await?;
+        Ok(())
+    }
+
+    pub async fn get_household_member_count(&self, household_id: i64) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) as count FROM members m 
+             JOIN addresses a ON m.osoite_id = a.id 
+             WHERE a.talous_id = ?",
+        )
+        .bind(household_id)
+        .fetch_one(&self.pool)
+        .await?;
+        Ok(row.get("count"))
+    }
+
+    fn household_credit_from_row(row: &sqlx::sqlite::SqliteRow) -> HouseholdCredit {
+        HouseholdCredit {
+            id: row.get("id"),
+            talous_id: row.get("talous_id"),
+            paivamaara: row.get("paivamaara"),
+            summa: row
+                .try_get::<f64, _>("summa")
+                .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
+            selite: row.get("selite"),
+            lasku_id: row.get("lasku_id"),
+            rivi_id: row.get("rivi_id"),
+            created_at: row.get("created_at"),
+        }
+    }
+
+    async fn household_credit_balance(&self, talous_id: i64) -> Result<f64> {
+        let saldo: f64 = sqlx::query(
+            "SELECT CAST(COALESCE(SUM(summa), 0) AS REAL) as saldo FROM household_credits WHERE talous_id = ?",
+        )
+        .bind(talous_id)
+        .fetch_one(&self.pool)
+        .await?
+        .get("saldo");
+        Ok((saldo * 100.0).round() / 100.0)
+    }
+
+    pub async fn get_household_credits(&self, talous_id: i64) -> Result<Vec<HouseholdCredit>> {
+        let rows = sqlx::query(
+            "SELECT * FROM household_credits WHERE talous_id = ? ORDER BY paivamaara, id",
+        )
+        .bind(talous_id)
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows.iter().map(Self::household_credit_from_row).collect())
+    }
+
+    /// Kirjaa talouden ennakkomaksun tai liikasuorituksen. Saldo vähennetään
+    /// automaattisesti talouden seuraavilta laskuilta.
+    pub async fn add_household_credit(&self, credit: &CreateHouseholdCredit) -> Result<HouseholdCredit> {
+        let summa = (credit.summa * 100.0).round() / 100.0;
+        if summa <= 0.0 {
+            return Err(anyhow::anyhow!("Ennakkomaksun summan on oltava positiivinen."));
+        }
+        let selite = credit.selite.trim();
+        if selite.is_empty() {
+            return Err(anyhow::anyhow!("Ennakkomaksulle on annettava selite."));
+        }
+        let exists = sqlx::query("SELECT 1 FROM households WHERE id = ?")
+            .bind(credit.talous_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .is_some();
+        if !exists {
+            return Err(anyhow::anyhow!("Taloutta ei löytynyt."));
+        }
+
+        let id = sqlx::query(
+            "INSERT INTO household_credits (talous_id, paivamaara, summa, selite) VALUES (?, ?, ?, ?)",
+        )
+        .bind(credit.talous_id)
+        .bind(credit.paivamaara)
+        .bind(summa)
+        .bind(selite)
+        .execute(&self.pool)
+        .await?
+        .last_insert_rowid();
+
+        let row = sqlx::query("SELECT * FROM household_credits WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(Self::household_credit_from_row(&row))
+    }
+
+    /// Poistaa kirjatun ennakkomaksun. Laskulla käytettyä tai Tilitiniin vietyä
+    /// ennakkomaksua ei voi poistaa; käytetty hyvitys palautuu laskun poistolla.
+    pub async fn delete_household_credit(&self, id: i64) -> Result<()> {
+        let row = sqlx::query("SELECT * FROM household_credits WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Ennakkomaksua ei löytynyt."))?;
+        let credit = Self::household_credit_from_row(&row);
+
+        if credit.lasku_id.is_some() {
+            return Err(anyhow::anyhow!(
+                "Laskulla käytettyä hyvitystä ei voi poistaa. Poista tai peru lasku."
+            ));
+        }
+        let exported = sqlx::query("SELECT tositenumero FROM tilitin_exports WHERE ennakko_id = ?")
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if let Some(row) = exported {
+            return Err(anyhow::anyhow!(
+                "Ennakkomaksu on viety Tilitiniin tositteella {}, eikä sitä voi poistaa.",
+                row.get::<i64, _>("tositenumero")
+            ));
+        }
+        if self.household_credit_balance(credit.talous_id).await? - credit.summa < -0.005 {
             return Err(anyhow::anyhow!(
-                "Jäsenmaksut puuttuvat vuodelle {} jäsentyypeille: {}. Määrittele jäsenmaksut ennen laskujen luontia.",
-                year,
-                missing_fees.join(", ")
+                "Ennakkomaksua on jo käytetty laskuilla, eikä sitä voi poistaa."
             ));
         }
 
-        // Tarkista että on aktiivisia jäseniä
-        let active_members =
-            sqlx::query("SELECT COUNT(*) as count FROM members WHERE aktiivinen = 1")
-                .fetch_one(&self.pool)
-                .await?
-                .get::<i64, _>("count");
+        sqlx::query("DELETE FROM household_credits WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
 
-        if active_members == 0 {
-            return Err(anyhow::anyhow!("Ei aktiivisia jäseniä laskutettavaksi."));
-        }
+    /// Talouden tiliote: laskut, hyvityslaskut, maksut, luottotappiot sekä
+    /// ennakkomaksut ja niiden käyttö aikajärjestyksessä juoksevalla saldolla.
+    /// Positiivinen saldo on talouden velkaa, negatiivinen talouden hyvitystä.
+    pub async fn get_household_statement(&self, talous_id: i64) -> Result<HouseholdStatement> {
+        let household = sqlx::query("SELECT talouden_nimi, vastaanottaja FROM households WHERE id = ?")
+            .bind(talous_id)
+            .fetch_optional(&self.pool)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Taloutta ei löytynyt."))?;
 
-        // Laske montako laskua luotaisiin (vain ne joilla ei ole jo laskua)
-        let households_without_invoice = sqlx::query(
-            "SELECT COUNT(DISTINCT h.id) as count
-             FROM households h
-             JOIN addresses a ON h.id = a.talous_id
-             JOIN members m ON m.osoite_id = a.id
-             JOIN membership_fees mf ON m.jasentyyppi = mf.jasentyyppi AND mf.vuosi = ?
-             WHERE m.aktiivinen = 1
-             AND NOT EXISTS (
-                 SELECT 1 FROM invoices i 
-                 WHERE i.talous_id = h.id 
-                 AND strftime('%Y', i.luontipaiva) = CAST(? as TEXT)
-             )",
-        )
-        .bind(year)
-        .bind(year)
-        .fetch_one(&self.pool)
-        .await?
-        .get::<i64, _>("count");
+        let amount = |row: &sqlx::sqlite::SqliteRow| -> f64 {
+            row.try_get::<f64, _>("summa")
+                .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64)
+        };
 
-        // Tarkista onko jo kaikille luotu laskut
-        let existing_invoices = sqlx::query(
-            "SELECT COUNT(*) as count FROM invoices 
-             WHERE strftime('%Y', luontipaiva) = CAST(? as TEXT)",
+        // (päivä, järjestys, tapahtuma, summa): positiivinen summa on veloitus
+        let mut events: Vec<(chrono::NaiveDate, u8, StatementEntry, f64)> = Vec::new();
+        let event = |paivamaara, tyyppi: &str, selite: String, lasku_id, laskunumero| StatementEntry {
+            paivamaara,
+            tyyppi: tyyppi.to_string(),
+            selite,
+            lasku_id,
+            laskunumero,
+            veloitus: 0.0,
+            hyvitys: 0.0,
+            saldo: 0.0,
+        };
+
+        let invoices = sqlx::query(
+            "SELECT i.id, i.laskunumero, i.viitenumero, i.luontipaiva, i.summa, i.maksettu,
+                    i.maksupaiva, i.luottotappio_pvm,
+                    COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) as vuosi
+             FROM invoices i
+             LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+             WHERE i.talous_id = ? ORDER BY i.id",
         )
-        .bind(year)
-        .fetch_one(&self.pool)
-        .await?
-        .get::<i64, _>("count");
+        .bind(talous_id)
+        .fetch_all(&self.pool)
+        .await?;
 
-        if households_without_invoice == 0 {
-            if existing_invoices > 0 {
-                return Err(anyhow::anyhow!(
-                    "Laskut on jo luotu vuodelle {}. {} laskua olemassa.",
-                    year,
-                    existing_invoices
-                ));
-            } else {
-                return Err(anyhow::anyhow!(
-                    "Ei laskutettavia jäseniä vuodelle {}.",
-                    year
-                ));
+        let mut avoimet_laskut = 0.0;
+        for row in &invoices {
+            let lasku_id: i64 = row.get("id");
+            let laskunumero: Option<String> = row.get("laskunumero");
+            let tunniste = laskunumero.clone().unwrap_or_else(|| row.get("viitenumero"));
+            let summa = amount(row);
+            let luottotappio: Option<chrono::NaiveDate> = row.get("luottotappio_pvm");
+            let maksettu: bool = row.get("maksettu");
+
+            let tyyppi = if summa < 0.0 { "hyvitys" } else { "lasku" };
+            events.push((
+                row.get("luontipaiva"),
+                2,
+                event(
+                    row.get("luontipaiva"),
+                    tyyppi,
+                    format!(
+                        "{} {} ({})",
+                        if summa < 0.0 { "Hyvityslasku" } else { "Lasku" },
+                        tunniste,
+                        row.get::<i32, _>("vuosi")
+                    ),
+                    Some(lasku_id),
+                    laskunumero.clone(),
+                ),
+                summa,
+            ));
+
+            if summa != 0.0 {
+                if let (true, Some(maksupaiva)) = (maksettu, row.get::<Option<chrono::NaiveDate>, _>("maksupaiva")) {
+                    events.push((
+                        maksupaiva,
+                        3,
+                        event(maksupaiva, "maksu", format!("Maksu {}", tunniste), Some(lasku_id), laskunumero.clone()),
+                        -summa,
+                    ));
+                }
+                if let Some(pvm) = luottotappio {
+                    events.push((
+                        pvm,
+                        4,
+                        event(pvm, "luottotappio", format!("Luottotappio {}", tunniste), Some(lasku_id), laskunumero.clone()),
+                        -summa,
+                    ));
+                }
+            }
+            if !maksettu && luottotappio.is_none() {
+                avoimet_laskut += summa;
             }
         }
 
-        let message = if existing_invoices > 0 {
-            format!(
-                "Valmis luomaan {} uutta laskua vuodelle {}. {} laskua on jo olemassa. Luodaan laskut vain niille jäsenille, joilla ei ole vielä laskua.",
-                households_without_invoice, year, existing_invoices
-            )
-        } else {
-            format!(
-                "Valmis luomaan {} laskua {} aktiiviselle jäsenelle vuodelle {}.",
-                households_without_invoice, active_members, year
-            )
-        };
+        // Ennakkomaksut hyvitetään, laskulla käytetty ennakko veloitetaan, koska
+        // lasku näkyy tiliotteella ennakolla vähennettynä
+        for credit in self.get_household_credits(talous_id).await? {
+            let laskunumero = match credit.lasku_id {
+                Some(lasku_id) => sqlx::query("SELECT laskunumero FROM invoices WHERE id = ?")
+                    .bind(lasku_id)
+                    .fetch_one(&self.pool)
+                    .await?
+                    .get("laskunumero"),
+                None => None,
+            };
+            let (order, tyyppi) = if credit.summa > 0.0 {
+                (0, "ennakkomaksu")
+            } else {
+                (1, "ennakkomaksu_kaytetty")
+            };
+            events.push((
+                credit.paivamaara,
+                order,
+                event(credit.paivamaara, tyyppi, credit.selite.clone(), credit.lasku_id, laskunumero),
+                -credit.summa,
+            ));
+        }
 
-        Ok(message)
+        events.sort_by_key(|(date, order, entry, _)| (*date, *order, entry.lasku_id.unwrap_or(0)));
+
+        let mut saldo = 0.0;
+        let tapahtumat = events
+            .into_iter()
+            .map(|(_, _, mut entry, summa)| {
+                if summa > 0.0 {
+                    entry.veloitus = summa;
+                } else {
+                    entry.hyvitys = -summa;
+                }
+                saldo = ((saldo + summa) * 100.0_f64).round() / 100.0;
+                entry.saldo = saldo;
+                entry
+            })
+            .collect();
+
+        Ok(HouseholdStatement {
+            talous_id,
+            talouden_nimi: household.get("talouden_nimi"),
+            vastaanottaja: household.get("vastaanottaja"),
+            tapahtumat,
+            saldo,
+            avoimet_laskut: (avoimet_laskut * 100.0_f64).round() / 100.0,
+            ennakkomaksut: self.household_credit_balance(talous_id).await?,
+        })
     }
 
-    pub async fn update_member_types_by_age(&self, year: i32) -> Result<u32> {
-        // Hae yhdistyksen nuorisojäsenen ikäraja
-        let org = self.get_organization().await?;
-        if org.is_none() {
-            return Ok(0);
+    fn account_mapping_from_row(row: &sqlx::sqlite::SqliteRow) -> AccountMapping {
+        AccountMapping {
+            id: row.get("id"),
+            kohde: row.get("kohde"),
+            jasentyyppi: row.get("jasentyyppi"),
+            maksu_id: row.get("maksu_id"),
+            tili: row.get("tili"),
+            created_at: row.get("created_at"),
+            updated_at: row.get("updated_at"),
         }
-        let age_limit = org.unwrap().nuorisojasen_ikaraja;
-        
-        // Päivitä nuorisojäsenet varsinaisiksi jäseniksi jos ikä on ikärajan yli tai yhtä suuri
-        let updated_count = sqlx::query(
-            "UPDATE members 
-             SET jasentyyppi = 'varsinainen', updated_at = CURRENT_TIMESTAMP
-             WHERE jasentyyppi = 'nuorisojasen' 
-             AND aktiivinen = 1
-             AND syntymaaika IS NOT NULL
-             AND (? - strftime('%Y', syntymaaika)) >= ?",
-        )
-        .bind(year)
-        .bind(age_limit)
-        .execute(&self.pool)
-        .await?
-        .rows_affected();
-
-        Ok(updated_count as u32)
     }
 
-    pub async fn create_invoice_for_year(
-        &self,
-        year: i32,
-        due_date: Option<chrono::NaiveDate>,
-    ) -> Result<Vec<Invoice>> {
-        // Aja jäsentyyppien päivitys ensin
-        let _ = self.update_member_types_by_age(year).await?;
-        // Hae vain ne taloudet joilla ei ole vielä laskua tälle vuodelle
-        let households = sqlx::query(
-            "SELECT DISTINCT h.id as household_id, h.talouden_nimi, h.vastaanottaja
-             FROM households h
-             JOIN addresses a ON h.id = a.talous_id
-             JOIN members m ON m.osoite_id = a.id
-             WHERE m.aktiivinen = 1
-             AND NOT EXISTS (
-                 SELECT 1 FROM invoices i 
-                 WHERE i.talous_id = h.id 
-                 AND strftime('%Y', i.luontipaiva) = CAST(? as TEXT)
-             )",
+    pub async fn get_account_mappings(&self) -> Result<Vec<AccountMapping>> {
+        let rows = sqlx::query(
+            "SELECT * FROM account_mappings
+             ORDER BY CASE kohde
+                 WHEN 'saatavat' THEN 1 WHEN 'pankki' THEN 2 WHEN 'tulot' THEN 3
+                 WHEN 'luottotappiot' THEN 4 WHEN 'jasentyyppi' THEN 5 ELSE 6 END,
+             jasentyyppi, maksu_id",
         )
-        .bind(year)
         .fetch_all(&self.pool)
         .await?;
 
-        let mut created_invoices = Vec::new();
-        let current_date = chrono::Utc::now().date_naive();
-        let due_date = due_date.unwrap_or_else(|| current_date + chrono::Duration::days(30));
-
-        for household_row in households {
-            let household_id: i64 = household_row.get("household_id");
-
-            // Hae jäsenmaksut tälle vuodelle
-            let members = sqlx::query(
-                "SELECT m.id, m.etunimi, m.sukunimi, m.jasentyyppi, COALESCE(mf.summa, 0.0) as summa
-                 FROM members m
-                 JOIN addresses a ON m.osoite_id = a.id
-                 JOIN households h ON a.talous_id = h.id
-                 LEFT JOIN membership_fees mf ON m.jasentyyppi = mf.jasentyyppi AND mf.vuosi = ?
-                 WHERE h.id = ? AND m.aktiivinen = 1",
-            )
-            .bind(year)
-            .bind(household_id)
-            .fetch_all(&self.pool)
-            .await?;
+        Ok(rows.iter().map(Self::account_mapping_from_row).collect())
+    }
 
-            // Laske kokonaissumma
-            let total_sum: f64 = members
-                .iter()
-                .map(|row| {
-                    // Kokeile ensin f64, sitten i64 ja muunna
-                    match row.try_get::<f64, _>("summa") {
-                        Ok(val) => val,
-                        Err(_) => row.get::<i64, _>("summa") as f64,
-                    }
-                })
-                .sum();
+    // Tallentaa kohteen tilin, tai korvaa kohteen aiemman tilin
+    pub async fn set_account_mapping(&self, mapping: &CreateAccountMapping) -> Result<AccountMapping> {
+        let tili = mapping.tili.trim();
+        if tili.is_empty() {
+            return Err(anyhow::anyhow!("Tilinumero on pakollinen."));
+        }
 
-            if total_sum <= 0.0 {
-                continue; // Ei laskutettavaa
+        let jasentyyppi = match mapping.kohde {
+            AccountTarget::Jasentyyppi => match mapping.jasentyyppi.as_deref().map(str::trim) {
+                Some(t) if !t.is_empty() => Some(self.resolve_member_type(t).await?.nimi),
+                _ => return Err(anyhow::anyhow!("Jäsentyypin tilille on valittava jäsentyyppi.")),
+            },
+            _ => None,
+        };
+        let maksu_id = match mapping.kohde {
+            AccountTarget::Lisamaksu => {
+                let id = mapping
+                    .maksu_id
+                    .ok_or_else(|| anyhow::anyhow!("Lisämaksun tilille on valittava lisämaksu."))?;
+                sqlx::query("SELECT id FROM fee_items WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&self.pool)
+                    .await?
+                    .ok_or_else(|| anyhow::anyhow!("Lisämaksua ei löytynyt ID:llä {}", id))?;
+                Some(id)
             }
+            _ => None,
+        };
 
-            // Generoi viitenumero suomalaisella tarkistussummalla
-            let base_reference = format!("{}{:05}", year, household_id);
-            let check_digit = calculate_finnish_check_digit(&base_reference);
-            let reference_number = format!("{}{}", base_reference, check_digit);
-            let invoice_number = format!("{}-{:03}", year, household_id);
-
-            // Luo lasku
-            let invoice_id = sqlx::query(
-                "INSERT INTO invoices (talous_id, luontipaiva, erapaiva, summa, viitenumero, laskunumero, maksettu)
-                 VALUES (?, ?, ?, ?, ?, ?, 0)"
-            )
-            .bind(household_id)
-            .bind(current_date)
-            .bind(due_date)
-            .bind(total_sum)
-            .bind(&reference_number)
-            .bind(&invoice_number)
-            .execute(&self.pool)
-            .await?
-            .last_insert_rowid();
-
-            // Luo laskurivit jokaiselle jäsenelle
-            for member_row in &members {
-                let member_id: i64 = member_row.get("id");
-                let member_fee: f64 = match member_row.try_get::<f64, _>("summa") {
-                    Ok(val) => val,
-                    Err(_) => member_row.get::<i64, _>("summa") as f64,
-                };
-                let etunimi: String = member_row.get("etunimi");
-                let sukunimi: String = member_row.get("sukunimi");
-                let description = format!("Jäsenmaksu {} - {} {}", year, etunimi, sukunimi);
+        let existing = sqlx::query(
+            "SELECT id FROM account_mappings
+             WHERE kohde = ? AND IFNULL(jasentyyppi, '') = IFNULL(?, '') AND IFNULL(maksu_id, 0) = IFNULL(?, 0)",
+        )
+        .bind(mapping.kohde.to_string())
+        .bind(&jasentyyppi)
+        .bind(maksu_id)
+        .fetch_optional(&self.pool)
+        .await?;
 
+        let id = match existing {
+            Some(row) => {
+                let id: i64 = row.get("id");
                 sqlx::query(
-                    "INSERT INTO invoice_lines (lasku_id, jasen_id, kuvaus, summa)
-                     VALUES (?, ?, ?, ?)",
+                    "UPDATE account_mappings SET tili = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
                 )
-                .bind(invoice_id)
-                .bind(member_id)
-                .bind(&description)
-                .bind(member_fee)
+                .bind(tili)
+                .bind(id)
                 .execute(&self.pool)
                 .await?;
+                id
             }
+            None => sqlx::query(
+                "INSERT INTO account_mappings (kohde, jasentyyppi, maksu_id, tili) VALUES (?, ?, ?, ?)",
+            )
+            .bind(mapping.kohde.to_string())
+            .bind(&jasentyyppi)
+            .bind(maksu_id)
+            .bind(tili)
+            .execute(&self.pool)
+            .await?
+            .last_insert_rowid(),
+        };
 
-            // Luo Invoice-objekti palautusta varten
-            let invoice = Invoice {
-                id: invoice_id,
-                talous_id: household_id,
-                luontipaiva: current_date,
-                erapaiva: due_date,
-                summa: total_sum,
-                viitenumero: reference_number,
-                laskunumero: Some(invoice_number),
-                maksettu: false,
-                maksupaiva: None,
-                created_at: chrono::Utc::now(),
-                updated_at: chrono::Utc::now(),
-            };
+        let row = sqlx::query("SELECT * FROM account_mappings WHERE id = ?")
+            .bind(id)
+            .fetch_one(&self.pool)
+            .await?;
 
-            created_invoices.push(invoice);
-        }
+        Ok(Self::account_mapping_from_row(&row))
+    }
 
-        Ok(created_invoices)
+    pub async fn delete_account_mapping(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM account_mappings WHERE id = ?")
+            .bind(id)
+            .execute(&self.pool)
+            .await?;
+        Ok(())
     }
 
-    pub async fn mark_invoice_paid(
+    async fn get_accounts(&self) -> Result<journal::Accounts> {
+        let mappings = self.get_account_mappings().await?;
+        let base = |kohde: AccountTarget, nimi: &str| {
+            mappings
+                .iter()
+                .find(|m| m.kohde == kohde.to_string())
+                .map(|m| m.tili.clone())
+                .ok_or_else(|| anyhow::anyhow!("Kirjanpidon {} tiliä ei ole määritetty.", nimi))
+        };
+
+        Ok(journal::Accounts {
+            saatavat: base(AccountTarget::Saatavat, "myyntisaamisten")?,
+            pankki: base(AccountTarget::Pankki, "pankkitilin")?,
+            tulot: base(AccountTarget::Tulot, "jäsenmaksutuottojen")?,
+            luottotappiot: base(AccountTarget::Luottotappiot, "luottotappioiden").ok(),
+            jasentyypit: mappings
+                .iter()
+                .filter_map(|m| Some((m.jasentyyppi.clone()?, m.tili.clone())))
+                .collect(),
+            lisamaksut: mappings
+                .iter()
+                .filter_map(|m| Some((m.maksu_id?, m.tili.clone())))
+                .collect(),
+        })
+    }
+
+    /// Aikavälin kirjanpitotositteet: aikavälillä luodut laskut ja hyvitykset,
+    /// aikavälillä maksetut laskut, aikavälillä kirjatut luottotappiot sekä
+    /// talouksien ennakkomaksut.
+    pub async fn get_journal(
         &self,
-        id: i64,
-        payment_date: chrono::NaiveDate,
-    ) -> Result<Invoice> {
-        // Päivitä lasku maksetuksi
-        sqlx::query(
-            "UPDATE invoices SET maksettu = 1, maksupaiva = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+    ) -> Result<Journal> {
+        if start > end {
+            return Err(anyhow::anyhow!("Aikavälin alku on loppua myöhemmin."));
+        }
+        let accounts = self.get_accounts().await?;
+
+        let rows = sqlx::query(
+            "SELECT i.id, i.laskunumero, i.viitenumero, i.luontipaiva, i.summa,
+                    CASE WHEN i.maksettu = 1 THEN i.maksupaiva END as maksupaiva,
+                    i.luottotappio_pvm,
+                    COALESCE(c.nimi, TRIM(m.etunimi || ' ' || m.sukunimi), h.talouden_nimi, '') as vastaanottaja
+             FROM invoices i
+             LEFT JOIN customers c ON i.asiakas_id = c.id
+             LEFT JOIN members m ON i.jasen_id = m.id
+             LEFT JOIN households h ON i.talous_id = h.id
+             WHERE (i.luontipaiva BETWEEN ? AND ?)
+                OR (i.maksettu = 1 AND i.maksupaiva BETWEEN ? AND ?)
+                OR (i.luottotappio_pvm BETWEEN ? AND ?)
+             ORDER BY i.id",
         )
-        .bind(payment_date)
-        .bind(id)
-        .execute(&self.pool)
+        .bind(start)
+        .bind(end)
+        .bind(start)
+        .bind(end)
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
         .await?;
 
-        // Hae päivitetty lasku
-        let row = sqlx::query("SELECT * FROM invoices WHERE id = ?")
-            .bind(id)
-            .fetch_one(&self.pool)
+        let mut invoices = Vec::new();
+        for row in &rows {
+            let lasku_id: i64 = row.get("id");
+            let line_rows = sqlx::query(
+                "SELECT il.maksu_id, il.summa, m.jasentyyppi,
+                        EXISTS (SELECT 1 FROM household_credits hc WHERE hc.rivi_id = il.id) as ennakko
+                 FROM invoice_lines il
+                 LEFT JOIN members m ON il.jasen_id = m.id
+                 WHERE il.lasku_id = ?
+                 ORDER BY il.id",
+            )
+            .bind(lasku_id)
+            .fetch_all(&self.pool)
             .await?;
 
-        Ok(Invoice {
-            id: row.get("id"),
-            talous_id: row.get("talous_id"),
-            luontipaiva: row.get("luontipaiva"),
-            erapaiva: row.get("erapaiva"),
+            invoices.push(JournalInvoice {
+                lasku_id,
+                laskunumero: row.get("laskunumero"),
+                viitenumero: row.get("viitenumero"),
+                vastaanottaja: row.get("vastaanottaja"),
+                luontipaiva: row.get("luontipaiva"),
+                summa: row
+                    .try_get::<f64, _>("summa")
+                    .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
+                maksupaiva: row.get("maksupaiva"),
+                luottotappio_pvm: row.get("luottotappio_pvm"),
+                rivit: line_rows
+                    .iter()
+                    .map(|line| JournalLine {
+                        jasentyyppi: line.get("jasentyyppi"),
+                        maksu_id: line.get("maksu_id"),
+                        summa: line
+                            .try_get::<f64, _>("summa")
+                            .unwrap_or_else(|_| line.get::<i64, _>("summa") as f64),
+                        ennakko: line.get("ennakko"),
+                    })
+                    .collect(),
+            });
+        }
+
+        let credits = sqlx::query(
+            "SELECT hc.id, hc.paivamaara, hc.summa, hc.selite, h.talouden_nimi
+             FROM household_credits hc
+             JOIN households h ON hc.talous_id = h.id
+             WHERE hc.summa > 0 AND hc.paivamaara BETWEEN ? AND ?
+             ORDER BY hc.id",
+        )
+        .bind(start)
+        .bind(end)
+        .fetch_all(&self.pool)
+        .await?
+        .iter()
+        .map(|row| JournalCredit {
+            ennakko_id: row.get("id"),
+            paivamaara: row.get("paivamaara"),
             summa: row
                 .try_get::<f64, _>("summa")
                 .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
-            viitenumero: row.get("viitenumero"),
-            laskunumero: row.get("laskunumero"),
-            maksettu: row.get::<i64, _>("maksettu") != 0,
-            maksupaiva: row.get("maksupaiva"),
-            created_at: row.get("created_at"),
-            updated_at: row.get("updated_at"),
+            selite: row.get("selite"),
+            vastaanottaja: row.get("talouden_nimi"),
         })
+        .collect::<Vec<_>>();
+
+        let write_offs = invoices.iter().any(|i| {
+            i.luottotappio_pvm
+                .is_some_and(|pvm| pvm >= start && pvm <= end)
+        });
+        if write_offs && accounts.luottotappiot.is_none() {
+            return Err(anyhow::anyhow!("Kirjanpidon luottotappioiden tiliä ei ole määritetty."));
+        }
+
+        Ok(journal::build_journal(start, end, &accounts, &invoices, &credits))
     }
 
-    pub async fn delete_invoice(&self, id: i64) -> Result<()> {
-        // Poista ensin laskurivit (foreign key constraint)
-        sqlx::query("DELETE FROM invoice_lines WHERE lasku_id = ?")
-            .bind(id)
-            .execute(&self.pool)
-            .await?;
+    /// Kirjaa aikavälin tositteet Tilitin-tietokantaan. Samaan tiedostoon aiemmin
+    /// viedyt tositteet ohitetaan; toiseen tiedostoon ne viedään uudelleen.
+    /// Tiedosto tunnistetaan kanonisesta polusta, joten suhteellinen polku ja
+    /// symbolinen linkki viittaavat samaan tiedostoon. Tilit haetaan Tilitinistä
+    /// tilinumeron mukaan ja tositteet numeroidaan tilikauden seuraavista
+    /// numeroista. Kaikki tositteet tarkistetaan ennen kirjoittamista, joten virhe
+    /// ei jätä vientiä kesken.
+    pub async fn export_to_tilitin(
+        &self,
+        start: chrono::NaiveDate,
+        end: chrono::NaiveDate,
+        tilitin_path: &str,
+    ) -> Result<TilitinExport> {
+        let journal = self.get_journal(start, end).await?;
+        let tietokanta = tilitin::canonical_path(tilitin_path)?;
+
+        // Viety tosite tunnistetaan laskusta ja tyypistä tai ennakkomaksusta.
+        // Vanhoissa vienneissä polku on tallennettu sellaisenaan, joten myös ne
+        // verrataan kanonisessa muodossa.
+        let mut exported: std::collections::HashMap<(Option<i64>, Option<i64>, String), i64> =
+            std::collections::HashMap::new();
+        let mut exported_documents = std::collections::HashSet::new();
+        let rows = sqlx::query(
+            "SELECT lasku_id, ennakko_id, tyyppi, tietokanta, tositenumero, tilitin_tosite_id
+             FROM tilitin_exports",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for row in &rows {
+            let stored: String = row.get("tietokanta");
+            if stored != tietokanta
+                && tilitin::canonical_path(&stored).ok().as_deref() != Some(tietokanta.as_str())
+            {
+                continue;
+            }
+            exported.insert(
+                (
+                    row.get("lasku_id"),
+                    row.get("ennakko_id"),
+                    row.get("tyyppi"),
+                ),
+                row.get("tositenumero"),
+            );
+            exported_documents.insert(row.get::<i64, _>("tilitin_tosite_id"));
+        }
 
-        // Poista lasku
-        sqlx::query("DELETE FROM invoices WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
+        let mut conn = tilitin::open(tilitin_path).await?;
+        let accounts = tilitin::accounts(&mut conn).await?;
+        let periods = tilitin::periods(&mut conn).await?;
+
+        let summary = |voucher: &JournalVoucher, tositenumero: i64| TilitinVoucher {
+            lasku_id: voucher.lasku_id,
+            ennakko_id: voucher.ennakko_id,
+            tyyppi: voucher.tyyppi.clone(),
+            paivamaara: voucher.paivamaara,
+            selite: voucher.selite.clone(),
+            tositenumero,
+        };
+
+        let mut ohitetut = Vec::new();
+        let mut pending = Vec::new();
+        for voucher in &journal.tositteet {
+            let key = (voucher.lasku_id, voucher.ennakko_id, voucher.tyyppi.clone());
+            if let Some(&numero) = exported.get(&key) {
+                ohitetut.push(summary(voucher, numero));
+                continue;
+            }
+            let period = tilitin::period_for(&periods, voucher.paivamaara)?;
+            if let Some(entry) = voucher
+                .viennit
+                .iter()
+                .find(|e| !accounts.contains_key(&e.tili))
+            {
+                return Err(anyhow::anyhow!(
+                    "Tiliä {} ei löydy Tilitin-tietokannasta.",
+                    entry.tili
+                ));
+            }
+            pending.push((period.id, voucher));
+        }
+
+        let mut tilitin_tx = conn.begin().await?;
+        let mut tx = self.pool.begin().await?;
+        let mut viedyt = Vec::new();
+
+        for (period_id, voucher) in pending {
+            // Tilitinin tapahtuma vahvistetaan ennen sovelluksen tapahtumaa. Jos
+            // jälkimmäinen on epäonnistunut, tosite on jo Tilitinissä ilman
+            // vientimerkintää; se tunnistetaan ja merkitään viedyksi uudelleen
+            // kirjaamatta.
+            let existing =
+                tilitin::find_voucher(&mut tilitin_tx, period_id, voucher, &exported_documents)
+                    .await?;
+            let (document_id, numero) = match existing {
+                Some(found) => found,
+                None => {
+                    tilitin::insert_voucher(&mut tilitin_tx, period_id, voucher, &accounts).await?
+                }
+            };
+            exported_documents.insert(document_id);
+
+            sqlx::query(
+                "INSERT INTO tilitin_exports (lasku_id, ennakko_id, tyyppi, tietokanta, tositenumero, tilitin_tosite_id)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(voucher.lasku_id)
+            .bind(voucher.ennakko_id)
+            .bind(&voucher.tyyppi)
+            .bind(&tietokanta)
+            .bind(numero)
+            .bind(document_id)
+            .execute(&mut *tx)
             .await?;
 
-        Ok(())
+            viedyt.push(summary(voucher, numero));
+        }
+
+        tilitin_tx.commit().await?;
+        tx.commit().await?;
+
+        Ok(TilitinExport {
+            tietokanta,
+            viedyt,
+            ohitetut,
+        })
     }
 
-    pub async fn delete_membership_fee(&self, id: i64) -> Result<()> {
-        sqlx::query("DELETE FROM membership_fees WHERE id = ?")
-            .bind(id)
-            .execute(&self.pool)
+    // Tilastofunktiot Dashboard:ia varten
+    pub async fn get_total_members(&self) -> Result<i64> {
+        let row = sqlx::query("SELECT COUNT(*) as count FROM members WHERE aktiivinen = 1")
+            .fetch_one(&self.pool)
             .await?;
-        Ok(())
+        Ok(row.get("count"))
+    }
+
+    pub async fn get_open_invoices_count(&self) -> Result<i64> {
+        let row = sqlx::query(
+            "SELECT COUNT(*) as count FROM invoices WHERE maksettu = 0 AND luottotappio_pvm IS NULL",
+        )
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(row.get("count"))
+    }
+
+    pub async fn get_total_receivables(&self) -> Result<f64> {
+        // Luottotappioksi kirjatut laskut eivät ole enää saatavia
+        let row = sqlx::query(
+            "SELECT COALESCE(SUM(summa), 0) as total FROM invoices
+             WHERE maksettu = 0 AND luottotappio_pvm IS NULL",
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Handle both f64 and i64 types from SQLite
+        let total = row
+            .try_get::<f64, _>("total")
+            .unwrap_or_else(|_| row.get::<i64, _>("total") as f64);
+        Ok(total)
     }
 
-    pub async fn update_address(
-        &self,
-        address_id: i64,
-        katuosoite: &str,
-        postinumero: &str,
-        postitoimipaikka: &str,
-    ) -> Result<()> {
-        sqlx::query(
-            "UPDATE addresses SET katuosoite = ?, postinumero = ?, postitoimipaikka = ?, updated_at = CURRENT_TIMESTAMP WHERE id = ?"
+    /// Saatavien ikäjakauma annetun päivän tilanteen mukaan. Lasku on avoin,
+    /// jos se on luotu viimeistään tuona päivänä eikä sitä ollut silloin
+    /// maksettu tai kirjattu luottotappioksi. Laskutusvuosi on laskutuskauden
+    /// maksuvuosi tai kauden puuttuessa luontivuosi.
+    pub async fn get_receivables_aging(&self, paivamaara: chrono::NaiveDate) -> Result<AgingReport> {
+        let rows = sqlx::query(
+            "SELECT i.talous_id, i.asiakas_id, i.erapaiva, i.summa,
+                    COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) as vuosi,
+                    COALESCE(c.nimi, h.vastaanottaja, h.talouden_nimi, 'Talous ' || i.talous_id, '') as nimi
+             FROM invoices i
+             LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+             LEFT JOIN customers c ON i.asiakas_id = c.id
+             LEFT JOIN households h ON i.talous_id = h.id AND i.asiakas_id IS NULL
+             WHERE i.luontipaiva <= ?
+               AND (i.maksettu = 0 OR i.maksupaiva IS NULL OR i.maksupaiva > ?)
+               AND (i.luottotappio_pvm IS NULL OR i.luottotappio_pvm > ?)
+               AND i.summa <> 0
+             ORDER BY i.erapaiva, i.id",
         )
-        .bind(katuosoite)
-        .bind(postinumero)
-        .bind(postitoimipaikka)
-        .bind(address_id)
-        .execute(&self.pool)
+        .bind(paivamaara)
+        .bind(paivamaara)
+        .bind(paivamaara)
+        .fetch_all(&self.pool)
         .await?;
-        Ok(())
-    }
 
-    pub async fn get_member_address_id(&self, member_id: i64) -> Result<i64> {
-        let row = sqlx::query("SELECT osoite_id FROM members WHERE id = ?")
-            .bind(member_id)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(row.get("osoite_id"))
+        let invoices: Vec<AgingInvoice> = rows
+            .iter()
+            .map(|row| {
+                let asiakas_id: Option<i64> = row.get("asiakas_id");
+                AgingInvoice {
+                    // Asiakaslasku kohdistuu asiakkaaseen, vaikka sillä olisi talous
+                    talous_id: if asiakas_id.is_some() { None } else { row.get("talous_id") },
+                    asiakas_id,
+                    nimi: row.get("nimi"),
+                    vuosi: row.get("vuosi"),
+                    erapaiva: row.get("erapaiva"),
+                    summa: row
+                        .try_get::<f64, _>("summa")
+                        .unwrap_or_else(|_| row.get::<i64, _>("summa") as f64),
+                }
+            })
+            .collect();
+
+        Ok(reports::build_aging_report(paivamaara, &invoices))
     }
 
-    pub async fn get_household_address_id(&self, household_id: i64) -> Result<i64> {
-        let row = sqlx::query("SELECT id FROM addresses WHERE talous_id = ?")
-            .bind(household_id)
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(row.get("id"))
+    /// Jäsenten liittymis- ja eroamistiedot sekä hyväksytyt tyyppimuutokset
+    /// raportteja varten jäsenen id:n mukaan
+    async fn get_report_members(&self) -> Result<std::collections::HashMap<i64, ReportMember>> {
+        let mut changes: std::collections::HashMap<i64, Vec<(chrono::NaiveDate, String)>> =
+            std::collections::HashMap::new();
+        let change_rows = sqlx::query(
+            "SELECT jasen_id, vanha_tyyppi, date(COALESCE(kasitelty, created_at)) as paiva
+             FROM member_type_changes
+             WHERE tila = 'hyvaksytty'
+             ORDER BY paiva, id",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+        for row in &change_rows {
+            changes
+                .entry(row.get("jasen_id"))
+                .or_default()
+                .push((row.get("paiva"), row.get("vanha_tyyppi")));
+        }
+
+        let rows = sqlx::query(
+            "SELECT id, jasentyyppi, liittymispaiva, eroamispaiva, aktiivinen FROM members",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rows
+            .iter()
+            .map(|row| {
+                let id: i64 = row.get("id");
+                let member = ReportMember {
+                    jasentyyppi: row.get("jasentyyppi"),
+                    liittymispaiva: row.get("liittymispaiva"),
+                    eroamispaiva: row.get("eroamispaiva"),
+                    aktiivinen: row.get("aktiivinen"),
+                    tyyppimuutokset: changes.remove(&id).unwrap_or_default(),
+                };
+                (id, member)
+            })
+            .collect())
     }
 
-    pub async fn cleanup_empty_households(&self) -> Result<()> {
-        let mut transaction = self.pool.begin().await?;
-        
-        // Find households with no members (and no invoices - invoices reference the household)
-        let empty_households = sqlx::query(
-            "SELECT DISTINCT h.id as household_id, a.id as address_id
-             FROM households h
-             LEFT JOIN addresses a ON a.talous_id = h.id
-             LEFT JOIN members m ON m.osoite_id = a.id
-             WHERE m.id IS NULL AND a.id IS NOT NULL
-             AND NOT EXISTS (SELECT 1 FROM invoices i WHERE i.talous_id = h.id)"
+    /// Vuosiraportti: jäsenmäärät jäsentyypeittäin vuoden alussa ja lopussa,
+    /// liittyneet ja eronneet sekä vuoden laskutus ja sen maksukertymä.
+    /// Aiempien päivien jäsentyypit päätellään hyväksytyistä tyyppimuutoksista;
+    /// käsin tehtyjä tyyppimuutoksia ja poistettuja jäseniä ei ole historiassa.
+    pub async fn get_membership_report(&self, year: i32) -> Result<MembershipReport> {
+        let types: Vec<String> = self
+            .get_member_types()
+            .await?
+            .into_iter()
+            .map(|t| t.nimi)
+            .collect();
+
+        let members: Vec<ReportMember> = self.get_report_members().await?.into_values().collect();
+
+        let (jasentyypit, yhteensa) = reports::count_members(year, &types, &members);
+        let eronneet_tuntematon = reports::count_unknown_resignations(year, &members);
+
+        let row = sqlx::query(
+            "SELECT COUNT(*) as laskuja,
+                    CAST(COALESCE(SUM(i.summa), 0) AS REAL) as laskutettu,
+                    COALESCE(SUM(i.maksettu = 1), 0) as maksettuja,
+                    CAST(COALESCE(SUM(CASE WHEN i.maksettu = 1 THEN i.summa ELSE 0 END), 0) AS REAL) as maksettu,
+                    COALESCE(SUM(i.maksettu = 0 AND i.luottotappio_pvm IS NULL), 0) as avoimia,
+                    CAST(COALESCE(SUM(CASE WHEN i.maksettu = 0 AND i.luottotappio_pvm IS NULL
+                                      THEN i.summa ELSE 0 END), 0) AS REAL) as avoinna,
+                    COALESCE(SUM(i.luottotappio_pvm IS NOT NULL), 0) as luottotappioita,
+                    CAST(COALESCE(SUM(CASE WHEN i.luottotappio_pvm IS NOT NULL
+                                      THEN i.summa ELSE 0 END), 0) AS REAL) as luottotappiot
+             FROM invoices i
+             LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+             WHERE COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) = ?",
         )
-        .fetch_all(&mut *transaction)
+        .bind(year)
+        .fetch_one(&self.pool)
         .await?;
+        let amount = |column: &str| (row.get::<f64, _>(column) * 100.0).round() / 100.0;
+
+        Ok(MembershipReport {
+            vuosi: year,
+            jasentyypit,
+            yhteensa,
+            eronneet_tuntematon,
+            laskuja: row.get("laskuja"),
+            laskutettu: amount("laskutettu"),
+            maksettuja: row.get("maksettuja"),
+            maksettu: amount("maksettu"),
+            avoimia: row.get("avoimia"),
+            avoinna: amount("avoinna"),
+            luottotappioita: row.get("luottotappioita"),
+            luottotappiot: amount("luottotappiot"),
+        })
+    }
 
-        for row in empty_households {
-            let household_id: i64 = row.get("household_id");
-            let address_id: i64 = row.get("address_id");
-            
-            // Delete the address first (due to foreign key constraints)
-            sqlx::query("DELETE FROM addresses WHERE id = ?")
-                .bind(address_id)
-                .execute(&mut *transaction)
-                .await?;
-            
-            // Delete the household
-            sqlx::query("DELETE FROM households WHERE id = ?")
-                .bind(household_id)
-                .execute(&mut *transaction)
-                .await?;
+    /// Tilastot vuosiväliltä: jäsenmäärät jäsentyypeittäin, liittyneet ja
+    /// eronneet kuukausittain sekä laskutusvuosittain keräysaste, erääntynyt
+    /// summa ja keskimääräinen maksuaika. Hyvityslaskut vähentävät laskutettua,
+    /// mutta eivät kuulu erääntyneisiin eivätkä maksuaikaan.
+    pub async fn get_statistics(&self, alkuvuosi: i32, loppuvuosi: i32) -> Result<Statistics> {
+        if alkuvuosi > loppuvuosi {
+            return Err(anyhow::anyhow!("Alkuvuosi ei voi olla loppuvuoden jälkeen."));
         }
-        
-        transaction.commit().await?;
-        Ok(())
+        if loppuvuosi - alkuvuosi >= 50 {
+            return Err(anyhow::anyhow!("Vuosiväli voi olla enintään 50 vuotta."));
+        }
+
+        let types: Vec<String> = self
+            .get_member_types()
+            .await?
+            .into_iter()
+            .map(|t| t.nimi)
+            .collect();
+        let members: Vec<ReportMember> = self.get_report_members().await?.into_values().collect();
+
+        let today = chrono::Utc::now().date_naive();
+        let rows = sqlx::query(
+            "SELECT COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) as vuosi,
+                    COUNT(*) as laskuja,
+                    CAST(COALESCE(SUM(i.summa), 0) AS REAL) as laskutettu,
+                    CAST(COALESCE(SUM(CASE WHEN i.maksettu = 1 THEN i.summa ELSE 0 END), 0) AS REAL) as maksettu,
+                    COALESCE(SUM(i.maksettu = 0 AND i.luottotappio_pvm IS NULL AND i.summa > 0
+                                 AND i.erapaiva < ?), 0) as eraantyneita,
+                    CAST(COALESCE(SUM(CASE WHEN i.maksettu = 0 AND i.luottotappio_pvm IS NULL AND i.summa > 0
+                                           AND i.erapaiva < ? THEN i.summa ELSE 0 END), 0) AS REAL) as eraantynyt,
+                    COALESCE(SUM(i.maksettu = 1 AND i.summa > 0 AND i.maksupaiva IS NOT NULL), 0) as maksuja,
+                    COALESCE(SUM(CASE WHEN i.maksettu = 1 AND i.summa > 0 AND i.maksupaiva IS NOT NULL
+                                      THEN MAX(julianday(i.maksupaiva) - julianday(COALESCE(i.lahetetty, i.luontipaiva)), 0)
+                                      ELSE 0 END), 0) as maksupaivia
+             FROM invoices i
+             LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+             WHERE COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) BETWEEN ? AND ?
+             GROUP BY vuosi",
+        )
+        .bind(today)
+        .bind(today)
+        .bind(alkuvuosi)
+        .bind(loppuvuosi)
+        .fetch_all(&self.pool)
+        .await?;
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        let average = |total: f64, count: i64| (count > 0).then(|| round(total / count as f64));
+
+        let mut vuodet = Vec::new();
+        let (mut eraantynyt, mut eraantyneita) = (0.0, 0);
+        let (mut maksupaivia, mut maksuja) = (0.0, 0);
+        for vuosi in alkuvuosi..=loppuvuosi {
+            let (jasentyypit, yhteensa) = reports::count_members(vuosi, &types, &members);
+            let row = rows.iter().find(|r| r.get::<i32, _>("vuosi") == vuosi);
+            let get_f64 = |column: &str| row.map_or(0.0, |r| r.get::<f64, _>(column));
+            let get_i64 = |column: &str| row.map_or(0, |r| r.get::<i64, _>(column));
+
+            let laskutettu = round(get_f64("laskutettu"));
+            let maksettu = round(get_f64("maksettu"));
+            eraantynyt += get_f64("eraantynyt");
+            eraantyneita += get_i64("eraantyneita");
+            maksupaivia += get_f64("maksupaivia");
+            maksuja += get_i64("maksuja");
+
+            vuodet.push(YearStatistics {
+                vuosi,
+                jasentyypit,
+                yhteensa,
+                laskuja: get_i64("laskuja"),
+                laskutettu,
+                maksettu,
+                keraysaste: (laskutettu > 0.0).then(|| round(maksettu / laskutettu * 100.0)),
+                eraantynyt: round(get_f64("eraantynyt")),
+                keskim_maksuaika: average(get_f64("maksupaivia"), get_i64("maksuja")),
+            });
+        }
+
+        Ok(Statistics {
+            alkuvuosi,
+            loppuvuosi,
+            vuodet,
+            kuukaudet: reports::count_monthly(alkuvuosi, loppuvuosi, &members),
+            eraantynyt: round(eraantynyt),
+            eraantyneita,
+            keskim_maksuaika: average(maksupaivia, maksuja),
+        })
     }
 
-    pub async fn update_household(
-        &self,
-        id: i64,
-        household: &CreateHousehold,
-    ) -> Result<Household> {
-        sqlx::query(
-            "UPDATE households SET 
-             talouden_nimi = ?, vastaanottaja = ?, laskutusosoite_sama = ?, laskutusosoite_id = ?, 
-             updated_at = CURRENT_TIMESTAMP
-             WHERE id = ?",
+    /// Jäsenluettelo asetusten mukaan. Henkilötunnukset vain pyydettäessä.
+    pub async fn get_member_register(&self, options: &MemberRegisterOptions) -> Result<MemberRegister> {
+        let paivamaara = options
+            .paivamaara
+            .unwrap_or_else(|| chrono::Utc::now().date_naive());
+        let members = self.get_members().await?;
+        let history = self.get_report_members().await?;
+
+        Ok(reports::build_member_register(paivamaara, options, &members, &history))
+    }
+
+    /// Äänioikeusluettelo yhdistyksen kokousta varten: aktiiviset jäsenet ja
+    /// laskutusvuoden jäsenmaksun tila. Hyvityslaskuja ja nollarivejä ei lasketa.
+    /// Luottotappioksi kirjattu lasku on maksamatta.
+    pub async fn get_voting_list(&self, year: i32, vapautetut: bool) -> Result<VotingList> {
+        let rows = sqlx::query(
+            "SELECT m.id, m.etunimi, m.sukunimi, m.jasentyyppi,
+                    COALESCE(mt.laskutettava, 0) as laskutettava,
+                    COALESCE(SUM(l.maksettu = 1), 0) as maksettuja,
+                    COALESCE(SUM(l.maksettu = 0), 0) as maksamatta
+             FROM members m
+             LEFT JOIN member_types mt ON mt.nimi = m.jasentyyppi
+             LEFT JOIN (
+                 SELECT DISTINCT il.jasen_id, i.id, i.maksettu
+                 FROM invoice_lines il
+                 JOIN invoices i ON il.lasku_id = i.id
+                 LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+                 WHERE il.jasen_id IS NOT NULL AND il.summa <> 0 AND i.summa >= 0
+                   AND COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER)) = ?
+             ) l ON l.jasen_id = m.id
+             WHERE m.aktiivinen = 1
+             GROUP BY m.id
+             ORDER BY m.sukunimi, m.etunimi",
         )
-        .bind(&household.talouden_nimi)
-        .bind(&household.vastaanottaja)
-        .bind(household.laskutusosoite_sama)
-        .bind(household.laskutusosoite_id)
-        .bind(id)
-        .execute(&self.pool)
+        .bind(year)
+        .fetch_all(&self.pool)
         .await?;
 
-        // Fetch and return the updated household
-        let row = sqlx::query("SELECT * FROM households WHERE id = ?")
-            .bind(id)
-            .fetch_one(&self.pool)
-            .await?;
+        let members: Vec<VotingMember> = rows
+            .iter()
+            .map(|row| VotingMember {
+                jasen_id: row.get("id"),
+                nimi: fee_rules::full_name(
+                    &row.get::<String, _>("etunimi"),
+                    &row.get::<String, _>("sukunimi"),
+                ),
+                jasentyyppi: row.get("jasentyyppi"),
+                laskutettava: row.get("laskutettava"),
+                maksettuja: row.get("maksettuja"),
+                maksamatta: row.get("maksamatta"),
+            })
+            .collect();
 
-        Ok(Household {
+        Ok(reports::build_voting_list(year, &members, vapautetut))
+    }
+
+    fn report_definition_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<ReportDefinition> {
+        let kohde: String = row.get("kohde");
+        Ok(ReportDefinition {
             id: row.get("id"),
-            talouden_nimi: row.get("talouden_nimi"),
-            vastaanottaja: row.try_get("vastaanottaja").ok(),
-            laskutusosoite_sama: row.get("laskutusosoite_sama"),
-            laskutusosoite_id: row.get("laskutusosoite_id"),
+            nimi: row.get("nimi"),
+            kuvaus: row.get("kuvaus"),
+            kohde: kohde.parse().map_err(|e: String| anyhow::anyhow!(e))?,
+            sarakkeet: serde_json::from_str(&row.get::<String, _>("sarakkeet"))?,
+            suodattimet: serde_json::from_str(&row.get::<String, _>("suodattimet"))?,
+            jarjestys: serde_json::from_str(&row.get::<String, _>("jarjestys"))?,
             created_at: row.get("created_at"),
             updated_at: row.get("updated_at"),
         })
     }
 
-    pub async fn update_household_address(
-        &self,
-        household_id: i64,
-        katuosoite: &str,
-        postinumero: &str,
-        postitoimipaikka: &str,
-    ) -> Result<()> {
-        sqlx::query(
-            "UPDATE addresses SET 
-             katuosoite = ?, postinumero = ?, postitoimipaikka = ?, 
-             updated_at = CURRENT_TIMESTAMP 
-             WHERE talous_id = ?",
-        )
-        .bind(katuosoite)
-        .bind(postinumero)
-        .bind(postitoimipaikka)
-        .bind(household_id)
-        .execute(&self.pool)
-        .await?;
-        Ok(())
+    pub async fn get_report_definitions(&self) -> Result<Vec<ReportDefinition>> {
+        let rows = sqlx::query("SELECT * FROM report_definitions ORDER BY nimi")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(Self::report_definition_from_row).collect()
     }
 
-    pub async fn delete_household(&self, id: i64) -> Result<()> {
-        let mut transaction = self.pool.begin().await?;
-
-        // Estä poisto jos taloudella on laskuja
-        let invoice_count = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE talous_id = ?")
+    pub async fn get_report_definition(&self, id: i64) -> Result<ReportDefinition> {
+        let row = sqlx::query("SELECT * FROM report_definitions WHERE id = ?")
             .bind(id)
-            .fetch_one(&mut *transaction)
+            .fetch_optional(&self.pool)
             .await?
-            .get::<i64, _>("count");
+            .ok_or_else(|| anyhow::anyhow!("Raporttia ei löytynyt."))?;
+        Self::report_definition_from_row(&row)
+    }
 
-        if invoice_count > 0 {
-            transaction.rollback().await?;
-            return Err(anyhow::anyhow!(
-                "Taloutta ei voi poistaa, koska siihen liittyy laskuja. Poista ensin talouden laskut."
-            ));
+    async fn check_report_name(&self, nimi: &str, id: Option<i64>) -> Result<()> {
+        let existing = sqlx::query("SELECT id FROM report_definitions WHERE nimi = ? AND id IS NOT ?")
+            .bind(nimi.trim())
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+        if existing.is_some() {
+            return Err(anyhow::anyhow!("Samanniminen raportti on jo olemassa."));
         }
+        Ok(())
+    }
 
-        // Estä poisto jos talouden jäsenillä on laskurivejä (esim. toisen talouden laskulla)
-        let line_count = sqlx::query(
-            "SELECT COUNT(*) as count FROM invoice_lines il
-             JOIN members m ON il.jasen_id = m.id
-             WHERE m.osoite_id IN (SELECT id FROM addresses WHERE talous_id = ?)",
+    pub async fn create_report_definition(
+        &self,
+        definition: &CreateReportDefinition,
+    ) -> Result<ReportDefinition> {
+        report_builder::validate(definition)?;
+        self.check_report_name(&definition.nimi, None).await?;
+
+        let id = sqlx::query(
+            "INSERT INTO report_definitions (nimi, kuvaus, kohde, sarakkeet, suodattimet, jarjestys)
+             VALUES (?, ?, ?, ?, ?, ?)",
         )
-        .bind(id)
-        .fetch_one(&mut *transaction)
+        .bind(definition.nimi.trim())
+        .bind(&definition.kuvaus)
+        .bind(definition.kohde.to_string())
+        .bind(serde_json::to_string(&definition.sarakkeet)?)
+        .bind(serde_json::to_string(&definition.suodattimet)?)
+        .bind(serde_json::to_string(&definition.jarjestys)?)
+        .execute(&self.pool)
         .await?
-        .get::<i64, _>("count");
+        .last_insert_rowid();
 
-        if line_count > 0 {
-            transaction.rollback().await?;
-            return Err(anyhow::anyhow!(
-                "Taloutta ei voi poistaa, koska sen jäseniin liittyy laskurivejä. Poista ensin laskut."
-            ));
-        }
+        self.get_report_definition(id).await
+    }
+
+    pub async fn update_report_definition(
+        &self,
+        id: i64,
+        definition: &CreateReportDefinition,
+    ) -> Result<ReportDefinition> {
+        report_builder::validate(definition)?;
+        self.get_report_definition(id).await?;
+        self.check_report_name(&definition.nimi, Some(id)).await?;
 
-        // First, delete all members in this household
         sqlx::query(
-            "DELETE FROM members WHERE osoite_id IN (SELECT id FROM addresses WHERE talous_id = ?)",
+            "UPDATE report_definitions
+             SET nimi = ?, kuvaus = ?, kohde = ?, sarakkeet = ?, suodattimet = ?, jarjestys = ?,
+                 updated_at = CURRENT_TIMESTAMP
+             WHERE id = ?",
         )
+        .bind(definition.nimi.trim())
+        .bind(&definition.kuvaus)
+        .bind(definition.kohde.to_string())
+        .bind(serde_json::to_string(&definition.sarakkeet)?)
+        .bind(serde_json::to_string(&definition.suodattimet)?)
+        .bind(serde_json::to_string(&definition.jarjestys)?)
         .bind(id)
-        .execute(&mut *transaction)
+        .execute(&self.pool)
         .await?;
 
-        // Delete the address
-        sqlx::query("DELETE FROM addresses WHERE talous_id = ?")
-            .bind(id)
-            .execute(&mut *transaction)
-            .await?;
+        self.get_report_definition(id).await
+    }
 
-        // Delete the household
-        sqlx::query("DELETE FROM households WHERE id = ?")
+    pub async fn delete_report_definition(&self, id: i64) -> Result<()> {
+        sqlx::query("DELETE FROM report_definitions WHERE id = ?")
             .bind(id)
-            .execute(&mut *transaction)
+            .execute(&self.pool)
             .await?;
-
-        transaction.commit().await?;
         Ok(())
     }
 
-    pub async fn get_household_member_count(&self, household_id: i64) -> Result<i64> {
-        let row = sqlx::query(
-            "SELECT COUNT(*) as count FROM members m 
-             JOIN addresses a ON m.osoite_id = a.id 
-             WHERE a.talous_id = ?",
-        )
-        .bind(household_id)
-        .fetch_one(&self.pool)
-        .await?;
-        Ok(row.get("count"))
-    }
-
-    // Tilastofunktiot Dashboard:ia varten
-    pub async fn get_total_members(&self) -> Result<i64> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM members WHERE aktiivinen = 1")
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(row.get("count"))
-    }
+    /// Ajaa raportin määrittelyn. Kysely rakennetaan sallituista kentistä ja
+    /// suodattimien arvot sekä parametrit sidotaan kyselyyn.
+    pub async fn run_report(
+        &self,
+        nimi: &str,
+        definition: &CreateReportDefinition,
+        params: &std::collections::HashMap<String, String>,
+    ) -> Result<ReportResult> {
+        let query = report_builder::build_query(definition, params)?;
+
+        let mut sql = sqlx::query(&query.sql);
+        for value in &query.binds {
+            sql = match value {
+                BindValue::Teksti(text) => sql.bind(text),
+                BindValue::Numero(number) => sql.bind(number),
+                BindValue::Paivamaara(date) => sql.bind(date),
+                BindValue::Totuus(flag) => sql.bind(flag),
+            };
+        }
+        let rows = sql.fetch_all(&self.pool).await?;
 
-    pub async fn get_open_invoices_count(&self) -> Result<i64> {
-        let row = sqlx::query("SELECT COUNT(*) as count FROM invoices WHERE maksettu = 0")
-            .fetch_one(&self.pool)
-            .await?;
-        Ok(row.get("count"))
+        Ok(ReportResult {
+            nimi: nimi.to_string(),
+            sarakkeet: query
+                .columns
+                .iter()
+                .map(|field| ReportFieldInfo {
+                    kentta: field.kentta.to_string(),
+                    otsikko: field.otsikko.to_string(),
+                    tyyppi: field.tyyppi,
+                })
+                .collect(),
+            rivit: rows
+                .iter()
+                .map(|row| {
+                    query
+                        .columns
+                        .iter()
+                        .enumerate()
+                        .map(|(i, field)| report_builder::format_value(row, i, field))
+                        .collect()
+                })
+                .collect(),
+        })
     }
 
-    pub async fn get_total_receivables(&self) -> Result<f64> {
-        let row =
-            sqlx::query("SELECT COALESCE(SUM(summa), 0) as total FROM invoices WHERE maksettu = 0")
-                .fetch_one(&self.pool)
-                .await?;
-
-        // Handle both f64 and i64 types from SQLite
-        let total = row
-            .try_get::<f64, _>("total")
-            .unwrap_or_else(|_| row.get::<i64, _>("total") as f64);
-        Ok(total)
+    /// Ajaa tallennetun raportin annetuilla parametreilla.
+    pub async fn run_saved_report(
+        &self,
+        id: i64,
+        params: &std::collections::HashMap<String, String>,
+    ) -> Result<ReportResult> {
+        let saved = self.get_report_definition(id).await?;
+        let definition = CreateReportDefinition {
+            nimi: saved.nimi.clone(),
+            kuvaus: saved.kuvaus,
+            kohde: saved.kohde,
+            sarakkeet: saved.sarakkeet,
+            suodattimet: saved.suodattimet,
+            jarjestys: saved.jarjestys,
+        };
+        self.run_report(&saved.nimi, &definition, params).await
     }
 
     pub async fn get_yearly_income(&self, year: i32) -> Result<f64> {
@@ -0,0 +1,298 @@
+use crate::fee_rules::full_name;
+use crate::models::{
+    AgingBuckets, AgingReport, InvoicePreview, Journal, MemberRegister, RegisterGrouping,
+    ReportFieldType, ReportResult, VotingList, VotingStatus,
+};
+use crate::xlsx::{self, Cell};
+
+// CSV-tiedostot puolipisteellä ja desimaalipilkulla, jotta suomenkielinen
+// Excel avaa ne suoraan. BOM kertoo Excelille merkistöksi UTF-8:n.
+const CSV_SEPARATOR: char = ';';
+
+pub fn csv_field(value: &str) -> String {
+    if value.contains(CSV_SEPARATOR)
+        || value.contains('"')
+        || value.contains('\n')
+        || value.contains('\r')
+    {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+pub fn csv_row(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(&CSV_SEPARATOR.to_string())
+}
+
+pub fn to_csv(headers: &[&str], rows: &[Vec<String>]) -> String {
+    let mut out = String::from("\u{feff}");
+    out.push_str(&csv_row(
+        &headers.iter().map(|h| h.to_string()).collect::<Vec<_>>(),
+    ));
+    out.push_str("\r\n");
+    for row in rows {
+        out.push_str(&csv_row(row));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+pub fn format_amount(value: f64) -> String {
+    format!("{:.2}", value).replace('.', ",")
+}
+
+/// Laskutuksen esikatselu CSV:nä: laskurivit, ohitetut jäsenet ja
+/// jäsentyyppimuutokset omina osioinaan tyhjällä rivillä erotettuina.
+pub fn invoice_preview_csv(preview: &InvoicePreview) -> String {
+    let mut rows = Vec::new();
+    for invoice in &preview.laskut {
+        for line in &invoice.rivit {
+            rows.push(vec![
+                invoice.talous_id.to_string(),
+                invoice
+                    .vastaanottaja
+                    .clone()
+                    .or_else(|| invoice.talouden_nimi.clone())
+                    .unwrap_or_default(),
+                if invoice.lisalasku { "kyllä" } else { "ei" }.to_string(),
+                line.jasen.clone().unwrap_or_default(),
+                line.jasentyyppi.clone().unwrap_or_default(),
+                line.kuvaus.clone(),
+                format_amount(line.summa),
+            ]);
+        }
+    }
+    rows.push(vec![
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        String::new(),
+        format!("Yhteensä ({} laskua)", preview.laskut.len()),
+        format_amount(preview.yhteensa),
+    ]);
+
+    let mut out = to_csv(
+        &["Talous", "Vastaanottaja", "Lisälasku", "Jäsen", "Jäsentyyppi", "Kuvaus", "Summa"],
+        &rows,
+    );
+
+    let skipped: Vec<Vec<String>> = preview
+        .ohitetut
+        .iter()
+        .map(|m| {
+            vec![
+                m.talous_id.to_string(),
+                full_name(&m.etunimi, &m.sukunimi),
+                m.jasentyyppi.clone(),
+                m.syy.clone(),
+                m.selite.clone(),
+            ]
+        })
+        .collect();
+    out.push_str("\r\n");
+    out.push_str(
+        to_csv(&["Talous", "Ohitettu jäsen", "Jäsentyyppi", "Syy", "Selite"], &skipped)
+            .trim_start_matches('\u{feff}'),
+    );
+
+    let changes: Vec<Vec<String>> = preview
+        .tyyppimuutokset
+        .iter()
+        .map(|c| {
+            vec![
+                full_name(&c.etunimi, &c.sukunimi),
+                c.ika.to_string(),
+                c.vanha_tyyppi.clone(),
+                c.uusi_tyyppi.clone(),
+            ]
+        })
+        .collect();
+    out.push_str("\r\n");
+    out.push_str(
+        to_csv(&["Jäsen", "Ikä", "Nykyinen tyyppi", "Uusi tyyppi"], &changes)
+            .trim_start_matches('\u{feff}'),
+    );
+
+    out
+}
+
+/// Kirjanpitotositteet CSV:nä, yksi vienti riviä kohden. Tositteen tiedot
+/// toistuvat jokaisella viennillä, jotta tiedoston voi tuoda sellaisenaan.
+pub fn journal_csv(journal: &Journal) -> String {
+    let mut rows = Vec::new();
+    for voucher in &journal.tositteet {
+        for entry in &voucher.viennit {
+            rows.push(vec![
+                voucher.numero.to_string(),
+                voucher.paivamaara.format("%Y-%m-%d").to_string(),
+                voucher.tyyppi.clone(),
+                entry.tili.clone(),
+                if entry.debet != 0.0 { format_amount(entry.debet) } else { String::new() },
+                if entry.kredit != 0.0 { format_amount(entry.kredit) } else { String::new() },
+                voucher.selite.clone(),
+                voucher.laskunumero.clone().unwrap_or_default(),
+            ]);
+        }
+    }
+
+    to_csv(
+        &["Tosite", "Päivämäärä", "Tyyppi", "Tili", "Debet", "Kredit", "Selite", "Laskunumero"],
+        &rows,
+    )
+}
+
+fn aging_amounts(summat: &AgingBuckets) -> Vec<String> {
+    [
+        summat.eraantymaton,
+        summat.paivaa_1_30,
+        summat.paivaa_31_90,
+        summat.paivaa_91_365,
+        summat.yli_vuosi,
+        summat.yhteensa,
+    ]
+    .iter()
+    .map(|v| format_amount(*v))
+    .collect()
+}
+
+/// Saatavien ikäjakauma CSV:nä: maksajat ja yhteensä-rivi sekä
+/// laskutusvuosittaiset summat omana osionaan tyhjällä rivillä erotettuna.
+pub fn aging_report_csv(report: &AgingReport) -> String {
+    const BUCKETS: [&str; 6] = ["Erääntymätön", "1–30 pv", "31–90 pv", "91–365 pv", "Yli vuosi", "Yhteensä"];
+
+    let mut rows: Vec<Vec<String>> = report
+        .rivit
+        .iter()
+        .map(|row| {
+            let mut fields = vec![row.nimi.clone(), row.laskuja.to_string()];
+            fields.extend(aging_amounts(&row.summat));
+            fields
+        })
+        .collect();
+    let mut total = vec![
+        format!("Yhteensä {}", report.paivamaara.format("%d.%m.%Y")),
+        report.rivit.iter().map(|r| r.laskuja).sum::<i64>().to_string(),
+    ];
+    total.extend(aging_amounts(&report.yhteensa));
+    rows.push(total);
+
+    let mut headers = vec!["Maksaja", "Laskuja"];
+    headers.extend(BUCKETS);
+    let mut out = to_csv(&headers, &rows);
+
+    let years: Vec<Vec<String>> = report
+        .vuodet
+        .iter()
+        .map(|year| {
+            let mut fields = vec![year.vuosi.to_string(), year.laskuja.to_string()];
+            fields.extend(aging_amounts(&year.summat));
+            fields
+        })
+        .collect();
+    let mut headers = vec!["Laskutusvuosi", "Laskuja"];
+    headers.extend(BUCKETS);
+    out.push_str("\r\n");
+    out.push_str(to_csv(&headers, &years).trim_start_matches('\u{feff}'));
+
+    out
+}
+
+/// Jäsenluettelo CSV:nä. Ryhmä-sarake on mukana vain ryhmiteltäessä ja
+/// henkilötunnus vain, jos se on pyydetty luetteloon.
+pub fn member_register_csv(register: &MemberRegister) -> String {
+    let grouped = register.ryhmittely != RegisterGrouping::Ei;
+
+    let mut headers = Vec::new();
+    if grouped {
+        headers.push("Ryhmä");
+    }
+    headers.extend(["Nimi", "Kotikunta", "Jäsentyyppi", "Liittymispäivä"]);
+    if register.henkilotunnukset {
+        headers.push("Henkilötunnus");
+    }
+
+    let mut rows = Vec::new();
+    for group in &register.ryhmat {
+        for member in &group.jasenet {
+            let mut fields = Vec::new();
+            if grouped {
+                fields.push(group.nimi.clone());
+            }
+            fields.extend([
+                member.nimi.clone(),
+                member.kotikunta.clone(),
+                member.jasentyyppi.clone(),
+                member.liittymispaiva.format("%d.%m.%Y").to_string(),
+            ]);
+            if register.henkilotunnukset {
+                fields.push(member.henkilotunnus.clone().unwrap_or_default());
+            }
+            rows.push(fields);
+        }
+    }
+
+    to_csv(&headers, &rows)
+}
+
+/// Äänioikeusluettelo CSV:nä kaikista aktiivisista jäsenistä jäsenmaksun tilan kanssa
+pub fn voting_list_csv(list: &VotingList) -> String {
+    let headers = ["Nimi", "Jäsentyyppi", "Jäsenmaksu", "Äänioikeus"];
+    let rows: Vec<Vec<String>> = list
+        .jasenet
+        .iter()
+        .map(|j| {
+            let tila = match j.tila {
+                VotingStatus::Maksettu => "maksettu",
+                VotingStatus::Maksamatta => "maksamatta",
+                VotingStatus::EiLaskutettu => "ei laskutettu",
+                VotingStatus::Vapautettu => "vapautettu",
+            };
+            vec![
+                j.nimi.clone(),
+                j.jasentyyppi.clone(),
+                tila.to_string(),
+                if j.aanioikeus { "kyllä" } else { "ei" }.to_string(),
+            ]
+        })
+        .collect();
+
+    to_csv(&headers, &rows)
+}
+
+/// Tallennetun raportin tulos CSV:nä
+pub fn report_csv(result: &ReportResult) -> String {
+    let headers: Vec<&str> = result.sarakkeet.iter().map(|c| c.otsikko.as_str()).collect();
+    to_csv(&headers, &result.rivit)
+}
+
+/// Tallennetun raportin tulos XLSX-työkirjana. Numerokentät kirjoitetaan
+/// lukuina, muut tekstinä sellaisinaan.
+pub fn report_xlsx(result: &ReportResult) -> Vec<u8> {
+    let headers: Vec<String> = result.sarakkeet.iter().map(|c| c.otsikko.clone()).collect();
+    let rows: Vec<Vec<Cell>> = result
+        .rivit
+        .iter()
+        .map(|row| {
+            row.iter()
+                .zip(&result.sarakkeet)
+                .map(|(value, column)| {
+                    let number = (column.tyyppi == ReportFieldType::Numero)
+                        .then(|| value.replace(',', ".").parse::<f64>().ok())
+                        .flatten();
+                    match number {
+                        Some(n) => Cell::Number(n),
+                        None => Cell::Text(value.clone()),
+                    }
+                })
+                .collect()
+        })
+        .collect();
+
+    xlsx::workbook(&result.nimi, &headers, &rows)
+}
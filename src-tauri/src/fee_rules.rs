@@ -0,0 +1,865 @@
+use crate::models::{FeeItem, FeeItemBasis, FeeRule, FeeRuleType};
+use chrono::Datelike;
+
+// Talouden yksittäisen jäsenen jäsenmaksu ennen alennuksia
+#[derive(Debug, Clone)]
+pub struct MemberFee {
+    pub jasen_id: i64,
+    pub etunimi: String,
+    pub sukunimi: String,
+    pub summa: f64,
+}
+
+// Alennusrivi, joka lisätään laskulle negatiivisena rivinä
+#[derive(Debug, Clone)]
+pub struct Discount {
+    pub jasen_id: i64,
+    pub kuvaus: String,
+    pub summa: f64,
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Jäsenen nimi riveille ja raportteihin. Yhteisöjäsenellä etunimi on tyhjä.
+pub fn full_name(etunimi: &str, sukunimi: &str) -> String {
+    format!("{} {}", etunimi, sukunimi).trim().to_string()
+}
+
+/// Laskee talouden alennusrivit aktiivisten maksusääntöjen perusteella.
+///
+/// Jäsenet järjestetään maksun mukaan suurimmasta pienimpään, joten "täydet maksut"
+/// ja "n:s jäsen" kohdistuvat aina edullisimpiin jäseniin. Säännöt käsitellään
+/// annetussa järjestyksessä ja kukin sääntö laskee alennuksensa edellisten
+/// sääntöjen jälkeisistä summista. Jäsenet, joiden maksu on nolla, eivät kuulu
+/// laskettaviin jäseniin.
+pub fn evaluate_household(rules: &[FeeRule], members: &[MemberFee]) -> Vec<Discount> {
+    evaluate_rules(rules, members)
+        .into_iter()
+        .map(|(_, discount)| discount)
+        .collect()
+}
+
+// Alennukset sääntöineen, jotta alennuksen voi kohdistaa toiselle jäsenelle
+fn evaluate_rules<'a>(rules: &'a [FeeRule], members: &[MemberFee]) -> Vec<(&'a FeeRule, Discount)> {
+    let mut billable: Vec<(MemberFee, f64)> = members
+        .iter()
+        .filter(|m| m.summa > 0.0)
+        .map(|m| (m.clone(), m.summa))
+        .collect();
+    billable.sort_by(|a, b| {
+        b.0.summa
+            .partial_cmp(&a.0.summa)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
+    let mut discounts = Vec::new();
+
+    for rule in rules.iter().filter(|r| r.aktiivinen) {
+        let rule_type: FeeRuleType = match rule.saantotyyppi.parse() {
+            Ok(t) => t,
+            Err(_) => continue,
+        };
+
+        match rule_type {
+            FeeRuleType::Katto => {
+                let full_fees = rule.taydet_maksut.unwrap_or(0).max(0) as usize;
+                for (member, remaining) in billable.iter_mut().skip(full_fees) {
+                    if *remaining > 0.0 {
+                        discounts.push((rule, discount_for(rule, member, *remaining)));
+                        *remaining = 0.0;
+                    }
+                }
+            }
+            FeeRuleType::NnesJasen => {
+                let position = rule.jasen_nro.unwrap_or(1).max(1) as usize;
+                let percent = rule.alennus_prosentti.unwrap_or(0.0).clamp(0.0, 100.0);
+                for (member, remaining) in billable.iter_mut().skip(position - 1) {
+                    let amount = round_cents(*remaining * percent / 100.0);
+                    if amount > 0.0 {
+                        discounts.push((rule, discount_for(rule, member, amount)));
+                        *remaining = round_cents(*remaining - amount);
+                    }
+                }
+            }
+            FeeRuleType::Perhemaksu => {
+                let min_members = rule.vahimmaisjasenet.unwrap_or(2).max(1) as usize;
+                let family_fee = rule.summa.unwrap_or(0.0).max(0.0);
+                if billable.len() < min_members {
+                    continue;
+                }
+
+                let total: f64 = billable.iter().map(|(_, remaining)| *remaining).sum();
+                let mut excess = round_cents(total - family_fee);
+                if excess <= 0.0 {
+                    continue;
+                }
+
+                // Kohdistetaan alennus edullisimmista jäsenistä alkaen, jotta
+                // yksittäinen rivi ei mene negatiiviseksi
+                for (member, remaining) in billable.iter_mut().rev() {
+                    if excess <= 0.0 {
+                        break;
+                    }
+                    let amount = round_cents(remaining.min(excess));
+                    if amount > 0.0 {
+                        discounts.push((rule, discount_for(rule, member, amount)));
+                        *remaining = round_cents(*remaining - amount);
+                        excess = round_cents(excess - amount);
+                    }
+                }
+            }
+        }
+    }
+
+    discounts
+}
+
+fn discount_for(rule: &FeeRule, member: &MemberFee, amount: f64) -> Discount {
+    Discount {
+        jasen_id: member.jasen_id,
+        kuvaus: format!(
+            "{} - {}",
+            rule.nimi,
+            full_name(&member.etunimi, &member.sukunimi)
+        ),
+        summa: -round_cents(amount),
+    }
+}
+
+// Talouden aktiivinen jäsen laskutusta varten
+#[derive(Debug, Clone)]
+pub struct HouseholdMember {
+    pub jasen_id: i64,
+    pub etunimi: String,
+    pub sukunimi: String,
+    pub jasentyyppi: String,
+    pub laskutettava: bool,
+    pub liittymispaiva: chrono::NaiveDate,
+    pub jasenmaksu: f64,
+    // Jäsenen tilaamat valinnaiset lisämaksut
+    pub tilatut_maksut: Vec<i64>,
+    // Lisämaksut, jotka jäseneltä on jo laskutettu aiemmin
+    pub laskutetut_maksut: Vec<i64>,
+}
+
+// Laskulle tuleva rivi ennen tallennusta. Ennakkorivi käyttää talouden
+// ennakkomaksua laskun vähennykseksi.
+#[derive(Debug, Clone)]
+pub struct LineDraft {
+    pub jasen_id: Option<i64>,
+    pub maksu_id: Option<i64>,
+    pub kuvaus: String,
+    pub summa: f64,
+    pub ennakko: bool,
+}
+
+// Vuosilaskutuksessa luotava lasku ennen tallennusta. Lisälasku on jo
+// laskutetun talouden myöhemmin liittyneille jäsenille. Jäsenkohtaisella
+// laskulla `jasen_id` on laskun saaja, tai huoltajan maksaessa `huoltaja_id`.
+// Asiakkaalle tehdyn laskun saaja on `asiakas_id`, eikä taloutta välttämättä ole.
+#[derive(Debug, Clone)]
+pub struct InvoiceDraft {
+    pub talous_id: Option<i64>,
+    pub asiakas_id: Option<i64>,
+    pub jasen_id: Option<i64>,
+    pub huoltaja_id: Option<i64>,
+    pub kausi_id: Option<i64>,
+    pub lisalasku: bool,
+    pub viesti: Option<String>,
+    pub lines: Vec<LineDraft>,
+}
+
+impl InvoiceDraft {
+    pub fn total(&self) -> f64 {
+        round_cents(self.lines.iter().map(|l| l.summa).sum())
+    }
+}
+
+/// Muodostaa talouden kausilaskun rivit: jäsenmaksut laskutettaville jäsenille,
+/// maksusääntöjen alennukset ja lisämaksujen rivit perusteen mukaan. `year` on
+/// kauden maksuvuosi ja `period` rivien kuvauksiin tuleva kauden nimi.
+pub fn household_lines(
+    year: i32,
+    period: &str,
+    rules: &[FeeRule],
+    items: &[FeeItem],
+    members: &[HouseholdMember],
+) -> Vec<LineDraft> {
+    let billable: Vec<&HouseholdMember> = members.iter().filter(|m| m.laskutettava).collect();
+
+    let member_fees: Vec<MemberFee> = billable
+        .iter()
+        .map(|m| MemberFee {
+            jasen_id: m.jasen_id,
+            etunimi: m.etunimi.clone(),
+            sukunimi: m.sukunimi.clone(),
+            summa: m.jasenmaksu,
+        })
+        .collect();
+
+    let mut lines: Vec<LineDraft> = member_fees
+        .iter()
+        .map(|m| LineDraft {
+            jasen_id: Some(m.jasen_id),
+            maksu_id: None,
+            kuvaus: format!(
+                "Jäsenmaksu {} - {}",
+                period,
+                full_name(&m.etunimi, &m.sukunimi)
+            ),
+            summa: m.summa,
+            ennakko: false,
+        })
+        .collect();
+
+    // Talouskohtaiset perhealennukset omina riveinään
+    lines.extend(
+        evaluate_household(rules, &member_fees)
+            .into_iter()
+            .map(|d| LineDraft {
+                jasen_id: Some(d.jasen_id),
+                maksu_id: None,
+                kuvaus: d.kuvaus,
+                summa: d.summa,
+                ennakko: false,
+            }),
+    );
+
+    for item in items.iter().filter(|i| i.aktiivinen) {
+        let basis: FeeItemBasis = match item.peruste.parse() {
+            Ok(b) => b,
+            Err(_) => continue,
+        };
+        let matches_type = |m: &&HouseholdMember| {
+            item.jasentyyppi
+                .as_ref()
+                .is_none_or(|t| t == &m.jasentyyppi)
+        };
+
+        let charged: Vec<&HouseholdMember> = match basis {
+            FeeItemBasis::Jasen => billable.iter().copied().filter(matches_type).collect(),
+            FeeItemBasis::UusiJasen => billable
+                .iter()
+                .copied()
+                .filter(matches_type)
+                .filter(|m| {
+                    m.liittymispaiva.year() == year && !m.laskutetut_maksut.contains(&item.id)
+                })
+                .collect(),
+            FeeItemBasis::Valinnainen => members
+                .iter()
+                .filter(matches_type)
+                .filter(|m| m.tilatut_maksut.contains(&item.id))
+                .collect(),
+            FeeItemBasis::Talous => {
+                if billable.iter().any(matches_type) {
+                    lines.push(LineDraft {
+                        jasen_id: None,
+                        maksu_id: Some(item.id),
+                        kuvaus: format!("{} {}", item.nimi, period),
+                        summa: round_cents(item.summa),
+                        ennakko: false,
+                    });
+                }
+                continue;
+            }
+        };
+
+        for member in charged {
+            lines.push(LineDraft {
+                jasen_id: Some(member.jasen_id),
+                maksu_id: Some(item.id),
+                kuvaus: format!(
+                    "{} {} - {}",
+                    item.nimi,
+                    period,
+                    full_name(&member.etunimi, &member.sukunimi)
+                ),
+                summa: round_cents(item.summa),
+                ennakko: false,
+            });
+        }
+    }
+
+    lines
+}
+
+/// Laskun rivit vain osalle talouden jäsenistä, esim. lisälasku myöhemmin
+/// liittyneille tai valituille jäsenille. `invoiced` on kauden laskuilla jo
+/// olevat jäsenet ja heidän saamansa alennukset, tai `None`, jos talouden kausi
+/// on vielä laskuttamatta.
+///
+/// Maksusäännöt lasketaan jo laskutetuille ja valituille jäsenille yhdessä.
+/// Valittujen jäsenten omat alennukset tulevat heidän riveilleen. Jos
+/// laskutetulle jäsenelle kuuluisi nyt enemmän alennusta kuin hän on saanut,
+/// erotus kohdistetaan valittujen jäsenten maksuihin, jotta talous ei maksa
+/// esimerkiksi maksukaton yli. Jos laskutetut ovat saaneet enemmän kuin nyt
+/// kuuluisi, ylimäärä vähennetään uusista alennuksista. Talouskohtaiset
+/// lisämaksut otetaan mukaan vain, jos talouden kausi on vielä laskuttamatta.
+pub fn member_subset_lines(
+    year: i32,
+    period: &str,
+    rules: &[FeeRule],
+    items: &[FeeItem],
+    members: &[HouseholdMember],
+    selected: &[i64],
+    invoiced: Option<&[(i64, f64)]>,
+) -> Vec<LineDraft> {
+    let household_items = invoiced.is_none();
+    let invoiced = invoiced.unwrap_or_default();
+    let granted = |id: i64| invoiced.iter().find(|(j, _)| *j == id).map(|(_, a)| *a);
+
+    // Valitut jäsenet viimeiseksi: tasasuurilla maksuilla alennukset kohdistuvat niihin
+    let mut group: Vec<HouseholdMember> = members
+        .iter()
+        .filter(|m| selected.contains(&m.jasen_id) || granted(m.jasen_id).is_some())
+        .cloned()
+        .collect();
+    group.sort_by_key(|m| selected.contains(&m.jasen_id));
+
+    let mut lines: Vec<LineDraft> = household_lines(year, period, &[], items, &group)
+        .into_iter()
+        .filter(|l| match l.jasen_id {
+            Some(id) => selected.contains(&id),
+            None => household_items,
+        })
+        .collect();
+
+    let member_fees: Vec<MemberFee> = group
+        .iter()
+        .filter(|m| m.laskutettava)
+        .map(|m| MemberFee {
+            jasen_id: m.jasen_id,
+            etunimi: m.etunimi.clone(),
+            sukunimi: m.sukunimi.clone(),
+            summa: m.jasenmaksu,
+        })
+        .collect();
+
+    // Uudet alennukset: valittujen omat (jäsen tiedossa) ja laskutetuilta
+    // puuttuvat, jotka kohdistetaan valituille (jäsen None)
+    let mut pending: Vec<(&FeeRule, Option<i64>, f64)> = Vec::new();
+    let mut granted_left: Vec<(i64, f64)> = invoiced.to_vec();
+    for (rule, discount) in evaluate_rules(rules, &member_fees) {
+        let amount = -discount.summa;
+        if selected.contains(&discount.jasen_id) {
+            pending.push((rule, Some(discount.jasen_id), amount));
+        } else if let Some((_, left)) = granted_left
+            .iter_mut()
+            .find(|(id, _)| *id == discount.jasen_id)
+        {
+            let covered = left.min(amount);
+            *left = round_cents(*left - covered);
+            if amount > covered {
+                pending.push((rule, None, round_cents(amount - covered)));
+            }
+        }
+    }
+
+    // Aiemmin annettu ylimääräinen alennus vähennetään viimeisistä uusista alennuksista
+    let mut surplus = round_cents(granted_left.iter().map(|(_, left)| left).sum());
+    for (_, _, amount) in pending.iter_mut().rev() {
+        if surplus <= 0.0 {
+            break;
+        }
+        let reduced = amount.min(surplus);
+        *amount = round_cents(*amount - reduced);
+        surplus = round_cents(surplus - reduced);
+    }
+
+    // Valittujen jäsenten maksut, joista puuttuvat alennukset voi vähentää
+    let mut remaining: Vec<(&MemberFee, f64)> = member_fees
+        .iter()
+        .filter(|m| selected.contains(&m.jasen_id))
+        .map(|m| (m, m.summa))
+        .collect();
+    for (_, member, amount) in &pending {
+        if let Some((_, left)) = remaining
+            .iter_mut()
+            .find(|(m, _)| Some(m.jasen_id) == *member)
+        {
+            *left = round_cents(*left - amount);
+        }
+    }
+    remaining.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let mut discounts = Vec::new();
+    for (rule, member, amount) in pending {
+        if amount <= 0.0 {
+            continue;
+        }
+        match member {
+            Some(id) => {
+                if let Some(fee) = member_fees.iter().find(|m| m.jasen_id == id) {
+                    discounts.push(discount_for(rule, fee, amount));
+                }
+            }
+            None => {
+                let mut amount = amount;
+                for (fee, left) in remaining.iter_mut() {
+                    if amount <= 0.0 {
+                        break;
+                    }
+                    let part = round_cents(left.min(amount));
+                    if part > 0.0 {
+                        discounts.push(discount_for(rule, fee, part));
+                        *left = round_cents(*left - part);
+                        amount = round_cents(amount - part);
+                    }
+                }
+            }
+        }
+    }
+
+    // Alennukset jäsenmaksurivien perään kuten koko talouden laskulla
+    let fee_lines = lines
+        .iter()
+        .take_while(|l| l.maksu_id.is_none() && l.jasen_id.is_some())
+        .count();
+    lines.splice(
+        fee_lines..fee_lines,
+        discounts.into_iter().map(|d| LineDraft {
+            jasen_id: Some(d.jasen_id),
+            maksu_id: None,
+            kuvaus: d.kuvaus,
+            summa: d.summa,
+            ennakko: false,
+        }),
+    );
+
+    lines
+}
+
+/// Jakaa talouden laskun jäsenkohtaisiksi laskuiksi laskutustavan mukaan.
+/// Jäsenet, joille `separate` on tosi, saavat oman laskun; muiden jäsenten rivit
+/// jäävät talouden laskulle. Talouskohtaiset rivit jäävät talouden laskulle, tai
+/// ensimmäiselle jäsenlaskulle, jos talouden laskulle ei jää jäsenrivejä.
+pub fn split_by_member(draft: InvoiceDraft, separate: impl Fn(i64) -> bool) -> Vec<InvoiceDraft> {
+    let mut household = InvoiceDraft {
+        lines: Vec::new(),
+        ..draft.clone()
+    };
+    let mut household_only = Vec::new();
+    let mut member_drafts: Vec<InvoiceDraft> = Vec::new();
+
+    for line in draft.lines {
+        match line.jasen_id {
+            Some(id) if separate(id) => {
+                match member_drafts.iter_mut().find(|d| d.jasen_id == Some(id)) {
+                    Some(member_draft) => member_draft.lines.push(line),
+                    None => member_drafts.push(InvoiceDraft {
+                        talous_id: draft.talous_id,
+                        asiakas_id: draft.asiakas_id,
+                        jasen_id: Some(id),
+                        huoltaja_id: None,
+                        kausi_id: draft.kausi_id,
+                        // Jäsenen ensimmäinen oma lasku kaudella ei ole lisälasku
+                        lisalasku: false,
+                        viesti: None,
+                        lines: vec![line],
+                    }),
+                }
+            }
+            Some(_) => household.lines.push(line),
+            None => household_only.push(line),
+        }
+    }
+
+    if household.lines.is_empty() && !member_drafts.is_empty() {
+        member_drafts[0].lines.extend(household_only);
+    } else {
+        household.lines.extend(household_only);
+    }
+
+    let mut drafts = Vec::new();
+    if !household.lines.is_empty() {
+        drafts.push(household);
+    }
+    drafts.extend(member_drafts);
+    drafts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Utc};
+
+    fn rule(saantotyyppi: FeeRuleType) -> FeeRule {
+        FeeRule {
+            id: 1,
+            nimi: "Perhealennus".to_string(),
+            saantotyyppi: saantotyyppi.to_string(),
+            vuosi: None,
+            taydet_maksut: None,
+            jasen_nro: None,
+            alennus_prosentti: None,
+            summa: None,
+            vahimmaisjasenet: None,
+            aktiivinen: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn member_fee(jasen_id: i64, summa: f64) -> MemberFee {
+        MemberFee {
+            jasen_id,
+            etunimi: format!("Jäsen{}", jasen_id),
+            sukunimi: "Virtanen".to_string(),
+            summa,
+        }
+    }
+
+    fn household_member(jasen_id: i64, jasenmaksu: f64) -> HouseholdMember {
+        HouseholdMember {
+            jasen_id,
+            etunimi: format!("Jäsen{}", jasen_id),
+            sukunimi: "Virtanen".to_string(),
+            jasentyyppi: "varsinainen".to_string(),
+            laskutettava: true,
+            liittymispaiva: NaiveDate::from_ymd_opt(2020, 1, 1).unwrap(),
+            jasenmaksu,
+            tilatut_maksut: Vec::new(),
+            laskutetut_maksut: Vec::new(),
+        }
+    }
+
+    fn household_item(summa: f64) -> FeeItem {
+        FeeItem {
+            id: 9,
+            nimi: "Jäsenlehti".to_string(),
+            summa,
+            peruste: FeeItemBasis::Talous.to_string(),
+            jasentyyppi: None,
+            vuosi: None,
+            aktiivinen: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn discounts_by_member(discounts: &[Discount]) -> Vec<(i64, f64)> {
+        discounts.iter().map(|d| (d.jasen_id, d.summa)).collect()
+    }
+
+    fn draft(lines: Vec<LineDraft>) -> InvoiceDraft {
+        InvoiceDraft {
+            talous_id: Some(1),
+            asiakas_id: None,
+            jasen_id: None,
+            huoltaja_id: None,
+            kausi_id: Some(1),
+            lisalasku: false,
+            viesti: None,
+            lines,
+        }
+    }
+
+    #[test]
+    fn cap_discounts_fees_after_full_fees() {
+        let katto = FeeRule {
+            taydet_maksut: Some(2),
+            ..rule(FeeRuleType::Katto)
+        };
+        let members = [
+            member_fee(1, 20.0),
+            member_fee(2, 40.0),
+            member_fee(3, 10.0),
+            member_fee(4, 30.0),
+        ];
+
+        let discounts = evaluate_household(&[katto], &members);
+
+        // Täydet maksut kahdelta kalleimmalta, muut maksuttomia
+        assert_eq!(
+            discounts_by_member(&discounts),
+            vec![(1, -20.0), (3, -10.0)]
+        );
+    }
+
+    #[test]
+    fn nth_member_discount_applies_from_position() {
+        let nnes = FeeRule {
+            jasen_nro: Some(3),
+            alennus_prosentti: Some(50.0),
+            ..rule(FeeRuleType::NnesJasen)
+        };
+        let members = [
+            member_fee(1, 40.0),
+            member_fee(2, 30.0),
+            member_fee(3, 20.0),
+            member_fee(4, 10.0),
+        ];
+
+        let discounts = evaluate_household(&[nnes], &members);
+
+        assert_eq!(discounts_by_member(&discounts), vec![(3, -10.0), (4, -5.0)]);
+    }
+
+    #[test]
+    fn family_fee_needs_minimum_members() {
+        let perhemaksu = FeeRule {
+            summa: Some(80.0),
+            vahimmaisjasenet: Some(3),
+            ..rule(FeeRuleType::Perhemaksu)
+        };
+
+        let two = [member_fee(1, 40.0), member_fee(2, 30.0)];
+        assert!(evaluate_household(std::slice::from_ref(&perhemaksu), &two).is_empty());
+
+        let three = [
+            member_fee(1, 40.0),
+            member_fee(2, 30.0),
+            member_fee(3, 20.0),
+        ];
+        let discounts = evaluate_household(&[perhemaksu], &three);
+        assert_eq!(discounts_by_member(&discounts), vec![(3, -10.0)]);
+    }
+
+    #[test]
+    fn family_fee_does_not_make_lines_negative() {
+        let perhemaksu = FeeRule {
+            summa: Some(45.0),
+            vahimmaisjasenet: Some(2),
+            ..rule(FeeRuleType::Perhemaksu)
+        };
+        let members = [
+            member_fee(1, 40.0),
+            member_fee(2, 20.0),
+            member_fee(3, 10.0),
+        ];
+
+        let discounts = evaluate_household(&[perhemaksu], &members);
+
+        assert_eq!(
+            discounts_by_member(&discounts),
+            vec![(3, -10.0), (2, -15.0)]
+        );
+    }
+
+    #[test]
+    fn combined_rules_use_remaining_amounts() {
+        let nnes = FeeRule {
+            jasen_nro: Some(2),
+            alennus_prosentti: Some(50.0),
+            ..rule(FeeRuleType::NnesJasen)
+        };
+        let katto = FeeRule {
+            taydet_maksut: Some(2),
+            ..rule(FeeRuleType::Katto)
+        };
+        let members = [
+            member_fee(1, 40.0),
+            member_fee(2, 40.0),
+            member_fee(3, 40.0),
+        ];
+
+        let discounts = evaluate_household(&[nnes, katto], &members);
+
+        assert_eq!(
+            discounts_by_member(&discounts),
+            vec![(2, -20.0), (3, -20.0), (3, -20.0)]
+        );
+        let total: f64 = members.iter().map(|m| m.summa).sum::<f64>()
+            + discounts.iter().map(|d| d.summa).sum::<f64>();
+        assert_eq!(round_cents(total), 60.0);
+    }
+
+    #[test]
+    fn inactive_rules_and_free_members_are_skipped() {
+        let katto = FeeRule {
+            taydet_maksut: Some(1),
+            aktiivinen: false,
+            ..rule(FeeRuleType::Katto)
+        };
+        let members = [member_fee(1, 40.0), member_fee(2, 30.0)];
+        assert!(evaluate_household(std::slice::from_ref(&katto), &members).is_empty());
+
+        let katto = FeeRule {
+            aktiivinen: true,
+            ..katto
+        };
+        let members = [member_fee(1, 0.0), member_fee(2, 30.0)];
+        assert!(evaluate_household(&[katto], &members).is_empty());
+    }
+
+    #[test]
+    fn split_by_member_keeps_household_total() {
+        let perhemaksu = FeeRule {
+            summa: Some(50.0),
+            vahimmaisjasenet: Some(2),
+            ..rule(FeeRuleType::Perhemaksu)
+        };
+        let members = [
+            household_member(1, 33.33),
+            household_member(2, 33.33),
+            household_member(3, 33.33),
+        ];
+        let lines = household_lines(
+            2025,
+            "2025",
+            &[perhemaksu],
+            &[household_item(5.0)],
+            &members,
+        );
+        let household = draft(lines);
+        assert_eq!(household.total(), 55.0);
+
+        let drafts = split_by_member(household.clone(), |id| id != 1);
+
+        assert_eq!(drafts.len(), 3);
+        let split_total: f64 = drafts.iter().map(|d| d.total()).sum();
+        assert_eq!(round_cents(split_total), household.total());
+        assert_eq!(drafts[0].jasen_id, None);
+        assert!(drafts[0].lines.iter().any(|l| l.maksu_id == Some(9)));
+        assert!(drafts.iter().all(|d| d.total() >= 0.0));
+    }
+
+    #[test]
+    fn split_by_member_moves_household_lines_to_first_member() {
+        let members = [household_member(1, 25.0), household_member(2, 15.0)];
+        let lines = household_lines(2025, "2025", &[], &[household_item(5.0)], &members);
+
+        let drafts = split_by_member(draft(lines), |_| true);
+
+        assert_eq!(drafts.len(), 2);
+        assert_eq!(drafts[0].jasen_id, Some(1));
+        assert_eq!(drafts[0].total(), 30.0);
+        assert_eq!(drafts[1].total(), 15.0);
+    }
+
+    #[test]
+    fn member_subset_lines_order_is_stable() {
+        let katto = FeeRule {
+            taydet_maksut: Some(1),
+            ..rule(FeeRuleType::Katto)
+        };
+        let members = [
+            household_member(1, 30.0),
+            household_member(2, 30.0),
+            household_member(3, 30.0),
+        ];
+
+        let lines = member_subset_lines(
+            2025,
+            "2025",
+            std::slice::from_ref(&katto),
+            &[household_item(5.0)],
+            &members,
+            &[1, 3],
+            Some(&[(2, 0.0)]),
+        );
+
+        // Valitut jäsenet säilyttävät keskinäisen järjestyksensä, ja alennus
+        // kohdistuu niihin, koska valitsematon jäsen maksaa täyden maksun
+        let order: Vec<(Option<i64>, f64)> = lines.iter().map(|l| (l.jasen_id, l.summa)).collect();
+        assert_eq!(
+            order,
+            vec![
+                (Some(1), 30.0),
+                (Some(3), 30.0),
+                (Some(1), -30.0),
+                (Some(3), -30.0)
+            ]
+        );
+
+        let again = member_subset_lines(
+            2025,
+            "2025",
+            &[katto],
+            &[household_item(5.0)],
+            &members,
+            &[1, 3],
+            None,
+        );
+        // Laskuttamattoman talouden valitsematon jäsen ei vaikuta alennuksiin, ja
+        // talouskohtainen lisämaksu tulee jäsenrivien perään
+        let order: Vec<(Option<i64>, f64)> = again.iter().map(|l| (l.jasen_id, l.summa)).collect();
+        assert_eq!(
+            order,
+            vec![
+                (Some(1), 30.0),
+                (Some(3), 30.0),
+                (Some(3), -30.0),
+                (None, 5.0)
+            ]
+        );
+        assert_eq!(again.last().unwrap().maksu_id, Some(9));
+    }
+
+    #[test]
+    fn member_subset_lines_moves_cap_discount_to_late_joiner() {
+        let katto = FeeRule {
+            taydet_maksut: Some(2),
+            ..rule(FeeRuleType::Katto)
+        };
+        // Jäsenet 1 ja 2 on jo laskutettu täysin maksuin; myöhemmin liittyneen
+        // jäsenen 3 maksu on suurin, joten katon alennus osuisi jäseneen 2
+        let members = [
+            household_member(1, 30.0),
+            household_member(2, 30.0),
+            household_member(3, 50.0),
+        ];
+
+        let lines = member_subset_lines(
+            2025,
+            "2025",
+            &[katto],
+            &[],
+            &members,
+            &[3],
+            Some(&[(1, 0.0), (2, 0.0)]),
+        );
+
+        let amounts: Vec<(Option<i64>, f64)> =
+            lines.iter().map(|l| (l.jasen_id, l.summa)).collect();
+        assert_eq!(amounts, vec![(Some(3), 50.0), (Some(3), -30.0)]);
+        assert!(lines[1].kuvaus.starts_with("Perhealennus - "));
+        // Talous maksaa yhteensä kaksi suurinta maksua
+        let total: f64 = 60.0 + lines.iter().map(|l| l.summa).sum::<f64>();
+        assert_eq!(round_cents(total), 80.0);
+    }
+
+    #[test]
+    fn member_subset_lines_counts_discounts_already_given() {
+        let katto = FeeRule {
+            taydet_maksut: Some(1),
+            ..rule(FeeRuleType::Katto)
+        };
+        let members = [
+            household_member(1, 40.0),
+            household_member(2, 30.0),
+            household_member(3, 20.0),
+        ];
+
+        // Jäsen 2 sai jo alennuksen, jäsenelle 3 kuuluu oma alennuksensa
+        let lines = member_subset_lines(
+            2025,
+            "2025",
+            std::slice::from_ref(&katto),
+            &[],
+            &members,
+            &[3],
+            Some(&[(1, 0.0), (2, 30.0)]),
+        );
+        let amounts: Vec<(Option<i64>, f64)> =
+            lines.iter().map(|l| (l.jasen_id, l.summa)).collect();
+        assert_eq!(amounts, vec![(Some(3), 20.0), (Some(3), -20.0)]);
+
+        // Aiemmin annettu ylimääräinen alennus vähentää uutta alennusta
+        let lines = member_subset_lines(
+            2025,
+            "2025",
+            &[katto],
+            &[],
+            &members,
+            &[3],
+            Some(&[(1, 10.0), (2, 30.0)]),
+        );
+        let amounts: Vec<(Option<i64>, f64)> =
+            lines.iter().map(|l| (l.jasen_id, l.summa)).collect();
+        assert_eq!(amounts, vec![(Some(3), 20.0), (Some(3), -10.0)]);
+    }
+}
@@ -0,0 +1,479 @@
+use crate::models::{Journal, JournalEntry, JournalVoucher};
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+// Kirjanpidon tilit. Jäsentyypin tai lisämaksun tilin puuttuessa rivi
+// kirjataan oletustulotilille.
+#[derive(Debug, Clone)]
+pub struct Accounts {
+    pub saatavat: String,
+    pub pankki: String,
+    pub tulot: String,
+    pub luottotappiot: Option<String>,
+    pub jasentyypit: HashMap<String, String>,
+    pub lisamaksut: HashMap<i64, String>,
+}
+
+impl Accounts {
+    /// Rivin tulotili: lisämaksun tili, muuten jäsenen jäsentyypin tili,
+    /// muuten oletustulotili.
+    pub fn income_account(&self, jasentyyppi: Option<&str>, maksu_id: Option<i64>) -> &str {
+        if let Some(tili) = maksu_id.and_then(|id| self.lisamaksut.get(&id)) {
+            return tili;
+        }
+        if let Some(tili) = jasentyyppi.and_then(|t| self.jasentyypit.get(t)) {
+            return tili;
+        }
+        &self.tulot
+    }
+}
+
+// Laskurivi kirjanpitoa varten. Jäsentyyppi on rivin jäsenen nykyinen tyyppi.
+// Ennakkorivi vähentää talouden ennakkomaksua eikä ole tuloa.
+#[derive(Debug, Clone)]
+pub struct JournalLine {
+    pub jasentyyppi: Option<String>,
+    pub maksu_id: Option<i64>,
+    pub summa: f64,
+    pub ennakko: bool,
+}
+
+// Lasku, sen maksu ja luottotappio kirjanpitoa varten
+#[derive(Debug, Clone)]
+pub struct JournalInvoice {
+    pub lasku_id: i64,
+    pub laskunumero: Option<String>,
+    pub viitenumero: String,
+    pub vastaanottaja: String,
+    pub luontipaiva: NaiveDate,
+    pub summa: f64,
+    pub maksupaiva: Option<NaiveDate>,
+    pub luottotappio_pvm: Option<NaiveDate>,
+    pub rivit: Vec<JournalLine>,
+}
+
+// Talouden ennakkomaksu kirjanpitoa varten
+#[derive(Debug, Clone)]
+pub struct JournalCredit {
+    pub ennakko_id: i64,
+    pub paivamaara: NaiveDate,
+    pub summa: f64,
+    pub selite: String,
+    pub vastaanottaja: String,
+}
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Positiivinen summa debetiin, negatiivinen kreditiin
+fn entry(tili: &str, amount: f64) -> JournalEntry {
+    JournalEntry {
+        tili: tili.to_string(),
+        debet: if amount > 0.0 { amount } else { 0.0 },
+        kredit: if amount < 0.0 { -amount } else { 0.0 },
+    }
+}
+
+/// Muodostaa aikavälin tositteet laskuista, maksuista, luottotappioista ja
+/// talouksien ennakkomaksuista.
+///
+/// Lasku kirjataan luontipäivälle myyntisaamisiin ja tulotileille, maksu ja
+/// luottotappio omalle päivälleen myyntisaamisia vastaan. Tositteet numeroidaan
+/// päivämäärän mukaan. Muistutusmaksuja ei kirjata, koska sovelluksessa ei ole
+/// maksumuistutuksia.
+pub fn build_journal(
+    alku: NaiveDate,
+    loppu: NaiveDate,
+    accounts: &Accounts,
+    invoices: &[JournalInvoice],
+    credits: &[JournalCredit],
+) -> Journal {
+    // (päivä, järjestys, tosite): laskut ennen saman päivän maksuja ja luottotappioita
+    let mut vouchers: Vec<(NaiveDate, u8, JournalVoucher)> = Vec::new();
+
+    for invoice in invoices {
+        let tunniste = invoice
+            .laskunumero
+            .clone()
+            .unwrap_or_else(|| invoice.viitenumero.clone());
+
+        let in_range = invoice.luontipaiva >= alku && invoice.luontipaiva <= loppu;
+        if in_range && (invoice.summa != 0.0 || !invoice.rivit.is_empty()) {
+            let mut income: Vec<(String, f64)> = Vec::new();
+            let mut add_income =
+                |tili: &str, amount: f64| match income.iter_mut().find(|(t, _)| t == tili) {
+                    Some((_, sum)) => *sum += amount,
+                    None => income.push((tili.to_string(), amount)),
+                };
+
+            let mut lines_total = 0.0;
+            let mut prepaid = 0.0;
+            for line in &invoice.rivit {
+                if line.ennakko {
+                    prepaid -= line.summa;
+                } else {
+                    add_income(
+                        accounts.income_account(line.jasentyyppi.as_deref(), line.maksu_id),
+                        line.summa,
+                    );
+                }
+                lines_total += line.summa;
+            }
+            // Rivien ja laskun summan erotus oletustulotilille, jotta tosite täsmää
+            let remainder = round_cents(invoice.summa - lines_total);
+            if remainder != 0.0 {
+                add_income(&accounts.tulot, remainder);
+            }
+
+            // Saamiset ennen ennakkomaksun vähentämistä, koska ennakkomaksu on
+            // kirjattu myyntisaamisten kreditiin
+            let saatavat = round_cents(invoice.summa + prepaid);
+            let mut viennit = Vec::new();
+            if saatavat != 0.0 {
+                viennit.push(entry(&accounts.saatavat, saatavat));
+            }
+            viennit.extend(
+                income
+                    .into_iter()
+                    .map(|(tili, sum)| (tili, round_cents(sum)))
+                    .filter(|(_, sum)| *sum != 0.0)
+                    .map(|(tili, sum)| entry(&tili, -sum)),
+            );
+
+            // Negatiivinen lasku on hyvityslasku, jolloin debet ja kredit vaihtuvat
+            let hyvitys = invoice.summa < 0.0;
+            vouchers.push((
+                invoice.luontipaiva,
+                0,
+                JournalVoucher {
+                    numero: 0,
+                    paivamaara: invoice.luontipaiva,
+                    tyyppi: if hyvitys { "hyvitys" } else { "lasku" }.to_string(),
+                    lasku_id: Some(invoice.lasku_id),
+                    ennakko_id: None,
+                    laskunumero: invoice.laskunumero.clone(),
+                    selite: format!(
+                        "{} {} {}",
+                        if hyvitys { "Hyvitys" } else { "Lasku" },
+                        tunniste,
+                        invoice.vastaanottaja
+                    )
+                    .trim()
+                    .to_string(),
+                    viennit,
+                },
+            ));
+        }
+
+        if let Some(maksupaiva) = invoice.maksupaiva {
+            if maksupaiva >= alku && maksupaiva <= loppu && invoice.summa != 0.0 {
+                vouchers.push((
+                    maksupaiva,
+                    1,
+                    JournalVoucher {
+                        numero: 0,
+                        paivamaara: maksupaiva,
+                        tyyppi: "maksu".to_string(),
+                        lasku_id: Some(invoice.lasku_id),
+                        ennakko_id: None,
+                        laskunumero: invoice.laskunumero.clone(),
+                        selite: format!("Maksu {} {}", tunniste, invoice.vastaanottaja)
+                            .trim()
+                            .to_string(),
+                        viennit: vec![
+                            entry(&accounts.pankki, invoice.summa),
+                            entry(&accounts.saatavat, -invoice.summa),
+                        ],
+                    },
+                ));
+            }
+        }
+
+        if let (Some(pvm), Some(tili)) = (invoice.luottotappio_pvm, &accounts.luottotappiot) {
+            if pvm >= alku && pvm <= loppu && invoice.summa != 0.0 {
+                vouchers.push((
+                    pvm,
+                    2,
+                    JournalVoucher {
+                        numero: 0,
+                        paivamaara: pvm,
+                        tyyppi: "luottotappio".to_string(),
+                        lasku_id: Some(invoice.lasku_id),
+                        ennakko_id: None,
+                        laskunumero: invoice.laskunumero.clone(),
+                        selite: format!("Luottotappio {} {}", tunniste, invoice.vastaanottaja)
+                            .trim()
+                            .to_string(),
+                        viennit: vec![
+                            entry(tili, invoice.summa),
+                            entry(&accounts.saatavat, -invoice.summa),
+                        ],
+                    },
+                ));
+            }
+        }
+    }
+
+    for credit in credits {
+        if credit.paivamaara >= alku && credit.paivamaara <= loppu && credit.summa != 0.0 {
+            vouchers.push((
+                credit.paivamaara,
+                1,
+                JournalVoucher {
+                    numero: 0,
+                    paivamaara: credit.paivamaara,
+                    tyyppi: "ennakkomaksu".to_string(),
+                    lasku_id: None,
+                    ennakko_id: Some(credit.ennakko_id),
+                    laskunumero: None,
+                    selite: format!("Ennakkomaksu {} {}", credit.vastaanottaja, credit.selite)
+                        .trim()
+                        .to_string(),
+                    viennit: vec![
+                        entry(&accounts.pankki, credit.summa),
+                        entry(&accounts.saatavat, -credit.summa),
+                    ],
+                },
+            ));
+        }
+    }
+
+    vouchers.sort_by_key(|(date, order, voucher)| {
+        (
+            *date,
+            *order,
+            voucher.lasku_id.unwrap_or(0),
+            voucher.ennakko_id.unwrap_or(0),
+        )
+    });
+
+    let tositteet: Vec<JournalVoucher> = vouchers
+        .into_iter()
+        .enumerate()
+        .map(|(i, (_, _, mut voucher))| {
+            voucher.numero = i as i64 + 1;
+            voucher
+        })
+        .collect();
+
+    let debet_yhteensa = round_cents(
+        tositteet
+            .iter()
+            .flat_map(|v| &v.viennit)
+            .map(|e| e.debet)
+            .sum(),
+    );
+    let kredit_yhteensa = round_cents(
+        tositteet
+            .iter()
+            .flat_map(|v| &v.viennit)
+            .map(|e| e.kredit)
+            .sum(),
+    );
+
+    Journal {
+        alku,
+        loppu,
+        tositteet,
+        debet_yhteensa,
+        kredit_yhteensa,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 3, day).unwrap()
+    }
+
+    fn accounts() -> Accounts {
+        Accounts {
+            saatavat: "1700".to_string(),
+            pankki: "1910".to_string(),
+            tulot: "3000".to_string(),
+            luottotappiot: Some("8600".to_string()),
+            jasentyypit: HashMap::from([("varsinainen".to_string(), "3010".to_string())]),
+            lisamaksut: HashMap::from([(1, "3020".to_string())]),
+        }
+    }
+
+    fn line(
+        jasentyyppi: Option<&str>,
+        maksu_id: Option<i64>,
+        summa: f64,
+        ennakko: bool,
+    ) -> JournalLine {
+        JournalLine {
+            jasentyyppi: jasentyyppi.map(str::to_string),
+            maksu_id,
+            summa,
+            ennakko,
+        }
+    }
+
+    fn invoice(lasku_id: i64, luontipaiva: NaiveDate, rivit: Vec<JournalLine>) -> JournalInvoice {
+        JournalInvoice {
+            lasku_id,
+            laskunumero: Some(format!("2025-{:03}", lasku_id)),
+            viitenumero: format!("2025{:05}", lasku_id),
+            vastaanottaja: "Virtanen".to_string(),
+            luontipaiva,
+            summa: round_cents(rivit.iter().map(|l| l.summa).sum()),
+            maksupaiva: None,
+            luottotappio_pvm: None,
+            rivit,
+        }
+    }
+
+    fn assert_balanced(journal: &Journal) {
+        for voucher in &journal.tositteet {
+            let debet: f64 = voucher.viennit.iter().map(|e| e.debet).sum();
+            let kredit: f64 = voucher.viennit.iter().map(|e| e.kredit).sum();
+            assert!(
+                (debet - kredit).abs() < 0.005,
+                "tosite {} ei täsmää: {} / {}",
+                voucher.selite,
+                debet,
+                kredit
+            );
+        }
+        assert_eq!(journal.debet_yhteensa, journal.kredit_yhteensa);
+    }
+
+    fn amount(voucher: &JournalVoucher, tili: &str) -> f64 {
+        voucher
+            .viennit
+            .iter()
+            .filter(|e| e.tili == tili)
+            .map(|e| e.debet - e.kredit)
+            .sum()
+    }
+
+    #[test]
+    fn invoice_with_prepayment_line_is_balanced() {
+        let lasku = invoice(
+            1,
+            date(1),
+            vec![
+                line(Some("varsinainen"), None, 30.0, false),
+                line(None, Some(1), 12.5, false),
+                line(None, None, -20.0, true),
+            ],
+        );
+        let journal = build_journal(date(1), date(31), &accounts(), &[lasku], &[]);
+
+        assert_balanced(&journal);
+        let voucher = &journal.tositteet[0];
+        assert_eq!(voucher.tyyppi, "lasku");
+        // Saamiset kirjataan ennen ennakkomaksun vähentämistä
+        assert_eq!(amount(voucher, "1700"), 42.5);
+        assert_eq!(amount(voucher, "3010"), -30.0);
+        assert_eq!(amount(voucher, "3020"), -12.5);
+    }
+
+    #[test]
+    fn payment_is_balanced() {
+        let mut lasku = invoice(
+            1,
+            date(1),
+            vec![line(Some("varsinainen"), None, 30.0, false)],
+        );
+        lasku.maksupaiva = Some(date(10));
+        let journal = build_journal(date(1), date(31), &accounts(), &[lasku], &[]);
+
+        assert_balanced(&journal);
+        let payment = journal
+            .tositteet
+            .iter()
+            .find(|v| v.tyyppi == "maksu")
+            .unwrap();
+        assert_eq!(payment.paivamaara, date(10));
+        assert_eq!(amount(payment, "1910"), 30.0);
+        assert_eq!(amount(payment, "1700"), -30.0);
+    }
+
+    #[test]
+    fn write_off_is_balanced() {
+        let mut lasku = invoice(
+            1,
+            date(1),
+            vec![line(Some("varsinainen"), None, 30.0, false)],
+        );
+        lasku.luottotappio_pvm = Some(date(20));
+        let journal = build_journal(date(1), date(31), &accounts(), &[lasku], &[]);
+
+        assert_balanced(&journal);
+        let write_off = journal
+            .tositteet
+            .iter()
+            .find(|v| v.tyyppi == "luottotappio")
+            .unwrap();
+        assert_eq!(amount(write_off, "8600"), 30.0);
+        assert_eq!(amount(write_off, "1700"), -30.0);
+    }
+
+    #[test]
+    fn credit_note_swaps_sides() {
+        let hyvitys = invoice(
+            1,
+            date(5),
+            vec![line(Some("varsinainen"), None, -15.0, false)],
+        );
+        let journal = build_journal(date(1), date(31), &accounts(), &[hyvitys], &[]);
+
+        assert_balanced(&journal);
+        let voucher = &journal.tositteet[0];
+        assert_eq!(voucher.tyyppi, "hyvitys");
+        assert_eq!(amount(voucher, "1700"), -15.0);
+        assert_eq!(amount(voucher, "3010"), 15.0);
+    }
+
+    #[test]
+    fn same_day_vouchers_are_ordered() {
+        let mut written_off = invoice(
+            2,
+            date(10),
+            vec![line(Some("varsinainen"), None, 30.0, false)],
+        );
+        written_off.luottotappio_pvm = Some(date(10));
+        let mut paid = invoice(1, date(10), vec![line(None, None, 20.0, false)]);
+        paid.maksupaiva = Some(date(10));
+        let earlier = invoice(3, date(9), vec![line(None, None, 10.0, false)]);
+        let credit = JournalCredit {
+            ennakko_id: 7,
+            paivamaara: date(10),
+            summa: 50.0,
+            selite: "Ennakkomaksu".to_string(),
+            vastaanottaja: "Virtanen".to_string(),
+        };
+
+        let journal = build_journal(
+            date(1),
+            date(31),
+            &accounts(),
+            &[written_off, paid, earlier],
+            &[credit],
+        );
+
+        assert_balanced(&journal);
+        let order: Vec<(i64, &str, Option<i64>, Option<i64>)> = journal
+            .tositteet
+            .iter()
+            .map(|v| (v.numero, v.tyyppi.as_str(), v.lasku_id, v.ennakko_id))
+            .collect();
+        assert_eq!(
+            order,
+            vec![
+                (1, "lasku", Some(3), None),
+                (2, "lasku", Some(1), None),
+                (3, "lasku", Some(2), None),
+                (4, "ennakkomaksu", None, Some(7)),
+                (5, "maksu", Some(1), None),
+                (6, "luottotappio", Some(2), None),
+            ]
+        );
+    }
+}
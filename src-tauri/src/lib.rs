@@ -1,4 +1,11 @@
 mod models;
+mod fee_rules;
+mod journal;
+mod tilitin;
+mod reports;
+mod report_builder;
+mod xlsx;
+mod export;
 #[path = "database_simple.rs"]
 mod database;
 mod commands;
@@ -33,18 +40,99 @@ pub fn run() {
                 commands::create_household_with_address,
                 commands::update_household_with_address,
                 commands::delete_household,
+                commands::get_household_statement,
+                commands::get_household_credits,
+                commands::add_household_credit,
+                commands::delete_household_credit,
                 commands::save_pdf_file,
                 commands::show_save_dialog,
+                commands::get_member_types,
+                commands::create_member_type,
+                commands::update_member_type,
+                commands::delete_member_type,
+                commands::get_member_type_rules,
+                commands::create_member_type_rule,
+                commands::update_member_type_rule,
+                commands::delete_member_type_rule,
+                commands::preview_member_type_changes,
+                commands::propose_member_type_changes,
+                commands::get_member_type_change_log,
+                commands::approve_member_type_change,
+                commands::reject_member_type_change,
                 commands::get_membership_fees,
                 commands::create_membership_fee,
                 commands::update_membership_fee,
                 commands::delete_membership_fee,
+                commands::get_fee_rules,
+                commands::create_fee_rule,
+                commands::update_fee_rule,
+                commands::delete_fee_rule,
+                commands::get_fee_items,
+                commands::create_fee_item,
+                commands::update_fee_item,
+                commands::delete_fee_item,
+                commands::get_member_guardian,
+                commands::set_member_guardian,
+                commands::remove_member_guardian,
+                commands::get_member_fee_items,
+                commands::subscribe_member_fee_item,
+                commands::unsubscribe_member_fee_item,
+                commands::get_billing_periods,
+                commands::create_billing_period,
+                commands::update_billing_period,
+                commands::delete_billing_period,
+                commands::get_customers,
+                commands::create_customer,
+                commands::update_customer,
+                commands::delete_customer,
                 commands::get_invoices,
                 commands::validate_invoice_creation,
                 commands::create_invoice_for_year,
+                commands::validate_period_invoicing,
+                commands::create_invoices_for_period,
+                commands::create_invoices_for_selection,
+                commands::preview_invoice_creation,
+                commands::preview_period_invoicing,
+                commands::preview_invoices_for_selection,
+                commands::export_invoice_preview,
+                commands::get_invoice_batches,
+                commands::rollback_invoice_batch,
+                commands::create_manual_invoice,
+                commands::update_invoice,
+                commands::add_invoice_line,
+                commands::update_invoice_line,
+                commands::delete_invoice_line,
+                commands::mark_invoice_sent,
+                commands::get_invoice_changes,
                 commands::mark_invoice_paid,
+                commands::get_write_off_candidates,
+                commands::write_off_invoices,
+                commands::cancel_invoice_write_off,
+                commands::clear_member_expulsion_review,
                 commands::delete_invoice,
+                commands::get_account_mappings,
+                commands::set_account_mapping,
+                commands::delete_account_mapping,
+                commands::get_journal,
+                commands::export_journal,
+                commands::export_to_tilitin,
+                commands::get_receivables_aging,
+                commands::export_receivables_aging,
+                commands::get_membership_report,
+                commands::get_member_register,
+                commands::export_member_register,
+                commands::get_voting_list,
+                commands::export_voting_list,
+                commands::get_report_fields,
+                commands::get_report_definitions,
+                commands::create_report_definition,
+                commands::update_report_definition,
+                commands::delete_report_definition,
+                commands::run_report,
+                commands::preview_report,
+                commands::export_report,
                 commands::get_dashboard_stats,
+                commands::get_statistics,
                 commands::show_directory_dialog,
                 commands::show_file_dialog,
                 commands::backup_database,
@@ -15,6 +15,7 @@ pub struct Organization {
     pub pankkitili: Option<String>,
     pub bic: Option<String>,
     pub nuorisojasen_ikaraja: i32,
+    pub laskutustapa: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -31,6 +32,41 @@ pub struct CreateOrganization {
     pub pankkitili: Option<String>,
     pub bic: Option<String>,
     pub nuorisojasen_ikaraja: i32,
+    #[serde(default)]
+    pub laskutustapa: BillingMode,
+}
+
+// Laskutustapa: lasku taloudelle, jokaiselle jäsenelle oma lasku tai talouden
+// lasku, josta oman laskun valinneet jäsenet (`oma_lasku`) laskutetaan erikseen
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum BillingMode {
+    #[default]
+    Talous,
+    Jasen,
+    TalousErikseen,
+}
+
+impl std::fmt::Display for BillingMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BillingMode::Talous => "talous",
+            BillingMode::Jasen => "jasen",
+            BillingMode::TalousErikseen => "talous_erikseen",
+        })
+    }
+}
+
+impl std::str::FromStr for BillingMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "talous" | "Talous" => Ok(BillingMode::Talous),
+            "jasen" | "Jasen" => Ok(BillingMode::Jasen),
+            "talous_erikseen" | "TalousErikseen" => Ok(BillingMode::TalousErikseen),
+            _ => Err(format!("Invalid billing mode: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -71,39 +107,93 @@ pub struct CreateAddress {
     pub talous_id: i64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub enum MemberType {
-    Varsinainen,
-    Nuorisojasen,
-    Kannatus,
-    Kunnia,
-}
-
-impl ToString for MemberType {
-    fn to_string(&self) -> String {
-        match self {
-            MemberType::Varsinainen => "varsinainen".to_string(),
-            MemberType::Nuorisojasen => "nuorisojasen".to_string(),
-            MemberType::Kannatus => "kannatus".to_string(),
-            MemberType::Kunnia => "kunnia".to_string(),
-        }
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MemberType {
+    pub id: i64,
+    pub nimi: String,
+    pub kuvaus: Option<String>,
+    pub ika_min: Option<i32>,
+    pub ika_max: Option<i32>,
+    pub laskutettava: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMemberType {
+    pub nimi: String,
+    pub kuvaus: Option<String>,
+    pub ika_min: Option<i32>,
+    pub ika_max: Option<i32>,
+    pub laskutettava: bool,
+}
+
+// Siirtymäsäännön iän laskentatapa: maksuvuosi miinus syntymävuosi, tai tarkka
+// ikä leikkuripäivänä syntymäpäivän mukaan
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum AgeBasis {
+    #[default]
+    Vuosi,
+    Paiva,
+}
+
+impl std::fmt::Display for AgeBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AgeBasis::Vuosi => "vuosi",
+            AgeBasis::Paiva => "paiva",
+        })
     }
 }
 
-impl std::str::FromStr for MemberType {
+impl std::str::FromStr for AgeBasis {
     type Err = String;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
-            "varsinainen" | "Varsinainen" => Ok(MemberType::Varsinainen),
-            "nuorisojasen" | "Nuorisojasen" => Ok(MemberType::Nuorisojasen),
-            "kannatus" | "Kannatus" => Ok(MemberType::Kannatus),
-            "kunnia" | "Kunnia" => Ok(MemberType::Kunnia),
-            _ => Err(format!("Invalid member type: {}", s)),
+            "vuosi" | "Vuosi" => Ok(AgeBasis::Vuosi),
+            "paiva" | "Paiva" => Ok(AgeBasis::Paiva),
+            _ => Err(format!("Invalid age basis: {}", s)),
         }
     }
 }
 
+// Ikään perustuva siirtymäsääntö: lähtötyypin jäsen, jonka ikä on välillä
+// ika_min..=ika_max, siirtyy kohdetyyppiin. Säännöt käydään läpi järjestyksessä
+// ja jäseneen sovelletaan ensimmäistä osuvaa sääntöä.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MemberTypeRule {
+    pub id: i64,
+    pub nimi: String,
+    pub lahtotyyppi: String,
+    pub kohdetyyppi: String,
+    pub ika_min: Option<i32>,
+    pub ika_max: Option<i32>,
+    pub laskentatapa: String,
+    pub leikkuripaiva: Option<String>,
+    pub jarjestys: i32,
+    pub aktiivinen: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMemberTypeRule {
+    pub nimi: String,
+    pub lahtotyyppi: String,
+    pub kohdetyyppi: String,
+    pub ika_min: Option<i32>,
+    pub ika_max: Option<i32>,
+    #[serde(default)]
+    pub laskentatapa: AgeBasis,
+    // Muodossa KK-PP, esim. "12-31"
+    #[serde(default)]
+    pub leikkuripaiva: Option<String>,
+    #[serde(default)]
+    pub jarjestys: i32,
+    pub aktiivinen: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Member {
     pub id: i64,
@@ -117,6 +207,16 @@ pub struct Member {
     pub liittymispaiva: NaiveDate,
     pub jasentyyppi: String,
     pub aktiivinen: bool,
+    pub oma_lasku: bool,
+    pub jasenlaji: String,
+    pub y_tunnus: Option<String>,
+    pub yhteyshenkilo: Option<String>,
+    pub laskuviite: Option<String>,
+    pub verkkolaskuosoite: Option<String>,
+    pub valittajatunnus: Option<String>,
+    pub erottaminen_esitetty: Option<NaiveDate>,
+    // Päivä, jona jäsen merkittiin ei-aktiiviseksi
+    pub eroamispaiva: Option<NaiveDate>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -131,8 +231,51 @@ pub struct CreateMember {
     pub sahkoposti: Option<String>,
     pub osoite_id: i64,
     pub liittymispaiva: NaiveDate,
-    pub jasentyyppi: MemberType,
+    pub jasentyyppi: String,
     pub aktiivinen: bool,
+    pub oma_lasku: bool,
+    #[serde(default)]
+    pub jasenlaji: MemberKind,
+    #[serde(default)]
+    pub y_tunnus: Option<String>,
+    #[serde(default)]
+    pub yhteyshenkilo: Option<String>,
+    #[serde(default)]
+    pub laskuviite: Option<String>,
+    #[serde(default)]
+    pub verkkolaskuosoite: Option<String>,
+    #[serde(default)]
+    pub valittajatunnus: Option<String>,
+}
+
+// Jäsenlaji: henkilöjäsen tai yhteisöjäsen (yritys tai muu yhteisö). Yhteisöjäsenen
+// nimi on sukunimi-kentässä, eikä sillä ole henkilötunnusta tai syntymäaikaa.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum MemberKind {
+    #[default]
+    Henkilo,
+    Yhteiso,
+}
+
+impl std::fmt::Display for MemberKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MemberKind::Henkilo => "henkilo",
+            MemberKind::Yhteiso => "yhteiso",
+        })
+    }
+}
+
+impl std::str::FromStr for MemberKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "henkilo" | "Henkilo" => Ok(MemberKind::Henkilo),
+            "yhteiso" | "Yhteiso" => Ok(MemberKind::Yhteiso),
+            _ => Err(format!("Invalid member kind: {}", s)),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
@@ -148,14 +291,241 @@ pub struct MembershipFee {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateMembershipFee {
     pub vuosi: i32,
-    pub jasentyyppi: MemberType,
+    pub jasentyyppi: String,
     pub summa: f64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FeeRuleType {
+    Katto,
+    NnesJasen,
+    Perhemaksu,
+}
+
+impl std::fmt::Display for FeeRuleType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FeeRuleType::Katto => "katto",
+            FeeRuleType::NnesJasen => "nnes_jasen",
+            FeeRuleType::Perhemaksu => "perhemaksu",
+        })
+    }
+}
+
+impl std::str::FromStr for FeeRuleType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "katto" | "Katto" => Ok(FeeRuleType::Katto),
+            "nnes_jasen" | "NnesJasen" => Ok(FeeRuleType::NnesJasen),
+            "perhemaksu" | "Perhemaksu" => Ok(FeeRuleType::Perhemaksu),
+            _ => Err(format!("Invalid fee rule type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FeeRule {
+    pub id: i64,
+    pub nimi: String,
+    pub saantotyyppi: String,
+    pub vuosi: Option<i32>,
+    pub taydet_maksut: Option<i32>,
+    pub jasen_nro: Option<i32>,
+    pub alennus_prosentti: Option<f64>,
+    pub summa: Option<f64>,
+    pub vahimmaisjasenet: Option<i32>,
+    pub aktiivinen: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFeeRule {
+    pub nimi: String,
+    pub saantotyyppi: FeeRuleType,
+    pub vuosi: Option<i32>,
+    pub taydet_maksut: Option<i32>,
+    pub jasen_nro: Option<i32>,
+    pub alennus_prosentti: Option<f64>,
+    pub summa: Option<f64>,
+    pub vahimmaisjasenet: Option<i32>,
+    pub aktiivinen: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FeeItemBasis {
+    Jasen,
+    Talous,
+    UusiJasen,
+    Valinnainen,
+}
+
+impl std::fmt::Display for FeeItemBasis {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            FeeItemBasis::Jasen => "jasen",
+            FeeItemBasis::Talous => "talous",
+            FeeItemBasis::UusiJasen => "uusi_jasen",
+            FeeItemBasis::Valinnainen => "valinnainen",
+        })
+    }
+}
+
+impl std::str::FromStr for FeeItemBasis {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jasen" | "Jasen" => Ok(FeeItemBasis::Jasen),
+            "talous" | "Talous" => Ok(FeeItemBasis::Talous),
+            "uusi_jasen" | "UusiJasen" => Ok(FeeItemBasis::UusiJasen),
+            "valinnainen" | "Valinnainen" => Ok(FeeItemBasis::Valinnainen),
+            _ => Err(format!("Invalid fee item basis: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FeeItem {
+    pub id: i64,
+    pub nimi: String,
+    pub summa: f64,
+    pub peruste: String,
+    pub jasentyyppi: Option<String>,
+    pub vuosi: Option<i32>,
+    pub aktiivinen: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateFeeItem {
+    pub nimi: String,
+    pub summa: f64,
+    pub peruste: FeeItemBasis,
+    pub jasentyyppi: Option<String>,
+    pub vuosi: Option<i32>,
+    pub aktiivinen: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum BillingPeriodType {
+    Vuosi,
+    Lukukausi,
+}
+
+impl std::fmt::Display for BillingPeriodType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BillingPeriodType::Vuosi => "vuosi",
+            BillingPeriodType::Lukukausi => "lukukausi",
+        })
+    }
+}
+
+impl std::str::FromStr for BillingPeriodType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vuosi" | "Vuosi" => Ok(BillingPeriodType::Vuosi),
+            "lukukausi" | "Lukukausi" => Ok(BillingPeriodType::Lukukausi),
+            _ => Err(format!("Invalid billing period type: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BillingPeriod {
+    pub id: i64,
+    pub nimi: String,
+    pub tyyppi: String,
+    pub maksuvuosi: i32,
+    pub lukukausi: Option<i32>,
+    pub oletuserapaiva: Option<NaiveDate>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateBillingPeriod {
+    pub nimi: String,
+    pub tyyppi: BillingPeriodType,
+    pub maksuvuosi: i32,
+    pub lukukausi: Option<i32>,
+    pub oletuserapaiva: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum CustomerType {
+    Henkilo,
+    Yhteiso,
+}
+
+impl std::fmt::Display for CustomerType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            CustomerType::Henkilo => "henkilo",
+            CustomerType::Yhteiso => "yhteiso",
+        })
+    }
+}
+
+impl std::str::FromStr for CustomerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "henkilo" | "Henkilo" => Ok(CustomerType::Henkilo),
+            "yhteiso" | "Yhteiso" => Ok(CustomerType::Yhteiso),
+            _ => Err(format!("Invalid customer type: {}", s)),
+        }
+    }
+}
+
+// Laskun saaja, joka ei ole talous: työnantaja, kunta, kattojärjestö, tilavuokraaja
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Customer {
+    pub id: i64,
+    pub tyyppi: String,
+    pub nimi: String,
+    pub y_tunnus: Option<String>,
+    pub verkkolaskuosoite: Option<String>,
+    pub valittajatunnus: Option<String>,
+    pub yhteyshenkilo: Option<String>,
+    pub sahkoposti: Option<String>,
+    pub puhelinnumero: Option<String>,
+    pub katuosoite: Option<String>,
+    pub postinumero: Option<String>,
+    pub postitoimipaikka: Option<String>,
+    pub aktiivinen: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateCustomer {
+    pub tyyppi: CustomerType,
+    pub nimi: String,
+    pub y_tunnus: Option<String>,
+    pub verkkolaskuosoite: Option<String>,
+    pub valittajatunnus: Option<String>,
+    pub yhteyshenkilo: Option<String>,
+    pub sahkoposti: Option<String>,
+    pub puhelinnumero: Option<String>,
+    pub katuosoite: Option<String>,
+    pub postinumero: Option<String>,
+    pub postitoimipaikka: Option<String>,
+    pub aktiivinen: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct Invoice {
     pub id: i64,
-    pub talous_id: i64,
+    pub talous_id: Option<i64>,
+    pub asiakas_id: Option<i64>,
     pub luontipaiva: NaiveDate,
     pub erapaiva: NaiveDate,
     pub summa: f64,
@@ -163,6 +533,15 @@ pub struct Invoice {
     pub laskunumero: Option<String>,
     pub maksettu: bool,
     pub maksupaiva: Option<NaiveDate>,
+    pub viesti: Option<String>,
+    pub lahetetty: Option<NaiveDate>,
+    pub kausi_id: Option<i64>,
+    pub lisalasku: bool,
+    pub ajo_id: Option<i64>,
+    pub jasen_id: Option<i64>,
+    pub huoltaja_id: Option<i64>,
+    pub luottotappio_pvm: Option<NaiveDate>,
+    pub luottotappio_syy: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -181,7 +560,8 @@ pub struct CreateInvoice {
 pub struct InvoiceLine {
     pub id: i64,
     pub lasku_id: i64,
-    pub jasen_id: i64,
+    pub jasen_id: Option<i64>,
+    pub maksu_id: Option<i64>,
     pub kuvaus: String,
     pub summa: f64,
     pub created_at: DateTime<Utc>,
@@ -190,24 +570,227 @@ pub struct InvoiceLine {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CreateInvoiceLine {
     pub lasku_id: i64,
-    pub jasen_id: i64,
+    pub jasen_id: Option<i64>,
+    pub maksu_id: Option<i64>,
     pub kuvaus: String,
     pub summa: f64,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateManualInvoice {
+    pub talous_id: Option<i64>,
+    pub jasen_id: Option<i64>,
+    #[serde(default)]
+    pub asiakas_id: Option<i64>,
+    pub erapaiva: NaiveDate,
+    pub viesti: Option<String>,
+    pub lines: Vec<CreateManualInvoiceLine>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateManualInvoiceLine {
+    pub jasen_id: Option<i64>,
+    pub maksu_id: Option<i64>,
+    pub kuvaus: String,
+    pub summa: f64,
+}
+
+// Laskun muokattavat otsikkotiedot
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateInvoice {
+    pub erapaiva: NaiveDate,
+    pub viesti: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InvoiceChange {
+    pub id: i64,
+    pub lasku_id: i64,
+    pub rivi_id: Option<i64>,
+    pub muutos: String,
+    pub vanha_arvo: Option<String>,
+    pub uusi_arvo: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+// Laskutuksen rajaus: tyhjä valinta laskuttaa kaikki kauden laskutettavat.
+// Jäsentason rajaukset (jäsen, liittymispäivä, jäsentyyppi) laskuttavat vain
+// valitut jäsenet; talouden muut jäsenet laskutetaan myöhemmin lisälaskulla.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct InvoiceSelection {
+    pub talous_idt: Option<Vec<i64>>,
+    pub jasen_id: Option<i64>,
+    pub liittynyt_alkaen: Option<NaiveDate>,
+    pub liittynyt_asti: Option<NaiveDate>,
+    pub jasentyyppi: Option<String>,
+}
+
+impl InvoiceSelection {
+    pub fn is_member_level(&self) -> bool {
+        self.jasen_id.is_some()
+            || self.liittynyt_alkaen.is_some()
+            || self.liittynyt_asti.is_some()
+            || self.jasentyyppi.is_some()
+    }
+
+    pub fn matches(
+        &self,
+        talous_id: i64,
+        jasen_id: i64,
+        liittymispaiva: NaiveDate,
+        jasentyyppi: &str,
+    ) -> bool {
+        self.talous_idt.as_ref().is_none_or(|ids| ids.contains(&talous_id))
+            && self.jasen_id.is_none_or(|id| id == jasen_id)
+            && self.liittynyt_alkaen.is_none_or(|d| liittymispaiva >= d)
+            && self.liittynyt_asti.is_none_or(|d| liittymispaiva <= d)
+            && self
+                .jasentyyppi
+                .as_ref()
+                .is_none_or(|t| t.eq_ignore_ascii_case(jasentyyppi))
+    }
+}
+
+// Laskutusajo: yhdellä kerralla luodut laskut, jotka voi perua yhdessä
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct InvoiceBatch {
+    pub id: i64,
+    pub kausi_id: Option<i64>,
+    pub laskuja: i64,
+    pub summa: f64,
+    pub peruttu: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceBatchResult {
+    pub ajo: InvoiceBatch,
+    pub laskut: Vec<Invoice>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvoiceWithDetails {
     pub invoice: Invoice,
-    pub household: Household,
-    pub address: Address,
+    pub household: Option<Household>,
+    pub address: Option<Address>,
     pub lines: Vec<InvoiceLineWithMember>,
     pub billing_address: Option<Address>,
+    pub member: Option<Member>,
+    pub payer: Option<InvoicePayer>,
+    pub customer: Option<Customer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InvoiceLineWithMember {
     pub line: InvoiceLine,
-    pub member: Member,
+    pub member: Option<Member>,
+}
+
+// Siirtymäsääntöjen mukainen jäsentyypin muutos, jota ei ole vielä tehty
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberTypeChange {
+    pub jasen_id: i64,
+    pub etunimi: String,
+    pub sukunimi: String,
+    pub ika: i32,
+    pub vanha_tyyppi: String,
+    pub uusi_tyyppi: String,
+    pub saanto_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum MemberTypeChangeStatus {
+    Ehdotettu,
+    Hyvaksytty,
+    Hylatty,
+}
+
+impl std::fmt::Display for MemberTypeChangeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            MemberTypeChangeStatus::Ehdotettu => "ehdotettu",
+            MemberTypeChangeStatus::Hyvaksytty => "hyvaksytty",
+            MemberTypeChangeStatus::Hylatty => "hylatty",
+        })
+    }
+}
+
+impl std::str::FromStr for MemberTypeChangeStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ehdotettu" | "Ehdotettu" => Ok(MemberTypeChangeStatus::Ehdotettu),
+            "hyvaksytty" | "Hyvaksytty" => Ok(MemberTypeChangeStatus::Hyvaksytty),
+            "hylatty" | "Hylatty" => Ok(MemberTypeChangeStatus::Hylatty),
+            _ => Err(format!("Invalid member type change status: {}", s)),
+        }
+    }
+}
+
+// Jäsentyyppimuutosten lokin rivi: ehdotus ja sen käsittely
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberTypeChangeLog {
+    pub id: i64,
+    pub jasen_id: i64,
+    pub etunimi: String,
+    pub sukunimi: String,
+    pub vanha_tyyppi: String,
+    pub uusi_tyyppi: String,
+    pub ika: Option<i32>,
+    pub vuosi: Option<i32>,
+    pub saanto_id: Option<i64>,
+    pub saanto: Option<String>,
+    pub tila: String,
+    pub ajo_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+    pub kasitelty: Option<DateTime<Utc>>,
+}
+
+// Laskutuksen esikatselu: mitä laskuja kaudelle luotaisiin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePreview {
+    pub kausi: BillingPeriod,
+    pub laskut: Vec<InvoicePreviewInvoice>,
+    pub ohitetut: Vec<SkippedMember>,
+    pub tyyppimuutokset: Vec<MemberTypeChange>,
+    pub laskuja: i64,
+    pub lisalaskuja: i64,
+    pub yhteensa: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePreviewInvoice {
+    pub talous_id: i64,
+    pub jasen_id: Option<i64>,
+    pub talouden_nimi: Option<String>,
+    pub vastaanottaja: Option<String>,
+    pub lisalasku: bool,
+    pub rivit: Vec<InvoicePreviewLine>,
+    pub summa: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePreviewLine {
+    pub jasen_id: Option<i64>,
+    pub jasen: Option<String>,
+    pub jasentyyppi: Option<String>,
+    pub maksu_id: Option<i64>,
+    pub kuvaus: String,
+    pub summa: f64,
+}
+
+// Laskutuksen ulkopuolelle jäävä jäsen ja syy.
+// syy: ei_aktiivinen, jo_laskutettu, ei_laskutettava, ei_jasenmaksua, nolla_summa
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedMember {
+    pub jasen_id: i64,
+    pub etunimi: String,
+    pub sukunimi: String,
+    pub jasentyyppi: String,
+    pub talous_id: i64,
+    pub syy: String,
+    pub selite: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -217,6 +800,521 @@ pub struct MemberWithAddress {
     pub household: Household,
 }
 
+// Jäsenen huoltaja/maksaja: toinen jäsen tai jäsenrekisterin ulkopuolinen henkilö,
+// jonka yhteystiedot ovat tässä
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberGuardian {
+    pub id: i64,
+    pub jasen_id: i64,
+    pub huoltaja_jasen_id: Option<i64>,
+    pub nimi: Option<String>,
+    pub katuosoite: Option<String>,
+    pub postinumero: Option<String>,
+    pub postitoimipaikka: Option<String>,
+    pub sahkoposti: Option<String>,
+    pub puhelinnumero: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateMemberGuardian {
+    pub huoltaja_jasen_id: Option<i64>,
+    pub nimi: Option<String>,
+    pub katuosoite: Option<String>,
+    pub postinumero: Option<String>,
+    pub postitoimipaikka: Option<String>,
+    pub sahkoposti: Option<String>,
+    pub puhelinnumero: Option<String>,
+}
+
+// Laskun maksajan yhteystiedot, kun lasku on ohjattu huoltajalle
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoicePayer {
+    pub huoltaja_id: Option<i64>,
+    pub nimi: String,
+    pub katuosoite: Option<String>,
+    pub postinumero: Option<String>,
+    pub postitoimipaikka: Option<String>,
+    pub sahkoposti: Option<String>,
+}
+
+// Laskujen kirjaus luottotappioksi. Erottamisehdotus merkitsee laskun
+// jäsenet erottamisen käsittelyyn.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateWriteOff {
+    pub lasku_idt: Vec<i64>,
+    pub paivamaara: NaiveDate,
+    pub syy: String,
+    #[serde(default)]
+    pub erottamisehdotus: bool,
+}
+
+// Kirjanpidon tilin kohde: perustilit sekä jäsentyypin ja lisämaksun tulotilit
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum AccountTarget {
+    Saatavat,
+    Pankki,
+    Tulot,
+    Luottotappiot,
+    Jasentyyppi,
+    Lisamaksu,
+}
+
+impl std::fmt::Display for AccountTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AccountTarget::Saatavat => "saatavat",
+            AccountTarget::Pankki => "pankki",
+            AccountTarget::Tulot => "tulot",
+            AccountTarget::Luottotappiot => "luottotappiot",
+            AccountTarget::Jasentyyppi => "jasentyyppi",
+            AccountTarget::Lisamaksu => "lisamaksu",
+        })
+    }
+}
+
+impl std::str::FromStr for AccountTarget {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "saatavat" | "Saatavat" => Ok(AccountTarget::Saatavat),
+            "pankki" | "Pankki" => Ok(AccountTarget::Pankki),
+            "tulot" | "Tulot" => Ok(AccountTarget::Tulot),
+            "luottotappiot" | "Luottotappiot" => Ok(AccountTarget::Luottotappiot),
+            "jasentyyppi" | "Jasentyyppi" => Ok(AccountTarget::Jasentyyppi),
+            "lisamaksu" | "Lisamaksu" => Ok(AccountTarget::Lisamaksu),
+            _ => Err(format!("Invalid account target: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AccountMapping {
+    pub id: i64,
+    pub kohde: String,
+    pub jasentyyppi: Option<String>,
+    pub maksu_id: Option<i64>,
+    pub tili: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateAccountMapping {
+    pub kohde: AccountTarget,
+    pub jasentyyppi: Option<String>,
+    pub maksu_id: Option<i64>,
+    pub tili: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub tili: String,
+    pub debet: f64,
+    pub kredit: f64,
+}
+
+// Kirjanpidon tosite. tyyppi: lasku, hyvitys, maksu, luottotappio, ennakkomaksu.
+// Ennakkomaksun tositteella on `ennakko_id`, muilla `lasku_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalVoucher {
+    pub numero: i64,
+    pub paivamaara: NaiveDate,
+    pub tyyppi: String,
+    pub lasku_id: Option<i64>,
+    pub ennakko_id: Option<i64>,
+    pub laskunumero: Option<String>,
+    pub selite: String,
+    pub viennit: Vec<JournalEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Journal {
+    pub alku: NaiveDate,
+    pub loppu: NaiveDate,
+    pub tositteet: Vec<JournalVoucher>,
+    pub debet_yhteensa: f64,
+    pub kredit_yhteensa: f64,
+}
+
+// Tilitiniin viety tai jo aiemmin viety tosite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TilitinVoucher {
+    pub lasku_id: Option<i64>,
+    pub ennakko_id: Option<i64>,
+    pub tyyppi: String,
+    pub paivamaara: NaiveDate,
+    pub selite: String,
+    pub tositenumero: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TilitinExport {
+    pub tietokanta: String,
+    pub viedyt: Vec<TilitinVoucher>,
+    pub ohitetut: Vec<TilitinVoucher>,
+}
+
+// Talouden ennakkomaksu (positiivinen) tai laskulla käytetty hyvitys (negatiivinen)
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct HouseholdCredit {
+    pub id: i64,
+    pub talous_id: i64,
+    pub paivamaara: NaiveDate,
+    pub summa: f64,
+    pub selite: String,
+    pub lasku_id: Option<i64>,
+    pub rivi_id: Option<i64>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateHouseholdCredit {
+    pub talous_id: i64,
+    pub paivamaara: NaiveDate,
+    pub summa: f64,
+    pub selite: String,
+}
+
+// Tiliotteen tapahtuma. tyyppi: lasku, hyvitys, maksu, luottotappio,
+// ennakkomaksu, ennakkomaksu_kaytetty. Saldo on tapahtuman jälkeinen saldo;
+// positiivinen saldo on talouden velkaa, negatiivinen talouden hyväksi.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementEntry {
+    pub paivamaara: NaiveDate,
+    pub tyyppi: String,
+    pub selite: String,
+    pub lasku_id: Option<i64>,
+    pub laskunumero: Option<String>,
+    pub veloitus: f64,
+    pub hyvitys: f64,
+    pub saldo: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HouseholdStatement {
+    pub talous_id: i64,
+    pub talouden_nimi: Option<String>,
+    pub vastaanottaja: Option<String>,
+    pub tapahtumat: Vec<StatementEntry>,
+    pub saldo: f64,
+    pub avoimet_laskut: f64,
+    pub ennakkomaksut: f64,
+}
+
+// Avoimet saatavat ikäluokittain eräpäivästä kuluneiden päivien mukaan
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgingBuckets {
+    pub eraantymaton: f64,
+    pub paivaa_1_30: f64,
+    pub paivaa_31_90: f64,
+    pub paivaa_91_365: f64,
+    pub yli_vuosi: f64,
+    pub yhteensa: f64,
+}
+
+// Maksajan (talous tai asiakas) avoimet laskut ikäluokittain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingRow {
+    pub talous_id: Option<i64>,
+    pub asiakas_id: Option<i64>,
+    pub nimi: String,
+    pub laskuja: i64,
+    pub summat: AgingBuckets,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingYear {
+    pub vuosi: i32,
+    pub laskuja: i64,
+    pub summat: AgingBuckets,
+}
+
+// Saatavien ikäjakauma päivämäärän tilanteen mukaan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgingReport {
+    pub paivamaara: NaiveDate,
+    pub rivit: Vec<AgingRow>,
+    pub vuodet: Vec<AgingYear>,
+    pub yhteensa: AgingBuckets,
+}
+
+// Jäsentyypin jäsenmäärät vuoden alussa ja lopussa sekä vuoden aikana
+// liittyneet ja eronneet
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MembershipTypeCount {
+    pub jasentyyppi: String,
+    pub alussa: i64,
+    pub lopussa: i64,
+    pub liittyneet: i64,
+    pub eronneet: i64,
+}
+
+// Vuosiraportti toimintakertomusta varten. Laskutustiedot koskevat vuoden
+// laskutusvuoden laskuja.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MembershipReport {
+    pub vuosi: i32,
+    pub jasentyypit: Vec<MembershipTypeCount>,
+    pub yhteensa: MembershipTypeCount,
+    // Eronneet, joiden eroamispäivä ei ole tiedossa; eivät sisälly eronneisiin
+    pub eronneet_tuntematon: i64,
+    pub laskuja: i64,
+    pub laskutettu: f64,
+    pub maksettuja: i64,
+    pub maksettu: f64,
+    pub avoimia: i64,
+    pub avoinna: f64,
+    pub luottotappioita: i64,
+    pub luottotappiot: f64,
+}
+
+// Jäsenluettelon järjestys ryhmän sisällä
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum RegisterOrder {
+    #[default]
+    Nimi,
+    Kotikunta,
+    Liittymispaiva,
+}
+
+// Jäsenluettelon ryhmittely
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub enum RegisterGrouping {
+    #[default]
+    Ei,
+    Jasentyyppi,
+    Kotikunta,
+}
+
+// Jäsenluettelon asetukset. Päivämäärän puuttuessa luettelo on tämän päivän
+// tilanteen mukainen. Henkilötunnukset tulevat mukaan vain erikseen pyydettäessä.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MemberRegisterOptions {
+    #[serde(default)]
+    pub paivamaara: Option<NaiveDate>,
+    #[serde(default)]
+    pub jarjestys: RegisterOrder,
+    #[serde(default)]
+    pub ryhmittely: RegisterGrouping,
+    #[serde(default)]
+    pub henkilotunnus: bool,
+}
+
+// Kotikunta on jäsenen osoitteen postitoimipaikka
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberRegisterEntry {
+    pub jasen_id: i64,
+    pub nimi: String,
+    pub kotikunta: String,
+    pub jasentyyppi: String,
+    pub liittymispaiva: NaiveDate,
+    pub henkilotunnus: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberRegisterGroup {
+    pub nimi: String,
+    pub jasenet: Vec<MemberRegisterEntry>,
+}
+
+// Yhdistyslain mukainen jäsenluettelo päivän tilanteen mukaan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemberRegister {
+    pub paivamaara: NaiveDate,
+    pub ryhmittely: RegisterGrouping,
+    pub henkilotunnukset: bool,
+    pub ryhmat: Vec<MemberRegisterGroup>,
+    pub jasenia: i64,
+}
+
+// Jäsenen jäsenmaksun tila äänioikeusluettelossa. Vapautettu on jäsentyyppi,
+// jota ei laskuteta, esim. kunniajäsen.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum VotingStatus {
+    Maksettu,
+    Maksamatta,
+    EiLaskutettu,
+    Vapautettu,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingListEntry {
+    pub jasen_id: i64,
+    pub nimi: String,
+    pub jasentyyppi: String,
+    pub tila: VotingStatus,
+    pub aanioikeus: bool,
+}
+
+// Äänioikeusluettelo: aktiiviset jäsenet ja laskutusvuoden jäsenmaksun tila
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VotingList {
+    pub vuosi: i32,
+    pub vapautetut: bool,
+    pub jasenet: Vec<VotingListEntry>,
+    pub aanioikeutettuja: i64,
+}
+
+// Jäsenmuutokset kuukaudelta
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MonthlyMembership {
+    pub vuosi: i32,
+    pub kuukausi: u32,
+    pub liittyneet: i64,
+    pub eronneet: i64,
+}
+
+// Vuoden tilastot. Jäsenmäärät ovat vuoden alun ja lopun mukaisia, laskutus
+// laskutusvuoden mukainen. Keräysaste on maksettujen osuus laskutetusta
+// prosentteina ja maksuaika päivinä laskun lähettämisestä maksuun.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct YearStatistics {
+    pub vuosi: i32,
+    pub jasentyypit: Vec<MembershipTypeCount>,
+    pub yhteensa: MembershipTypeCount,
+    pub laskuja: i64,
+    pub laskutettu: f64,
+    pub maksettu: f64,
+    pub keraysaste: Option<f64>,
+    pub eraantynyt: f64,
+    pub keskim_maksuaika: Option<f64>,
+}
+
+// Tilastot vuosiväliltä trendejä varten. Erääntynyt summa ja keskimääräinen
+// maksuaika ovat koko vuosivälin laskuista.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Statistics {
+    pub alkuvuosi: i32,
+    pub loppuvuosi: i32,
+    pub vuodet: Vec<YearStatistics>,
+    pub kuukaudet: Vec<MonthlyMembership>,
+    pub eraantynyt: f64,
+    pub eraantyneita: i64,
+    pub keskim_maksuaika: Option<f64>,
+}
+
+// Tallennetun raportin kohde
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+pub enum ReportSource {
+    #[default]
+    Jasenet,
+    Taloudet,
+    Laskut,
+}
+
+impl std::fmt::Display for ReportSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReportSource::Jasenet => "jasenet",
+            ReportSource::Taloudet => "taloudet",
+            ReportSource::Laskut => "laskut",
+        })
+    }
+}
+
+impl std::str::FromStr for ReportSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "jasenet" | "Jasenet" => Ok(ReportSource::Jasenet),
+            "taloudet" | "Taloudet" => Ok(ReportSource::Taloudet),
+            "laskut" | "Laskut" => Ok(ReportSource::Laskut),
+            _ => Err(format!("Invalid report source: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ReportFieldType {
+    Teksti,
+    Numero,
+    Paivamaara,
+    Totuus,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum FilterOperator {
+    Yhtasuuri,
+    Eri,
+    Sisaltaa,
+    AlkaaJolla,
+    Suurempi,
+    Pienempi,
+    Vahintaan,
+    Enintaan,
+    Tyhja,
+    EiTyhja,
+}
+
+// Suodattimen arvo: kiinteä arvo tai raporttia ajettaessa annettava parametri
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum FilterValue {
+    Arvo(String),
+    Parametri(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportFilter {
+    pub kentta: String,
+    pub operaattori: FilterOperator,
+    #[serde(default)]
+    pub arvo: Option<FilterValue>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportSort {
+    pub kentta: String,
+    #[serde(default)]
+    pub laskeva: bool,
+}
+
+// Tallennettu raportti: kohde, sarakkeet, suodattimet ja järjestys
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDefinition {
+    pub id: i64,
+    pub nimi: String,
+    pub kuvaus: Option<String>,
+    pub kohde: ReportSource,
+    pub sarakkeet: Vec<String>,
+    pub suodattimet: Vec<ReportFilter>,
+    pub jarjestys: Vec<ReportSort>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateReportDefinition {
+    pub nimi: String,
+    pub kuvaus: Option<String>,
+    pub kohde: ReportSource,
+    pub sarakkeet: Vec<String>,
+    #[serde(default)]
+    pub suodattimet: Vec<ReportFilter>,
+    #[serde(default)]
+    pub jarjestys: Vec<ReportSort>,
+}
+
+// Raportissa käytettävissä oleva kenttä käyttöliittymää varten
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportFieldInfo {
+    pub kentta: String,
+    pub otsikko: String,
+    pub tyyppi: ReportFieldType,
+}
+
+// Ajetun raportin tulos. Arvot on muotoiltu näytettäviksi: päivämäärät
+// muodossa pp.kk.vvvv, luvut desimaalipilkulla ja totuusarvot kyllä/ei.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportResult {
+    pub nimi: String,
+    pub sarakkeet: Vec<ReportFieldInfo>,
+    pub rivit: Vec<Vec<String>>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DashboardStats {
     pub total_members: i64,
@@ -0,0 +1,576 @@
+use crate::export::format_amount;
+use crate::models::{
+    CreateReportDefinition, FilterOperator, FilterValue, ReportFieldInfo, ReportFieldType,
+    ReportFilter, ReportSort, ReportSource,
+};
+use anyhow::Result;
+use chrono::NaiveDate;
+use sqlx::Row;
+use std::collections::HashMap;
+use ReportFieldType::{Numero, Paivamaara, Teksti, Totuus};
+
+// Tallennettujen raporttien kentät. Raportti viittaa kenttiin vain nimellä;
+// SQL-lausekkeet ovat tässä, ja käyttäjän antamat arvot sidotaan parametreina,
+// joten raportin määrittelyllä ei voi ajaa omaa SQL:ää. Henkilötunnusta ei ole
+// kenttänä tarkoituksella.
+#[derive(Debug)]
+pub struct Field {
+    pub kentta: &'static str,
+    pub otsikko: &'static str,
+    pub tyyppi: ReportFieldType,
+    sql: &'static str,
+    rahamaara: bool,
+}
+
+const fn field(
+    kentta: &'static str,
+    otsikko: &'static str,
+    tyyppi: ReportFieldType,
+    sql: &'static str,
+) -> Field {
+    Field {
+        kentta,
+        otsikko,
+        tyyppi,
+        sql,
+        rahamaara: false,
+    }
+}
+
+const fn amount(kentta: &'static str, otsikko: &'static str, sql: &'static str) -> Field {
+    Field {
+        kentta,
+        otsikko,
+        tyyppi: ReportFieldType::Numero,
+        sql,
+        rahamaara: true,
+    }
+}
+
+const MEMBER_FIELDS: &[Field] = &[
+    field(
+        "nimi",
+        "Nimi",
+        Teksti,
+        "TRIM(m.etunimi || ' ' || m.sukunimi)",
+    ),
+    field("etunimi", "Etunimi", Teksti, "m.etunimi"),
+    field("sukunimi", "Sukunimi", Teksti, "m.sukunimi"),
+    field("jasentyyppi", "Jäsentyyppi", Teksti, "m.jasentyyppi"),
+    field("jasenlaji", "Jäsenlaji", Teksti, "m.jasenlaji"),
+    field("sahkoposti", "Sähköposti", Teksti, "m.sahkoposti"),
+    field("puhelinnumero", "Puhelinnumero", Teksti, "m.puhelinnumero"),
+    field("syntymaaika", "Syntymäaika", Paivamaara, "m.syntymaaika"),
+    field(
+        "liittymispaiva",
+        "Liittymispäivä",
+        Paivamaara,
+        "m.liittymispaiva",
+    ),
+    field(
+        "liittymisvuosi",
+        "Liittymisvuosi",
+        Numero,
+        "CAST(strftime('%Y', m.liittymispaiva) AS INTEGER)",
+    ),
+    field("eroamispaiva", "Eroamispäivä", Paivamaara, "m.eroamispaiva"),
+    field("aktiivinen", "Aktiivinen", Totuus, "m.aktiivinen"),
+    field("oma_lasku", "Oma lasku", Totuus, "m.oma_lasku"),
+    field("katuosoite", "Katuosoite", Teksti, "a.katuosoite"),
+    field("postinumero", "Postinumero", Teksti, "a.postinumero"),
+    field(
+        "postitoimipaikka",
+        "Postitoimipaikka",
+        Teksti,
+        "a.postitoimipaikka",
+    ),
+    field("talous", "Talous", Teksti, "h.talouden_nimi"),
+];
+
+const HOUSEHOLD_FIELDS: &[Field] = &[
+    field("talouden_nimi", "Talous", Teksti, "h.talouden_nimi"),
+    field(
+        "vastaanottaja",
+        "Laskun vastaanottaja",
+        Teksti,
+        "h.vastaanottaja",
+    ),
+    field("katuosoite", "Katuosoite", Teksti, "a.katuosoite"),
+    field("postinumero", "Postinumero", Teksti, "a.postinumero"),
+    field(
+        "postitoimipaikka",
+        "Postitoimipaikka",
+        Teksti,
+        "a.postitoimipaikka",
+    ),
+    field(
+        "jasenia",
+        "Aktiivisia jäseniä",
+        Numero,
+        "(SELECT COUNT(*) FROM members hm JOIN addresses ha ON hm.osoite_id = ha.id
+          WHERE ha.talous_id = h.id AND hm.aktiivinen = 1)",
+    ),
+    amount(
+        "avoinna",
+        "Avoinna",
+        "(SELECT COALESCE(SUM(hi.summa), 0) FROM invoices hi
+          WHERE hi.talous_id = h.id AND hi.maksettu = 0 AND hi.luottotappio_pvm IS NULL)",
+    ),
+];
+
+const INVOICE_FIELDS: &[Field] = &[
+    field("laskunumero", "Laskunumero", Teksti, "i.laskunumero"),
+    field("viitenumero", "Viitenumero", Teksti, "i.viitenumero"),
+    field(
+        "maksaja",
+        "Maksaja",
+        Teksti,
+        "COALESCE(c.nimi, h.vastaanottaja, h.talouden_nimi)",
+    ),
+    field("luontipaiva", "Laskun päivä", Paivamaara, "i.luontipaiva"),
+    field("erapaiva", "Eräpäivä", Paivamaara, "i.erapaiva"),
+    field(
+        "laskutusvuosi",
+        "Laskutusvuosi",
+        Numero,
+        "COALESCE(bp.maksuvuosi, CAST(strftime('%Y', i.luontipaiva) AS INTEGER))",
+    ),
+    amount("summa", "Summa", "i.summa"),
+    field("maksettu", "Maksettu", Totuus, "i.maksettu"),
+    field("maksupaiva", "Maksupäivä", Paivamaara, "i.maksupaiva"),
+    field("lahetetty", "Lähetetty", Paivamaara, "i.lahetetty"),
+    field(
+        "luottotappio",
+        "Luottotappio",
+        Totuus,
+        "(i.luottotappio_pvm IS NOT NULL)",
+    ),
+    field("viesti", "Viesti", Teksti, "i.viesti"),
+];
+
+fn fields(kohde: ReportSource) -> &'static [Field] {
+    match kohde {
+        ReportSource::Jasenet => MEMBER_FIELDS,
+        ReportSource::Taloudet => HOUSEHOLD_FIELDS,
+        ReportSource::Laskut => INVOICE_FIELDS,
+    }
+}
+
+fn from_clause(kohde: ReportSource) -> &'static str {
+    match kohde {
+        ReportSource::Jasenet => {
+            "members m
+             JOIN addresses a ON m.osoite_id = a.id
+             JOIN households h ON a.talous_id = h.id"
+        }
+        ReportSource::Taloudet => {
+            "households h
+             LEFT JOIN addresses a ON a.id = (SELECT MIN(id) FROM addresses WHERE talous_id = h.id)"
+        }
+        ReportSource::Laskut => {
+            "invoices i
+             LEFT JOIN billing_periods bp ON i.kausi_id = bp.id
+             LEFT JOIN customers c ON i.asiakas_id = c.id
+             LEFT JOIN households h ON i.talous_id = h.id AND i.asiakas_id IS NULL"
+        }
+    }
+}
+
+fn find_field(kohde: ReportSource, kentta: &str) -> Result<&'static Field> {
+    fields(kohde)
+        .iter()
+        .find(|f| f.kentta == kentta)
+        .ok_or_else(|| anyhow::anyhow!("Tuntematon kenttä: {}", kentta))
+}
+
+/// Kohteen kentät raporttieditoria varten.
+pub fn field_infos(kohde: ReportSource) -> Vec<ReportFieldInfo> {
+    fields(kohde)
+        .iter()
+        .map(|f| ReportFieldInfo {
+            kentta: f.kentta.to_string(),
+            otsikko: f.otsikko.to_string(),
+            tyyppi: f.tyyppi,
+        })
+        .collect()
+}
+
+/// Suodattimien parametrit esiintymisjärjestyksessä, kukin kerran.
+pub fn parameters(suodattimet: &[ReportFilter]) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for filter in suodattimet {
+        if let Some(FilterValue::Parametri(name)) = &filter.arvo {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+    }
+    names
+}
+
+// Sidottava arvo kentän tyypin mukaan
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindValue {
+    Teksti(String),
+    Numero(f64),
+    Paivamaara(NaiveDate),
+    Totuus(bool),
+}
+
+fn parse_value(field: &Field, value: &str) -> Result<BindValue> {
+    let value = value.trim();
+    let invalid = || anyhow::anyhow!("Kentän {} arvo ei kelpaa: {}", field.otsikko, value);
+    Ok(match field.tyyppi {
+        Teksti => BindValue::Teksti(value.to_string()),
+        Numero => BindValue::Numero(value.replace(',', ".").parse().map_err(|_| invalid())?),
+        Paivamaara => BindValue::Paivamaara(
+            NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .or_else(|_| NaiveDate::parse_from_str(value, "%d.%m.%Y"))
+                .map_err(|_| invalid())?,
+        ),
+        Totuus => BindValue::Totuus(match value.to_lowercase().as_str() {
+            "kyllä" | "kylla" | "true" | "1" => true,
+            "ei" | "false" | "0" => false,
+            _ => return Err(invalid()),
+        }),
+    })
+}
+
+fn needs_value(operaattori: FilterOperator) -> bool {
+    !matches!(operaattori, FilterOperator::Tyhja | FilterOperator::EiTyhja)
+}
+
+fn check_filter(field: &Field, filter: &ReportFilter) -> Result<()> {
+    use FilterOperator::*;
+    let allowed = match filter.operaattori {
+        Sisaltaa | AlkaaJolla => field.tyyppi == Teksti,
+        Suurempi | Pienempi | Vahintaan | Enintaan => matches!(field.tyyppi, Numero | Paivamaara),
+        Yhtasuuri | Eri | Tyhja | EiTyhja => true,
+    };
+    if !allowed {
+        return Err(anyhow::anyhow!(
+            "Ehtoa {:?} ei voi käyttää kenttään {}.",
+            filter.operaattori,
+            field.otsikko
+        ));
+    }
+
+    match (&filter.arvo, needs_value(filter.operaattori)) {
+        (None, true) => Err(anyhow::anyhow!(
+            "Kentän {} ehdolta puuttuu arvo.",
+            field.otsikko
+        )),
+        (Some(FilterValue::Arvo(value)), true) => parse_value(field, value).map(|_| ()),
+        (Some(FilterValue::Parametri(name)), true) if name.trim().is_empty() => Err(
+            anyhow::anyhow!("Kentän {} ehdon parametrilta puuttuu nimi.", field.otsikko),
+        ),
+        _ => Ok(()),
+    }
+}
+
+/// Tarkistaa raportin määrittelyn ennen tallennusta.
+pub fn validate(definition: &CreateReportDefinition) -> Result<()> {
+    if definition.nimi.trim().is_empty() {
+        return Err(anyhow::anyhow!("Raportin nimi puuttuu."));
+    }
+    if definition.sarakkeet.is_empty() {
+        return Err(anyhow::anyhow!("Valitse raporttiin vähintään yksi sarake."));
+    }
+    for kentta in &definition.sarakkeet {
+        find_field(definition.kohde, kentta)?;
+    }
+    for filter in &definition.suodattimet {
+        check_filter(find_field(definition.kohde, &filter.kentta)?, filter)?;
+    }
+    for sort in &definition.jarjestys {
+        find_field(definition.kohde, &sort.kentta)?;
+    }
+    Ok(())
+}
+
+fn escape_like(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+// Raportin kysely: SQL kenttien lausekkeista ja sidottavat arvot
+#[derive(Debug)]
+pub struct ReportQuery {
+    pub sql: String,
+    pub binds: Vec<BindValue>,
+    pub columns: Vec<&'static Field>,
+}
+
+/// Rakentaa raportin kyselyn. Parametrien arvot annetaan nimen mukaan;
+/// puuttuva parametri on virhe.
+pub fn build_query(
+    definition: &CreateReportDefinition,
+    params: &HashMap<String, String>,
+) -> Result<ReportQuery> {
+    validate(definition)?;
+    let kohde = definition.kohde;
+
+    let columns = definition
+        .sarakkeet
+        .iter()
+        .map(|kentta| find_field(kohde, kentta))
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut conditions = Vec::new();
+    let mut binds = Vec::new();
+    for filter in &definition.suodattimet {
+        let field = find_field(kohde, &filter.kentta)?;
+        let expr = field.sql;
+
+        let value = match &filter.arvo {
+            _ if !needs_value(filter.operaattori) => None,
+            Some(FilterValue::Arvo(value)) => Some(parse_value(field, value)?),
+            Some(FilterValue::Parametri(name)) => {
+                let value = params
+                    .get(name)
+                    .filter(|v| !v.trim().is_empty())
+                    .ok_or_else(|| {
+                        anyhow::anyhow!("Raportin parametrille {} ei annettu arvoa.", name)
+                    })?;
+                Some(parse_value(field, value)?)
+            }
+            None => None,
+        };
+        let collate = if field.tyyppi == Teksti {
+            " COLLATE NOCASE"
+        } else {
+            ""
+        };
+
+        let condition = match filter.operaattori {
+            FilterOperator::Yhtasuuri => format!("{} = ?{}", expr, collate),
+            FilterOperator::Eri => format!("{} IS NOT ?{}", expr, collate),
+            FilterOperator::Suurempi => format!("{} > ?", expr),
+            FilterOperator::Pienempi => format!("{} < ?", expr),
+            FilterOperator::Vahintaan => format!("{} >= ?", expr),
+            FilterOperator::Enintaan => format!("{} <= ?", expr),
+            FilterOperator::Sisaltaa | FilterOperator::AlkaaJolla => {
+                format!("{} LIKE ? ESCAPE '\\'", expr)
+            }
+            FilterOperator::Tyhja if field.tyyppi == Teksti => {
+                format!("COALESCE({}, '') = ''", expr)
+            }
+            FilterOperator::Tyhja => format!("{} IS NULL", expr),
+            FilterOperator::EiTyhja if field.tyyppi == Teksti => {
+                format!("COALESCE({}, '') <> ''", expr)
+            }
+            FilterOperator::EiTyhja => format!("{} IS NOT NULL", expr),
+        };
+        conditions.push(condition);
+
+        if let Some(value) = value {
+            binds.push(match (filter.operaattori, value) {
+                (FilterOperator::Sisaltaa, BindValue::Teksti(text)) => {
+                    BindValue::Teksti(format!("%{}%", escape_like(&text)))
+                }
+                (FilterOperator::AlkaaJolla, BindValue::Teksti(text)) => {
+                    BindValue::Teksti(format!("{}%", escape_like(&text)))
+                }
+                (_, value) => value,
+            });
+        }
+    }
+
+    // Ilman järjestystä rivit järjestetään ensimmäisen sarakkeen mukaan
+    let default_sort = [ReportSort {
+        kentta: definition.sarakkeet[0].clone(),
+        laskeva: false,
+    }];
+    let sorts = if definition.jarjestys.is_empty() {
+        &default_sort[..]
+    } else {
+        &definition.jarjestys[..]
+    };
+    let order = sorts
+        .iter()
+        .map(|sort| {
+            let field = find_field(kohde, &sort.kentta)?;
+            Ok(format!(
+                "{}{} {}",
+                field.sql,
+                if field.tyyppi == Teksti {
+                    " COLLATE NOCASE"
+                } else {
+                    ""
+                },
+                if sort.laskeva { "DESC" } else { "ASC" }
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let select = columns
+        .iter()
+        .enumerate()
+        .map(|(i, field)| format!("{} AS c{}", field.sql, i))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!("SELECT {} FROM {}", select, from_clause(kohde));
+    if !conditions.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&conditions.join(" AND "));
+    }
+    sql.push_str(" ORDER BY ");
+    sql.push_str(&order.join(", "));
+
+    Ok(ReportQuery {
+        sql,
+        binds,
+        columns,
+    })
+}
+
+/// Muotoilee tulosrivin arvon näytettäväksi kentän tyypin mukaan.
+pub fn format_value(row: &sqlx::sqlite::SqliteRow, index: usize, field: &Field) -> String {
+    match field.tyyppi {
+        Teksti => row
+            .try_get::<Option<String>, _>(index)
+            .ok()
+            .flatten()
+            .unwrap_or_default(),
+        Numero => {
+            let value = row
+                .try_get::<Option<f64>, _>(index)
+                .ok()
+                .flatten()
+                .or_else(|| {
+                    row.try_get::<Option<i64>, _>(index)
+                        .ok()
+                        .flatten()
+                        .map(|v| v as f64)
+                });
+            match value {
+                Some(v) if field.rahamaara => format_amount(v),
+                Some(v) => v.to_string().replace('.', ","),
+                None => String::new(),
+            }
+        }
+        Paivamaara => row
+            .try_get::<Option<NaiveDate>, _>(index)
+            .ok()
+            .flatten()
+            .map(|d| d.format("%d.%m.%Y").to_string())
+            .unwrap_or_default(),
+        Totuus => match row.try_get::<Option<bool>, _>(index).ok().flatten() {
+            Some(true) => "kyllä".to_string(),
+            Some(false) => "ei".to_string(),
+            None => String::new(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(sarakkeet: &[&str], suodattimet: Vec<ReportFilter>) -> CreateReportDefinition {
+        CreateReportDefinition {
+            nimi: "Testi".to_string(),
+            kuvaus: None,
+            kohde: ReportSource::Jasenet,
+            sarakkeet: sarakkeet.iter().map(|s| s.to_string()).collect(),
+            suodattimet,
+            jarjestys: Vec::new(),
+        }
+    }
+
+    fn filter(
+        kentta: &str,
+        operaattori: FilterOperator,
+        arvo: Option<FilterValue>,
+    ) -> ReportFilter {
+        ReportFilter {
+            kentta: kentta.to_string(),
+            operaattori,
+            arvo,
+        }
+    }
+
+    fn value(arvo: &str) -> Option<FilterValue> {
+        Some(FilterValue::Arvo(arvo.to_string()))
+    }
+
+    #[test]
+    fn build_query_accepts_only_listed_fields() {
+        let params = HashMap::new();
+        assert!(build_query(&definition(&["henkilotunnus"], Vec::new()), &params).is_err());
+        assert!(build_query(&definition(&["m.etunimi"], Vec::new()), &params).is_err());
+
+        let injected = filter("sukunimi) OR (1=1", FilterOperator::Tyhja, None);
+        assert!(build_query(&definition(&["nimi"], vec![injected]), &params).is_err());
+
+        let mut sorted = definition(&["nimi"], Vec::new());
+        sorted.jarjestys.push(ReportSort {
+            kentta: "random()".to_string(),
+            laskeva: false,
+        });
+        assert!(build_query(&sorted, &params).is_err());
+    }
+
+    #[test]
+    fn build_query_rejects_operator_for_field_type() {
+        let contains_date = filter("syntymaaika", FilterOperator::Sisaltaa, value("2000"));
+        let definition = definition(&["nimi"], vec![contains_date]);
+        assert!(build_query(&definition, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn build_query_escapes_like_wildcards() {
+        let definition = definition(
+            &["nimi"],
+            vec![
+                filter("sukunimi", FilterOperator::Sisaltaa, value("50%_a\\b")),
+                filter("etunimi", FilterOperator::AlkaaJolla, value("x_")),
+            ],
+        );
+        let query = build_query(&definition, &HashMap::new()).unwrap();
+
+        assert!(query.sql.contains("m.sukunimi LIKE ? ESCAPE '\\'"));
+        assert_eq!(
+            query.binds,
+            vec![
+                BindValue::Teksti("%50\\%\\_a\\\\b%".to_string()),
+                BindValue::Teksti("x\\_%".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn build_query_binds_values_as_parameters() {
+        let text = "O'Brien'; DROP TABLE members; --";
+        let definition = definition(
+            &["nimi", "liittymispaiva"],
+            vec![
+                filter("sukunimi", FilterOperator::Yhtasuuri, value(text)),
+                filter(
+                    "liittymispaiva",
+                    FilterOperator::Vahintaan,
+                    Some(FilterValue::Parametri("alku".to_string())),
+                ),
+                filter("sahkoposti", FilterOperator::Tyhja, None),
+            ],
+        );
+        let params = HashMap::from([("alku".to_string(), "1.2.2024".to_string())]);
+        let query = build_query(&definition, &params).unwrap();
+
+        assert!(!query.sql.contains("O'Brien"));
+        assert!(!query.sql.contains("DROP"));
+        assert_eq!(query.sql.matches('?').count(), query.binds.len());
+        assert_eq!(
+            query.binds,
+            vec![
+                BindValue::Teksti(text.to_string()),
+                BindValue::Paivamaara(NaiveDate::from_ymd_opt(2024, 2, 1).unwrap()),
+            ]
+        );
+
+        // Puuttuva parametri on virhe
+        assert!(build_query(&definition, &HashMap::new()).is_err());
+    }
+}
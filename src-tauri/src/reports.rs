@@ -0,0 +1,341 @@
+use crate::fee_rules::full_name;
+use crate::models::{
+    AgingBuckets, AgingReport, AgingRow, AgingYear, MemberRegister, MemberRegisterEntry,
+    MemberRegisterGroup, MemberRegisterOptions, MembershipTypeCount, MemberWithAddress,
+    MonthlyMembership,
+    RegisterGrouping, RegisterOrder, VotingList, VotingListEntry, VotingStatus,
+};
+use chrono::{Datelike, NaiveDate};
+use std::collections::HashMap;
+
+fn round_cents(value: f64) -> f64 {
+    (value * 100.0).round() / 100.0
+}
+
+// Avoin lasku ikäjakaumaa varten. Maksajana on talous tai asiakas.
+#[derive(Debug, Clone)]
+pub struct AgingInvoice {
+    pub talous_id: Option<i64>,
+    pub asiakas_id: Option<i64>,
+    pub nimi: String,
+    pub vuosi: i32,
+    pub erapaiva: NaiveDate,
+    pub summa: f64,
+}
+
+impl AgingBuckets {
+    /// Lisää summan ikäluokkaan eräpäivästä kuluneiden päivien mukaan.
+    /// Eräpäivänä lasku ei ole vielä erääntynyt.
+    pub fn add(&mut self, days_overdue: i64, summa: f64) {
+        let bucket = match days_overdue {
+            i64::MIN..=0 => &mut self.eraantymaton,
+            1..=30 => &mut self.paivaa_1_30,
+            31..=90 => &mut self.paivaa_31_90,
+            91..=365 => &mut self.paivaa_91_365,
+            _ => &mut self.yli_vuosi,
+        };
+        *bucket = round_cents(*bucket + summa);
+        self.yhteensa = round_cents(self.yhteensa + summa);
+    }
+}
+
+/// Ryhmittelee avoimet laskut maksajittain ja laskutusvuosittain. Maksajat
+/// järjestetään nimen ja vuodet vuoden mukaan.
+pub fn build_aging_report(paivamaara: NaiveDate, invoices: &[AgingInvoice]) -> AgingReport {
+    let mut rivit: Vec<AgingRow> = Vec::new();
+    let mut vuodet: Vec<AgingYear> = Vec::new();
+    let mut yhteensa = AgingBuckets::default();
+
+    for invoice in invoices {
+        let days = (paivamaara - invoice.erapaiva).num_days();
+
+        let row = match rivit
+            .iter_mut()
+            .position(|r| r.talous_id == invoice.talous_id && r.asiakas_id == invoice.asiakas_id)
+        {
+            Some(i) => &mut rivit[i],
+            None => {
+                rivit.push(AgingRow {
+                    talous_id: invoice.talous_id,
+                    asiakas_id: invoice.asiakas_id,
+                    nimi: invoice.nimi.clone(),
+                    laskuja: 0,
+                    summat: AgingBuckets::default(),
+                });
+                rivit.last_mut().unwrap()
+            }
+        };
+        row.laskuja += 1;
+        row.summat.add(days, invoice.summa);
+
+        let year = match vuodet.iter_mut().position(|y| y.vuosi == invoice.vuosi) {
+            Some(i) => &mut vuodet[i],
+            None => {
+                vuodet.push(AgingYear {
+                    vuosi: invoice.vuosi,
+                    laskuja: 0,
+                    summat: AgingBuckets::default(),
+                });
+                vuodet.last_mut().unwrap()
+            }
+        };
+        year.laskuja += 1;
+        year.summat.add(days, invoice.summa);
+
+        yhteensa.add(days, invoice.summa);
+    }
+
+    rivit.sort_by_key(|a| a.nimi.to_lowercase());
+    vuodet.sort_by_key(|y| y.vuosi);
+
+    AgingReport {
+        paivamaara,
+        rivit,
+        vuodet,
+        yhteensa,
+    }
+}
+
+// Jäsen vuosiraporttia varten. Tyyppimuutokset ovat hyväksyttyjä muutoksia
+// (päivä, vanha tyyppi) aikajärjestyksessä.
+#[derive(Debug, Clone)]
+pub struct ReportMember {
+    pub jasentyyppi: String,
+    pub liittymispaiva: NaiveDate,
+    pub eroamispaiva: Option<NaiveDate>,
+    pub aktiivinen: bool,
+    pub tyyppimuutokset: Vec<(NaiveDate, String)>,
+}
+
+impl ReportMember {
+    /// Jäsentyyppi päivän päättyessä: nykyinen tyyppi, ellei sitä ole
+    /// muutettu päivän jälkeen, jolloin ensimmäisen myöhemmän muutoksen vanha
+    /// tyyppi.
+    pub fn type_on(&self, date: NaiveDate) -> &str {
+        self.tyyppimuutokset
+            .iter()
+            .find(|(pvm, _)| *pvm > date)
+            .map_or(&self.jasentyyppi, |(_, vanha)| vanha)
+    }
+
+    /// Oliko jäsen jäsenenä päivän päättyessä. Ei-aktiivinen jäsen, jonka
+    /// eroamispäivä ei ole tiedossa, ei ole jäsenenä minään päivänä.
+    pub fn is_member_on(&self, date: NaiveDate) -> bool {
+        self.liittymispaiva <= date
+            && self.eroamispaiva.map_or(self.aktiivinen, |ero| ero > date)
+    }
+}
+
+// Jäsentyypin rivi; puuttuva tyyppi lisätään loppuun
+fn type_count<'a>(counts: &'a mut Vec<MembershipTypeCount>, tyyppi: &str) -> &'a mut MembershipTypeCount {
+    let index = match counts.iter().position(|c| c.jasentyyppi == tyyppi) {
+        Some(i) => i,
+        None => {
+            counts.push(MembershipTypeCount {
+                jasentyyppi: tyyppi.to_string(),
+                ..Default::default()
+            });
+            counts.len() - 1
+        }
+    };
+    &mut counts[index]
+}
+
+/// Jäsenmäärät jäsentyypeittäin vuoden alussa (edellisen vuoden lopussa) ja
+/// lopussa sekä vuoden aikana liittyneet ja eronneet. Tyypit ovat annetussa
+/// järjestyksessä; muut tyypit lisätään loppuun. Palauttaa myös yhteissummat.
+pub fn count_members(
+    vuosi: i32,
+    types: &[String],
+    members: &[ReportMember],
+) -> (Vec<MembershipTypeCount>, MembershipTypeCount) {
+    let loppu = NaiveDate::from_ymd_opt(vuosi, 12, 31).unwrap();
+    let edellinen = NaiveDate::from_ymd_opt(vuosi - 1, 12, 31).unwrap();
+
+    let mut counts: Vec<MembershipTypeCount> = types
+        .iter()
+        .map(|t| MembershipTypeCount {
+            jasentyyppi: t.clone(),
+            ..Default::default()
+        })
+        .collect();
+    let mut yhteensa = MembershipTypeCount {
+        jasentyyppi: "Yhteensä".to_string(),
+        ..Default::default()
+    };
+
+    for member in members {
+        if member.is_member_on(edellinen) {
+            type_count(&mut counts, member.type_on(edellinen)).alussa += 1;
+            yhteensa.alussa += 1;
+        }
+        if member.is_member_on(loppu) {
+            type_count(&mut counts, member.type_on(loppu)).lopussa += 1;
+            yhteensa.lopussa += 1;
+        }
+        if member.liittymispaiva.year() == vuosi {
+            type_count(&mut counts, member.type_on(member.liittymispaiva)).liittyneet += 1;
+            yhteensa.liittyneet += 1;
+        }
+        if let Some(ero) = member.eroamispaiva.filter(|e| e.year() == vuosi) {
+            type_count(&mut counts, member.type_on(ero)).eronneet += 1;
+            yhteensa.eronneet += 1;
+        }
+    }
+
+    (counts, yhteensa)
+}
+
+/// Vuoden loppuun mennessä liittyneet ei-aktiiviset jäsenet, joiden
+/// eroamispäivä ei ole tiedossa. He ovat eronneet ennen eroamispäivän
+/// kirjaamista, joten eroamisvuotta ei tiedetä.
+pub fn count_unknown_resignations(vuosi: i32, members: &[ReportMember]) -> i64 {
+    members
+        .iter()
+        .filter(|m| !m.aktiivinen && m.eroamispaiva.is_none() && m.liittymispaiva.year() <= vuosi)
+        .count() as i64
+}
+
+/// Liittyneet ja eronneet kuukausittain vuosiväliltä. Kaikki kuukaudet ovat
+/// mukana, myös ne, joina muutoksia ei ollut.
+pub fn count_monthly(alkuvuosi: i32, loppuvuosi: i32, members: &[ReportMember]) -> Vec<MonthlyMembership> {
+    let mut months: Vec<MonthlyMembership> = (alkuvuosi..=loppuvuosi)
+        .flat_map(|vuosi| {
+            (1..=12).map(move |kuukausi| MonthlyMembership {
+                vuosi,
+                kuukausi,
+                ..Default::default()
+            })
+        })
+        .collect();
+    let index = |date: NaiveDate| {
+        (alkuvuosi..=loppuvuosi)
+            .contains(&date.year())
+            .then(|| ((date.year() - alkuvuosi) * 12) as usize + date.month0() as usize)
+    };
+
+    for member in members {
+        if let Some(i) = index(member.liittymispaiva) {
+            months[i].liittyneet += 1;
+        }
+        if let Some(i) = member.eroamispaiva.and_then(index) {
+            months[i].eronneet += 1;
+        }
+    }
+
+    months
+}
+
+/// Jäsenluettelo: päivänä jäsenenä olleet jäsenet ryhmiteltyinä ja
+/// järjestettyinä asetusten mukaan. Jäsentyyppi on päivän mukainen.
+pub fn build_member_register(
+    paivamaara: NaiveDate,
+    options: &MemberRegisterOptions,
+    members: &[MemberWithAddress],
+    history: &HashMap<i64, ReportMember>,
+) -> MemberRegister {
+    let mut entries: Vec<MemberRegisterEntry> = members
+        .iter()
+        .filter_map(|m| Some((m, history.get(&m.member.id)?)))
+        .filter(|(_, h)| h.is_member_on(paivamaara))
+        .map(|(m, h)| MemberRegisterEntry {
+            jasen_id: m.member.id,
+            nimi: full_name(&m.member.etunimi, &m.member.sukunimi),
+            kotikunta: m.address.postitoimipaikka.trim().to_string(),
+            jasentyyppi: h.type_on(paivamaara).to_string(),
+            liittymispaiva: m.member.liittymispaiva,
+            henkilotunnus: if options.henkilotunnus {
+                m.member.henkilotunnus.clone()
+            } else {
+                None
+            },
+        })
+        .collect();
+
+    // Nimijärjestys sukunimen mukaan; yhteisön nimi on sukunimi-kentässä
+    let sort_name = |entry: &MemberRegisterEntry| {
+        let member = &members.iter().find(|m| m.member.id == entry.jasen_id).unwrap().member;
+        format!("{} {}", member.sukunimi, member.etunimi).to_lowercase()
+    };
+    entries.sort_by_cached_key(|e| {
+        let name = sort_name(e);
+        match options.jarjestys {
+            RegisterOrder::Nimi => (String::new(), None, name),
+            RegisterOrder::Kotikunta => (e.kotikunta.to_lowercase(), None, name),
+            RegisterOrder::Liittymispaiva => (String::new(), Some(e.liittymispaiva), name),
+        }
+    });
+
+    let jasenia = entries.len() as i64;
+    let mut ryhmat: Vec<MemberRegisterGroup> = Vec::new();
+    for entry in entries {
+        let nimi = match options.ryhmittely {
+            RegisterGrouping::Ei => String::new(),
+            RegisterGrouping::Jasentyyppi => entry.jasentyyppi.clone(),
+            RegisterGrouping::Kotikunta => entry.kotikunta.clone(),
+        };
+        match ryhmat.iter_mut().find(|r| r.nimi == nimi) {
+            Some(ryhma) => ryhma.jasenet.push(entry),
+            None => ryhmat.push(MemberRegisterGroup {
+                nimi,
+                jasenet: vec![entry],
+            }),
+        }
+    }
+    ryhmat.sort_by_key(|r| r.nimi.to_lowercase());
+
+    MemberRegister {
+        paivamaara,
+        ryhmittely: options.ryhmittely.clone(),
+        henkilotunnukset: options.henkilotunnus,
+        ryhmat,
+        jasenia,
+    }
+}
+
+// Aktiivinen jäsen ja hänen laskutusvuoden laskurivinsä äänioikeusluetteloa varten
+#[derive(Debug, Clone)]
+pub struct VotingMember {
+    pub jasen_id: i64,
+    pub nimi: String,
+    pub jasentyyppi: String,
+    pub laskutettava: bool,
+    pub maksettuja: i64,
+    pub maksamatta: i64,
+}
+
+/// Äänioikeusluettelo. Jäsenellä on äänioikeus, kun hänellä on vuoden laskuilla
+/// rivejä ja kaikki ne laskut on maksettu. Jäsentyypit, joita ei laskuteta,
+/// ovat äänioikeutettuja vain, jos `vapautetut` on valittu.
+pub fn build_voting_list(vuosi: i32, members: &[VotingMember], vapautetut: bool) -> VotingList {
+    let jasenet: Vec<VotingListEntry> = members
+        .iter()
+        .map(|m| {
+            let tila = if m.maksamatta > 0 {
+                VotingStatus::Maksamatta
+            } else if m.maksettuja > 0 {
+                VotingStatus::Maksettu
+            } else if !m.laskutettava {
+                VotingStatus::Vapautettu
+            } else {
+                VotingStatus::EiLaskutettu
+            };
+            let aanioikeus = tila == VotingStatus::Maksettu
+                || (vapautetut && tila == VotingStatus::Vapautettu);
+            VotingListEntry {
+                jasen_id: m.jasen_id,
+                nimi: m.nimi.clone(),
+                jasentyyppi: m.jasentyyppi.clone(),
+                tila,
+                aanioikeus,
+            }
+        })
+        .collect();
+
+    VotingList {
+        vuosi,
+        vapautetut,
+        aanioikeutettuja: jasenet.iter().filter(|j| j.aanioikeus).count() as i64,
+        jasenet,
+    }
+}
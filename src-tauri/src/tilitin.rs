@@ -0,0 +1,212 @@
+use crate::models::JournalVoucher;
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, NaiveTime};
+use sqlx::{Connection, Row, SqliteConnection};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+// Tilitin (Java) tallentaa päivämäärät millisekunteina paikallisesta
+// keskiyöstä. Puolen päivän lisäys lukiessa antaa oikean päivän aikavyöhykkeestä
+// riippumatta; kirjoitettaessa UTC-keskiyö on Suomessa saman päivän aamu.
+const HALF_DAY_MILLIS: i64 = 12 * 60 * 60 * 1000;
+
+fn date_to_millis(date: NaiveDate) -> i64 {
+    date.and_time(NaiveTime::MIN).and_utc().timestamp_millis()
+}
+
+fn date_from_row(row: &sqlx::sqlite::SqliteRow, column: &str) -> Result<NaiveDate> {
+    if let Ok(millis) = row.try_get::<i64, _>(column) {
+        return DateTime::from_timestamp_millis(millis + HALF_DAY_MILLIS)
+            .map(|d| d.date_naive())
+            .ok_or_else(|| anyhow::anyhow!("Virheellinen päivämäärä Tilitin-tietokannassa."));
+    }
+    let text: String = row.try_get(column)?;
+    NaiveDate::parse_from_str(text.get(..10).unwrap_or(&text), "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Virheellinen päivämäärä Tilitin-tietokannassa: {}", text))
+}
+
+// Tilitinin tilikausi
+#[derive(Debug, Clone)]
+pub struct Period {
+    pub id: i64,
+    pub alku: NaiveDate,
+    pub loppu: NaiveDate,
+    pub lukittu: bool,
+}
+
+/// Avaa olemassa olevan Tilitin-tietokannan ja tarkistaa, että siinä on
+/// Tilitinin taulut. Uutta tiedostoa ei luoda.
+pub async fn open(path: &str) -> Result<SqliteConnection> {
+    if !Path::new(path).exists() {
+        return Err(anyhow::anyhow!("Tilitin-tietokantaa ei löytynyt: {}", path));
+    }
+
+    let url = format!("sqlite:{}", path.replace('\\', "/"));
+    let mut conn = SqliteConnection::connect(&url).await?;
+
+    let tables: i64 = sqlx::query(
+        "SELECT COUNT(*) FROM sqlite_master
+         WHERE type = 'table' AND name IN ('account', 'period', 'document', 'entry')",
+    )
+    .fetch_one(&mut conn)
+    .await?
+    .get(0);
+    if tables != 4 {
+        return Err(anyhow::anyhow!(
+            "Tiedosto {} ei ole Tilitin-tietokanta.",
+            path
+        ));
+    }
+
+    Ok(conn)
+}
+
+/// Tilitin-tiedoston kanoninen polku, jolla viennit tunnistetaan. Suhteellinen
+/// polku ja symbolinen linkki johtavat samaan tiedostoon.
+pub fn canonical_path(path: &str) -> Result<String> {
+    let canonical = std::fs::canonicalize(path)
+        .map_err(|_| anyhow::anyhow!("Tilitin-tietokantaa ei löytynyt: {}", path))?;
+    Ok(canonical.to_string_lossy().into_owned())
+}
+
+/// Tilitinin tilit tilinumeron mukaan
+pub async fn accounts(conn: &mut SqliteConnection) -> Result<HashMap<String, i64>> {
+    let rows = sqlx::query("SELECT id, number FROM account")
+        .fetch_all(&mut *conn)
+        .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| (row.get::<String, _>("number"), row.get::<i64, _>("id")))
+        .collect())
+}
+
+pub async fn periods(conn: &mut SqliteConnection) -> Result<Vec<Period>> {
+    let rows =
+        sqlx::query("SELECT id, start_date, end_date, locked FROM period ORDER BY start_date")
+            .fetch_all(&mut *conn)
+            .await?;
+
+    rows.iter()
+        .map(|row| {
+            Ok(Period {
+                id: row.get("id"),
+                alku: date_from_row(row, "start_date")?,
+                loppu: date_from_row(row, "end_date")?,
+                lukittu: row.get("locked"),
+            })
+        })
+        .collect()
+}
+
+/// Tositteen tilikausi. Lukittuun tai puuttuvaan tilikauteen ei voi kirjata.
+pub fn period_for(periods: &[Period], date: NaiveDate) -> Result<&Period> {
+    let period = periods
+        .iter()
+        .find(|p| p.alku <= date && date <= p.loppu)
+        .ok_or_else(|| anyhow::anyhow!("Päivälle {} ei ole tilikautta Tilitinissä.", date))?;
+    if period.lukittu {
+        return Err(anyhow::anyhow!(
+            "Tilikausi {} – {} on lukittu Tilitinissä.",
+            period.alku,
+            period.loppu
+        ));
+    }
+    Ok(period)
+}
+
+/// Etsii tilikaudelta tositteen, joka vastaa kirjattavaa tositetta päivältä,
+/// selitteeltä ja vienneiltä. Jo tunnetut tositteet jätetään huomiotta.
+/// Palauttaa Tilitinin tositteen id:n ja tositenumeron.
+pub async fn find_voucher(
+    conn: &mut SqliteConnection,
+    period_id: i64,
+    voucher: &JournalVoucher,
+    known: &HashSet<i64>,
+) -> Result<Option<(i64, i64)>> {
+    let candidates = sqlx::query(
+        "SELECT d.id, d.number FROM document d
+         JOIN entry e ON e.document_id = d.id
+         WHERE d.period_id = ? AND d.date = ? AND e.row_number = 0 AND e.description = ?
+         ORDER BY d.number",
+    )
+    .bind(period_id)
+    .bind(date_to_millis(voucher.paivamaara))
+    .bind(&voucher.selite)
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for candidate in candidates {
+        let document_id: i64 = candidate.get("id");
+        if known.contains(&document_id) {
+            continue;
+        }
+
+        let entries = sqlx::query(
+            "SELECT debit, CAST(amount AS REAL) AS amount FROM entry
+             WHERE document_id = ? ORDER BY row_number",
+        )
+        .bind(document_id)
+        .fetch_all(&mut *conn)
+        .await?;
+        let matches = entries.len() == voucher.viennit.len()
+            && entries.iter().zip(&voucher.viennit).all(|(row, entry)| {
+                let debit: bool = row.get("debit");
+                let amount: f64 = row.get("amount");
+                let expected = if debit { entry.debet } else { entry.kredit };
+                debit == (entry.debet != 0.0) && (amount - expected).abs() < 0.005
+            });
+        if matches {
+            return Ok(Some((document_id, candidate.get("number"))));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Kirjaa tositteen Tilitiniin tilikauden seuraavalla tositenumerolla.
+/// Palauttaa Tilitinin tositteen id:n ja tositenumeron.
+pub async fn insert_voucher(
+    conn: &mut SqliteConnection,
+    period_id: i64,
+    voucher: &JournalVoucher,
+    accounts: &HashMap<String, i64>,
+) -> Result<(i64, i64)> {
+    let number: i64 =
+        sqlx::query("SELECT COALESCE(MAX(number), 0) + 1 FROM document WHERE period_id = ?")
+            .bind(period_id)
+            .fetch_one(&mut *conn)
+            .await?
+            .get(0);
+
+    let document_id =
+        sqlx::query("INSERT INTO document (number, period_id, date) VALUES (?, ?, ?)")
+            .bind(number)
+            .bind(period_id)
+            .bind(date_to_millis(voucher.paivamaara))
+            .execute(&mut *conn)
+            .await?
+            .last_insert_rowid();
+
+    for (row_number, entry) in voucher.viennit.iter().enumerate() {
+        let account_id = accounts.get(&entry.tili).ok_or_else(|| {
+            anyhow::anyhow!("Tiliä {} ei löydy Tilitin-tietokannasta.", entry.tili)
+        })?;
+        let debit = entry.debet != 0.0;
+
+        sqlx::query(
+            "INSERT INTO entry (document_id, account_id, debit, amount, description, row_number, flags)
+             VALUES (?, ?, ?, ?, ?, ?, 0)",
+        )
+        .bind(document_id)
+        .bind(account_id)
+        .bind(debit)
+        .bind(if debit { entry.debet } else { entry.kredit })
+        .bind(&voucher.selite)
+        .bind(row_number as i64)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok((document_id, number))
+}
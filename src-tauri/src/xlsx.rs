@@ -0,0 +1,232 @@
+// Yksinkertainen XLSX-kirjoitin yhden taulukon työkirjoille. XLSX on
+// ZIP-paketti XML-tiedostoja; tiedostot tallennetaan pakkaamattomina, joten
+// erillistä ZIP-kirjastoa ei tarvita. Merkkijonot kirjoitetaan soluihin
+// suoraan (inlineStr) ja otsikkorivi lihavoidaan.
+
+pub enum Cell {
+    Text(String),
+    Number(f64),
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+// ZIP-paketti ilman pakkausta. Aikaleimana on 1.1.1980.
+fn zip(files: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    const DOS_DATE: u16 = 0x0021;
+    let mut out = Vec::new();
+    let mut central = Vec::new();
+
+    for (name, data) in files {
+        let offset = out.len() as u32;
+        let crc = crc32(data);
+        let size = data.len() as u32;
+
+        let mut local = 0x0403_4b50u32.to_le_bytes().to_vec();
+        local.extend_from_slice(&20u16.to_le_bytes()); // versio
+        local.extend_from_slice(&0u16.to_le_bytes()); // liput
+        local.extend_from_slice(&0u16.to_le_bytes()); // ei pakkausta
+        local.extend_from_slice(&0u16.to_le_bytes()); // aika
+        local.extend_from_slice(&DOS_DATE.to_le_bytes());
+        local.extend_from_slice(&crc.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes());
+        local.extend_from_slice(&size.to_le_bytes());
+        local.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        local.extend_from_slice(&0u16.to_le_bytes());
+        local.extend_from_slice(name.as_bytes());
+        out.extend_from_slice(&local);
+        out.extend_from_slice(data);
+
+        let mut entry = 0x0201_4b50u32.to_le_bytes().to_vec();
+        entry.extend_from_slice(&20u16.to_le_bytes()); // tekijän versio
+        entry.extend_from_slice(&20u16.to_le_bytes()); // tarvittava versio
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(&0u16.to_le_bytes());
+        entry.extend_from_slice(&DOS_DATE.to_le_bytes());
+        entry.extend_from_slice(&crc.to_le_bytes());
+        entry.extend_from_slice(&size.to_le_bytes());
+        entry.extend_from_slice(&size.to_le_bytes());
+        entry.extend_from_slice(&(name.len() as u16).to_le_bytes());
+        entry.extend_from_slice(&[0; 12]); // lisäkenttä, kommentti, levy, attribuutit
+        entry.extend_from_slice(&offset.to_le_bytes());
+        entry.extend_from_slice(name.as_bytes());
+        central.extend_from_slice(&entry);
+    }
+
+    let central_offset = out.len() as u32;
+    out.extend_from_slice(&central);
+    out.extend_from_slice(&0x0605_4b50u32.to_le_bytes());
+    out.extend_from_slice(&[0; 4]); // levyt
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(files.len() as u16).to_le_bytes());
+    out.extend_from_slice(&(central.len() as u32).to_le_bytes());
+    out.extend_from_slice(&central_offset.to_le_bytes());
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\t' | '\n' | '\r'))
+        .collect::<String>()
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Sarakkeen kirjaintunnus: 0 -> A, 25 -> Z, 26 -> AA
+fn column_name(mut index: usize) -> String {
+    let mut name = Vec::new();
+    loop {
+        name.push(b'A' + (index % 26) as u8);
+        if index < 26 {
+            break;
+        }
+        index = index / 26 - 1;
+    }
+    name.reverse();
+    String::from_utf8(name).unwrap_or_default()
+}
+
+fn cell_xml(reference: &str, cell: &Cell, style: u8) -> String {
+    match cell {
+        Cell::Number(value) if value.is_finite() => {
+            format!(
+                "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+                reference, style, value
+            )
+        }
+        Cell::Number(_) => String::new(),
+        Cell::Text(text) => format!(
+            "<c r=\"{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+            reference,
+            style,
+            escape_xml(text)
+        ),
+    }
+}
+
+/// Työkirja, jossa on yksi taulukko otsikkorivillä. Taulukon nimi lyhennetään
+/// Excelin 31 merkin rajaan.
+pub fn workbook(sheet_name: &str, headers: &[String], rows: &[Vec<Cell>]) -> Vec<u8> {
+    let sheet_name: String = sheet_name
+        .chars()
+        .filter(|c| !matches!(c, '[' | ']' | ':' | '*' | '?' | '/' | '\\'))
+        .take(31)
+        .collect();
+    let sheet_name = if sheet_name.trim().is_empty() {
+        "Raportti".to_string()
+    } else {
+        sheet_name
+    };
+
+    let mut sheet = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\"><sheetData>",
+    );
+    let header_cells: Vec<Cell> = headers.iter().map(|h| Cell::Text(h.clone())).collect();
+    for (r, (row, style)) in std::iter::once((&header_cells, 1))
+        .chain(rows.iter().map(|row| (row, 0)))
+        .enumerate()
+    {
+        sheet.push_str(&format!("<row r=\"{}\">", r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            sheet.push_str(&cell_xml(
+                &format!("{}{}", column_name(c), r + 1),
+                cell,
+                style,
+            ));
+        }
+        sheet.push_str("</row>");
+    }
+    sheet.push_str("</sheetData></worksheet>");
+
+    let content_types = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+        <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+        <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+        <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+        <Override PartName=\"/xl/worksheets/sheet1.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>\
+        <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>\
+        </Types>";
+    let root_rels = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+        <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+        </Relationships>";
+    let workbook = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+         <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" \
+         xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+         <sheets><sheet name=\"{}\" sheetId=\"1\" r:id=\"rId1\"/></sheets></workbook>",
+        escape_xml(&sheet_name)
+    );
+    let workbook_rels = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+        <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet1.xml\"/>\
+        <Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>\
+        </Relationships>";
+    let styles = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\
+        <styleSheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+        <fonts count=\"2\"><font><sz val=\"11\"/><name val=\"Calibri\"/></font>\
+        <font><b/><sz val=\"11\"/><name val=\"Calibri\"/></font></fonts>\
+        <fills count=\"2\"><fill><patternFill patternType=\"none\"/></fill>\
+        <fill><patternFill patternType=\"gray125\"/></fill></fills>\
+        <borders count=\"1\"><border><left/><right/><top/><bottom/><diagonal/></border></borders>\
+        <cellStyleXfs count=\"1\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\"/></cellStyleXfs>\
+        <cellXfs count=\"2\"><xf numFmtId=\"0\" fontId=\"0\" fillId=\"0\" borderId=\"0\" xfId=\"0\"/>\
+        <xf numFmtId=\"0\" fontId=\"1\" fillId=\"0\" borderId=\"0\" xfId=\"0\" applyFont=\"1\"/></cellXfs>\
+        </styleSheet>";
+
+    zip(&[
+        ("[Content_Types].xml", content_types.as_bytes().to_vec()),
+        ("_rels/.rels", root_rels.as_bytes().to_vec()),
+        ("xl/workbook.xml", workbook.into_bytes()),
+        (
+            "xl/_rels/workbook.xml.rels",
+            workbook_rels.as_bytes().to_vec(),
+        ),
+        ("xl/worksheets/sheet1.xml", sheet.into_bytes()),
+        ("xl/styles.xml", styles.as_bytes().to_vec()),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn column_name_continues_after_z() {
+        assert_eq!(column_name(0), "A");
+        assert_eq!(column_name(25), "Z");
+        assert_eq!(column_name(26), "AA");
+        assert_eq!(column_name(701), "ZZ");
+        assert_eq!(column_name(702), "AAA");
+    }
+
+    #[test]
+    fn crc32_matches_check_value() {
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn escape_xml_escapes_markup_and_drops_control_characters() {
+        assert_eq!(
+            escape_xml("<a href=\"x\">&</a>"),
+            "&lt;a href=&quot;x&quot;&gt;&amp;&lt;/a&gt;"
+        );
+        assert_eq!(escape_xml("rivi\u{1}\n\tsarake\u{b}"), "rivi\n\tsarake");
+    }
+}
@@ -16,6 +16,7 @@ import Households from "./components/Households.vue";
 import Invoices from "./components/Invoices.vue";
 import MembershipFees from "./components/MembershipFees.vue";
 import OrganizationSettings from "./components/OrganizationSettings.vue";
+import Reports from "./components/Reports.vue";
 
 const routes = [
   { path: "/", component: Dashboard, name: "dashboard" },
@@ -23,6 +24,7 @@ const routes = [
   { path: "/households", component: Households, name: "households" },
   { path: "/invoices", component: Invoices, name: "invoices" },
   { path: "/fees", component: MembershipFees, name: "fees" },
+  { path: "/reports", component: Reports, name: "reports" },
   { path: "/settings", component: OrganizationSettings, name: "settings" },
 ];
 
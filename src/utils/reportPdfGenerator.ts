@@ -0,0 +1,129 @@
+import jsPDF from 'jspdf'
+
+// Taulukkomuotoiset raportit (tiliote, listat). Sarakkeiden leveydet ovat
+// millimetreinä; yhteensä niiden on mahduttava sivun leveyteen marginaalien sisään.
+export interface ReportColumn {
+  header: string
+  width: number
+  align?: 'left' | 'right'
+}
+
+export interface ReportPdfData {
+  title: string
+  subtitle?: string[]
+  organization: any
+  columns: ReportColumn[]
+  rows: string[][]
+  // Yhteenvetorivit taulukon alle, esim. [['Saldo', '40,00 €']]
+  summary?: [string, string][]
+  landscape?: boolean
+}
+
+const margin = 15
+const rowHeight = 6
+
+const createReportPDF = (data: ReportPdfData): jsPDF => {
+  const pdf = new jsPDF(data.landscape ? 'l' : 'p', 'mm', 'a4')
+  const pageWidth = pdf.internal.pageSize.getWidth()
+  const pageHeight = pdf.internal.pageSize.getHeight()
+  let yPos = margin
+
+  // Otsikko: yhdistys vasemmalle, raportin nimi oikealle
+  pdf.setFontSize(14)
+  pdf.setFont('helvetica', 'bold')
+  pdf.text(data.organization?.nimi || 'Yhdistys', margin, yPos)
+  pdf.text(data.title, pageWidth - margin, yPos, { align: 'right' })
+  yPos += 6
+
+  pdf.setFontSize(10)
+  pdf.setFont('helvetica', 'normal')
+  for (const line of data.subtitle || []) {
+    pdf.text(line, margin, yPos)
+    yPos += 5
+  }
+  yPos += 4
+
+  const drawHeader = () => {
+    pdf.setFont('helvetica', 'bold')
+    let x = margin
+    for (const column of data.columns) {
+      const textX = column.align === 'right' ? x + column.width - 1 : x + 1
+      pdf.text(column.header, textX, yPos, { align: column.align === 'right' ? 'right' : 'left' })
+      x += column.width
+    }
+    yPos += 2
+    pdf.setLineWidth(0.3)
+    pdf.line(margin, yPos, pageWidth - margin, yPos)
+    yPos += rowHeight - 1
+    pdf.setFont('helvetica', 'normal')
+  }
+
+  pdf.setFontSize(9)
+  drawHeader()
+
+  for (const row of data.rows) {
+    if (yPos > pageHeight - margin - rowHeight) {
+      pdf.addPage()
+      yPos = margin
+      drawHeader()
+    }
+    let x = margin
+    data.columns.forEach((column, i) => {
+      const text = pdf.splitTextToSize(row[i] ?? '', column.width - 2)[0] || ''
+      const textX = column.align === 'right' ? x + column.width - 1 : x + 1
+      pdf.text(text, textX, yPos, { align: column.align === 'right' ? 'right' : 'left' })
+      x += column.width
+    })
+    yPos += rowHeight
+  }
+
+  if (data.summary?.length) {
+    if (yPos > pageHeight - margin - rowHeight * (data.summary.length + 1)) {
+      pdf.addPage()
+      yPos = margin
+    }
+    pdf.setLineWidth(0.3)
+    pdf.line(margin, yPos - rowHeight + 2, pageWidth - margin, yPos - rowHeight + 2)
+    yPos += 2
+    pdf.setFontSize(10)
+    for (const [label, value] of data.summary) {
+      pdf.setFont('helvetica', 'bold')
+      pdf.text(label, margin + 1, yPos)
+      pdf.setFont('helvetica', 'normal')
+      pdf.text(value, pageWidth - margin - 1, yPos, { align: 'right' })
+      yPos += rowHeight
+    }
+  }
+
+  // Sivunumerot ja tulostuspäivä alatunnisteeseen
+  const pages = pdf.getNumberOfPages()
+  const printed = new Date().toLocaleDateString('fi-FI')
+  pdf.setFontSize(8)
+  for (let page = 1; page <= pages; page++) {
+    pdf.setPage(page)
+    pdf.text(`Tulostettu ${printed}`, margin, pageHeight - 8)
+    pdf.text(`Sivu ${page}/${pages}`, pageWidth - margin, pageHeight - 8, { align: 'right' })
+  }
+
+  return pdf
+}
+
+export const generateReportPDF = async (data: ReportPdfData, defaultFilename: string): Promise<boolean> => {
+  try {
+    const { invoke } = await import('@tauri-apps/api/core')
+    const savePath = await invoke('show_save_dialog', { defaultFilename })
+
+    if (!savePath) return false
+
+    const pdf = createReportPDF(data)
+    const uint8Array = Array.from(new Uint8Array(pdf.output('arraybuffer')))
+    await invoke('save_pdf_file', { filePath: savePath, data: uint8Array })
+    return true
+  } catch (error) {
+    console.error('Report PDF generation failed:', error)
+    throw new Error(`PDF-raportin luonti epäonnistui: ${error instanceof Error ? error.message : String(error)}`)
+  }
+}
+
+export const formatAmount = (value: number): string =>
+  `${value.toFixed(2).replace('.', ',')} €`
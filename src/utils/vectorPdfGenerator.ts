@@ -66,15 +66,49 @@ const createInvoicePDF = (data: VectorInvoiceData): jsPDF => {
 
   // Recipient info without box
   pdf.setFont('helvetica', 'bold')
-  pdf.text(invoice?.household?.vastaanottaja || invoice?.household?.talouden_nimi || 'Nimetön', margin, yPos)
+  // Huoltajalle ohjatun laskun saaja ja osoite ovat huoltajan, asiakaslaskun asiakkaan
+  const payer = invoice?.customer || invoice?.payer
+  const recipient = payer
+    ? payer.nimi
+    : invoice?.member
+      ? `${invoice.member.etunimi} ${invoice.member.sukunimi}`
+      : invoice?.household?.vastaanottaja || invoice?.household?.talouden_nimi
+  const recipientAddress = payer || invoice?.address
+  pdf.text(recipient || 'Nimetön', margin, yPos)
   yPos += 5
   pdf.setFont('helvetica', 'normal')
-  if (invoice?.address?.katuosoite) {
-    pdf.text(invoice.address.katuosoite, margin, yPos)
+  if (invoice?.customer?.yhteyshenkilo) {
+    pdf.text(invoice.customer.yhteyshenkilo, margin, yPos)
     yPos += 5
   }
-  if (invoice?.address?.postinumero && invoice?.address?.postitoimipaikka) {
-    pdf.text(`${invoice.address.postinumero} ${invoice.address.postitoimipaikka}`, margin, yPos)
+  if (recipientAddress?.katuosoite) {
+    pdf.text(recipientAddress.katuosoite, margin, yPos)
+    yPos += 5
+  }
+  if (recipientAddress?.postinumero && recipientAddress?.postitoimipaikka) {
+    pdf.text(`${recipientAddress.postinumero} ${recipientAddress.postitoimipaikka}`, margin, yPos)
+    yPos += 5
+  }
+  if (invoice?.customer?.y_tunnus) {
+    pdf.text(`Y-tunnus: ${invoice.customer.y_tunnus}`, margin, yPos)
+    yPos += 5
+  }
+  // Yhteisöjäsenen laskulle sen y-tunnus ja laskun viite
+  const corporateMember = invoice?.customer || invoice?.payer
+    ? null
+    : invoice?.member?.jasenlaji === 'yhteiso'
+      ? invoice.member
+      : invoice?.lines?.find((l: any) => l.member?.jasenlaji === 'yhteiso')?.member
+  if (corporateMember?.yhteyshenkilo) {
+    pdf.text(`Yhteyshenkilö: ${corporateMember.yhteyshenkilo}`, margin, yPos)
+    yPos += 5
+  }
+  if (corporateMember?.y_tunnus) {
+    pdf.text(`Y-tunnus: ${corporateMember.y_tunnus}`, margin, yPos)
+    yPos += 5
+  }
+  if (corporateMember?.laskuviite) {
+    pdf.text(`Viitteenne: ${corporateMember.laskuviite}`, margin, yPos)
     yPos += 5
   }
 
@@ -136,7 +170,7 @@ const createInvoicePDF = (data: VectorInvoiceData): jsPDF => {
     pdf.setFont('helvetica', 'normal')
     for (const line of invoice.lines) {
       pdf.text(line.line.kuvaus || '', colPositions[0] + 2, tableRowY + 6)
-      pdf.text(`${line.member.etunimi} ${line.member.sukunimi}`, colPositions[1] + 2, tableRowY + 6)
+      pdf.text(line.member ? `${line.member.etunimi} ${line.member.sukunimi}` : '', colPositions[1] + 2, tableRowY + 6)
       const amount = line.line.summa || 0
       pdf.text(`${amount.toFixed(2)} €`, colPositions[2] + 2, tableRowY + 6)
 
@@ -156,6 +190,14 @@ const createInvoicePDF = (data: VectorInvoiceData): jsPDF => {
 
   yPos = tableRowY + 20
 
+  // Free-text message
+  if (invoice?.viesti) {
+    pdf.setFont('helvetica', 'normal')
+    const messageLines = pdf.splitTextToSize(invoice.viesti, 170)
+    pdf.text(messageLines, margin, yPos)
+    yPos += messageLines.length * 6 + 6
+  }
+
   // Payment info
   pdf.setFont('helvetica', 'bold')
   pdf.text('Maksutiedot', margin, yPos)
@@ -219,12 +261,13 @@ export const generatePrintablePDF = async (data: VectorInvoiceData): Promise<Arr
   return pdf.output('arraybuffer')
 }
 
-export const generateVectorInvoicePDF = async (data: VectorInvoiceData, defaultFilename: string): Promise<void> => {
+// Palauttaa false, jos käyttäjä perui tallennuksen
+export const generateVectorInvoicePDF = async (data: VectorInvoiceData, defaultFilename: string): Promise<boolean> => {
   try {
     const { invoke } = await import('@tauri-apps/api/core')
     const savePath = await invoke('show_save_dialog', { defaultFilename })
 
-    if (!savePath) return
+    if (!savePath) return false
 
     const pdf = createInvoicePDF(data)
 
@@ -232,7 +275,7 @@ export const generateVectorInvoicePDF = async (data: VectorInvoiceData, defaultF
     const pdfData = pdf.output('arraybuffer')
     const uint8Array = Array.from(new Uint8Array(pdfData))
     await invoke('save_pdf_file', { filePath: savePath, data: uint8Array })
-
+    return true
   } catch (error) {
     console.error('Vector PDF generation failed:', error)
     throw new Error(`Vector PDF luonti epäonnistui: ${error instanceof Error ? error.message : String(error)}`)